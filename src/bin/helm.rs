@@ -1,39 +1,96 @@
 #[cfg(feature = "gpu")]
 use concrete_core::prelude::*;
-use debug_print::debug_println;
-use helm::{ascii, circuit, circuit::EvalCircuit, verilog_parser};
+use helm::{
+    ascii, circuit, circuit::EvalCircuit, gates::GateType, info_println, manifest, verilog_parser,
+    PtxtType,
+};
+use itertools::Itertools;
 #[cfg(feature = "gpu")]
 use rand::rngs::OsRng;
 #[cfg(feature = "gpu")]
 use rand::RngCore;
-use std::time::Instant;
-use termion::color;
-use tfhe::{
-    boolean::gen_keys, generate_keys, shortint::parameters::PARAM_MESSAGE_1_CARRY_1_KS_PBS,
-    ConfigBuilder,
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
 };
+use termion::color;
 
 fn main() {
-    ascii::print_art();
+    let run_start = Instant::now();
     let matches = helm::parse_args();
+    helm::set_quiet(matches.get_flag("quiet"));
+    if !helm::is_quiet() {
+        ascii::print_art();
+    }
+    if matches.get_flag("list-gate-types") {
+        for cell in verilog_parser::GATE_CELLS {
+            println!("{:<6} {:<26} {}", cell.keyword, cell.pins, cell.function);
+        }
+        return;
+    }
     let file_name = matches
         .get_one::<String>("verilog")
         .expect("Verilog input file is required");
-    let num_cycles = *matches.get_one::<usize>("cycles").expect("required");
+    // Checked before anything else reads the netlist and panics on errors.
+    if matches.get_flag("check-only") {
+        match verilog_parser::check_verilog_file(
+            file_name,
+            matches.get_one::<String>("arithmetic").is_some(),
+        ) {
+            Ok(()) => {
+                info_println!(
+                    "{}[✓]{} {} parsed without errors.",
+                    color::Fg(color::LightGreen),
+                    color::Fg(color::Reset),
+                    file_name
+                );
+                return;
+            }
+            Err(error) => {
+                eprintln!(
+                    "{}[!]{} {}",
+                    color::Fg(color::LightRed),
+                    color::Fg(color::Reset),
+                    error
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
     let verbose = matches.get_flag("verbose");
     #[cfg(feature = "gpu")]
     let gpu_eval = matches.get_flag("gpu");
     #[cfg(not(feature = "gpu"))]
     let gpu_eval = false;
     let inputs_filename = matches.get_one::<String>("input-wires-file").cloned();
+    let input_binary_file = matches.get_one::<String>("input-binary");
+    let output_binary_file = matches.get_one::<String>("output-binary");
     let outputs_filename = matches.get_one::<String>("output-wires-file").cloned();
-    let arithmetic = matches.get_one::<String>("arithmetic");
-
-    // TODO: Add support for this.
-    // If it's arithmetic and the num_cycles variable has been set
-    if arithmetic.is_some() && num_cycles > 1 {
-        panic!("Arithmetic does not currently support sequential. Set num_cycles to 1.");
-    }
+    let hex_output = matches.get_flag("hex-output");
+    let state_trace_file = matches.get_one::<String>("state-trace");
+    let batch_vectors_file = matches.get_one::<String>("batch-vectors");
+    let stream_outputs_file = matches.get_one::<String>("stream-outputs");
+    let checkpoints = matches
+        .get_many::<String>("checkpoint")
+        .unwrap_or_default()
+        .cloned()
+        .collect::<Vec<_>>();
+    let trivial_inputs = matches
+        .get_many::<String>("trivial-input")
+        .unwrap_or_default()
+        .cloned()
+        .collect::<Vec<_>>();
+    let default_input = matches.get_one::<String>("default-input");
+    let input_aliases = matches
+        .get_one::<String>("wire-aliases")
+        .map(|file| verilog_parser::read_wire_aliases(file))
+        .unwrap_or_default();
+    let output_aliases = input_aliases
+        .iter()
+        .map(|(file_name, netlist_name)| (netlist_name.clone(), file_name.clone()))
+        .collect::<HashMap<_, _>>();
+    let arithmetic = helm::get_arithmetic_type(matches.get_one::<String>("arithmetic"), file_name);
 
     let wire_inputs = if let Some(occurrences) = matches.get_occurrences("input-wires") {
         occurrences
@@ -42,11 +99,91 @@ fn main() {
     } else {
         vec![]
     };
+    for wire in helm::duplicate_input_wires(&wire_inputs) {
+        if matches.get_flag("dedup-inputs") {
+            info_println!(
+                "{}[!]{} Input wire \"{}\" is given more than once, keeping the last value.",
+                color::Fg(color::LightYellow),
+                color::Fg(color::Reset),
+                wire
+            );
+        } else {
+            panic!(
+                "{}[!]{} Input wire \"{}\" is given more than once (pass --dedup-inputs to keep \
+                    the last value).",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                wire
+            );
+        }
+    }
+    let read_input_wire_map = |arithmetic_type: &str| match input_binary_file {
+        Some(binary_file) => verilog_parser::read_binary_wires(binary_file, arithmetic_type),
+        None => helm::get_input_wire_map(
+            inputs_filename.clone(),
+            wire_inputs.clone(),
+            arithmetic_type,
+        ),
+    };
 
-    let (gates_set, wire_set, input_wires, output_wires, dff_outputs, has_luts, _) =
-        verilog_parser::read_verilog_file(file_name, arithmetic.is_some());
+    let (
+        mut gates_set,
+        mut wire_set,
+        mut input_wires,
+        mut output_wires,
+        mut dff_outputs,
+        has_luts,
+        _,
+    ) = verilog_parser::read_verilog_file(file_name, arithmetic.is_some());
+    if let Some(constraints_file) = matches.get_one::<String>("constraints") {
+        if arithmetic.is_some() {
+            panic!(
+                "{}[!]{} Case analysis constants only apply to boolean circuits.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        gates_set = circuit::tie_inputs(
+            gates_set,
+            &mut input_wires,
+            &mut wire_set,
+            &verilog_parser::read_constraints(constraints_file),
+        );
+    }
+    if let Some(patch_file) = matches.get_one::<String>("patch") {
+        gates_set = circuit::apply_patch(
+            gates_set,
+            &mut input_wires,
+            &wire_set,
+            &verilog_parser::read_patch(patch_file),
+        );
+    }
+    // The combinational cut is evaluated once, whatever the netlist asks for.
+    let num_cycles = if matches.get_flag("only-combinational") {
+        let netlist_cycles = helm::get_cycle_count(None, file_name);
+        if netlist_cycles > 1 {
+            info_println!(
+                "{}[!]{} Ignoring the {} cycles set by the netlist: --only-combinational \
+                    evaluates one cycle.",
+                color::Fg(color::LightYellow),
+                color::Fg(color::Reset),
+                netlist_cycles
+            );
+        }
+        1
+    } else {
+        helm::get_cycle_count(matches.get_one::<usize>("cycles").copied(), file_name)
+    };
+    if matches.get_flag("only-combinational") {
+        gates_set = circuit::cut_at_registers(
+            gates_set,
+            &mut input_wires,
+            &mut output_wires,
+            &mut dff_outputs,
+        );
+    }
 
-    let is_sequential = dff_outputs.len() > 1;
+    let is_sequential = !dff_outputs.is_empty();
     if num_cycles > 1 && !is_sequential {
         panic!(
             "{}[!]{} Cannot run combinational circuit for more than one cycles.",
@@ -55,17 +192,689 @@ fn main() {
         );
     }
 
+    // Arithmetic mode evaluates on integers even without arithmetic gates.
+    let key_set = match arithmetic {
+        Some(_) => circuit::KeySet::Integer,
+        None => circuit::KeySet::from_gates(&gates_set),
+    };
+    if key_set == circuit::KeySet::Integer && arithmetic.is_none() {
+        panic!(
+            "{}[!]{} Arithmetic operators need an arithmetic type: pass --arithmetic or set the \
+                helm_arith attribute.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset)
+        );
+    }
+
+    if batch_vectors_file.is_some()
+        && (key_set != circuit::KeySet::Boolean || gpu_eval || matches.get_flag("lazy-encryption"))
+    {
+        panic!(
+            "{}[!]{} Batched vectors are only evaluated in gates mode on the CPU, with all inputs \
+                encrypted upfront.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset)
+        );
+    }
+
+    let checkpoints = checkpoints
+        .iter()
+        .map(|wire| helm::rename_wire(wire, &input_aliases))
+        .collect::<Vec<_>>();
+    if let Some(wire) = checkpoints.iter().find(|wire| !wire_set.contains(*wire)) {
+        panic!(
+            "{}[!]{} Checkpoint \"{}\" is not a wire of the netlist.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            wire
+        );
+    }
+    if !checkpoints.is_empty() {
+        if key_set != circuit::KeySet::Boolean || gpu_eval {
+            panic!(
+                "{}[!]{} Checkpoints are only decrypted in gates mode on the CPU.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        eprintln!(
+            "{}[!]{} Checkpoints are decrypted with the client key during the evaluation, which \
+                leaks their values. Only use them for debugging.",
+            color::Fg(color::LightYellow),
+            color::Fg(color::Reset)
+        );
+    }
+
+    let trivial_inputs = trivial_inputs
+        .iter()
+        .map(|wire| helm::rename_wire(wire, &input_aliases))
+        .collect::<Vec<_>>();
+    if let Some(wire) = trivial_inputs
+        .iter()
+        .find(|wire| !input_wires.contains(*wire))
+    {
+        panic!(
+            "{}[!]{} Trivial input \"{}\" is not an input wire of the netlist.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            wire
+        );
+    }
+    if !trivial_inputs.is_empty() {
+        if key_set != circuit::KeySet::Boolean || gpu_eval {
+            panic!(
+                "{}[!]{} Trivial inputs are only supported in gates mode on the CPU.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        eprintln!(
+            "{}[!]{} Trivial inputs are not encrypted at all, so the server can read them. Only \
+                use them for public constants.",
+            color::Fg(color::LightYellow),
+            color::Fg(color::Reset)
+        );
+    }
+
+    if stream_outputs_file.is_some() && (key_set != circuit::KeySet::Boolean || gpu_eval) {
+        panic!(
+            "{}[!]{} Outputs are only streamed in gates mode on the CPU.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset)
+        );
+    }
+
+    if matches.get_flag("lazy-encryption") && (key_set != circuit::KeySet::Boolean || gpu_eval) {
+        panic!(
+            "{}[!]{} Lazy encryption is only supported in gates mode on the CPU.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset)
+        );
+    }
+
+    if matches.get_flag("evaluate-twice-check") && gpu_eval {
+        panic!(
+            "{}[!]{} The evaluate-twice check does not run on the GPU.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset)
+        );
+    }
+
+    let output_order = if matches.get_flag("sort-outputs") {
+        vec![]
+    } else {
+        output_wires
+            .iter()
+            .map(|wire| helm::rename_wire(wire, &output_aliases))
+            .collect::<Vec<_>>()
+    };
+
+    // Unknown (x) constants have no ciphertext, only a plaintext value.
+    let has_unknown_constants = gates_set
+        .iter()
+        .any(|gate| gate.get_gate_type() == GateType::ConstUnknown);
     let mut circuit_ptxt =
         circuit::Circuit::new(gates_set, &input_wires, &output_wires, &dff_outputs);
+    circuit_ptxt.set_register_init_values(verilog_parser::read_register_init_values(file_name));
+    if let Some(module_name) = verilog_parser::read_module_name(file_name) {
+        circuit_ptxt.set_module_name(&module_name);
+    }
+    if matches.get_flag("validate-widths") {
+        if arithmetic.is_none() {
+            panic!(
+                "{}[!]{} Widths are only validated in arithmetic mode.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        if let Err(error) = circuit_ptxt.check_widths(&verilog_parser::read_wire_widths(file_name))
+        {
+            panic!(
+                "{}[!]{} {}.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                error
+            );
+        }
+    }
+    if let Some(budget) = matches.get_one::<usize>("bootstrap-budget") {
+        if key_set != circuit::KeySet::Boolean || gpu_eval {
+            panic!(
+                "{}[!]{} The bootstrap budget is only supported in gates mode on the CPU.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        circuit_ptxt.set_bootstrap_budget(*budget);
+    }
+    if let Some(seconds) = matches.get_one::<f64>("time-limit-per-gate") {
+        if !seconds.is_finite() || *seconds <= 0.0 {
+            panic!(
+                "{}[!]{} The gate time limit must be a positive number of seconds.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        if key_set == circuit::KeySet::Integer || gpu_eval {
+            panic!(
+                "{}[!]{} The gate time limit is only supported in gates and LUTs modes on the CPU.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        circuit_ptxt.set_time_limit_per_gate(Duration::from_secs_f64(*seconds));
+    }
+    circuit_ptxt.set_work_stealing(matches.get_flag("work-stealing"));
+    // A schedule names the gates as parsed, so it skips the rewrites.
+    let schedule_file = matches.get_one::<String>("schedule");
+    if !matches.get_flag("no-optimize") && schedule_file.is_none() {
+        circuit_ptxt.optimize();
+    }
 
     circuit_ptxt.sort_circuit();
     circuit_ptxt.compute_levels();
+    if let Some(schedule_file) = schedule_file {
+        let schedule = verilog_parser::read_schedule(schedule_file);
+        if let Err(error) = circuit_ptxt.set_schedule(&schedule) {
+            panic!(
+                "{}[!]{} {}.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                error
+            );
+        }
+    }
+    if verbose {
+        info_println!("{}\n", circuit_ptxt);
+    }
+    let gate_count = circuit_ptxt.gate_count();
+    let bootstrap_count = circuit_ptxt.count_bootstraps();
+    let level_count = circuit_ptxt.depth();
+    // Time spent in the encrypted evaluation, across cycles.
+    let mut eval_seconds = 0.0;
     #[cfg(debug_assertions)]
-    circuit_ptxt.print_level_map();
-    debug_println!();
+    {
+        circuit_ptxt.print_level_map();
+        info_println!();
+    }
+
+    if let Some(max_depth) = matches.get_one::<usize>("max-depth") {
+        if let Err(error) = circuit_ptxt.check_max_depth(*max_depth) {
+            panic!(
+                "{}[!]{} {}.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                error
+            );
+        }
+    }
+
+    if matches.get_flag("dump-gate-truthtables") {
+        circuit_ptxt.print_gate_truth_tables();
+        return;
+    }
+
+    if matches.get_flag("count-critical-bootstraps") {
+        info_println!(
+            "{}[✓]{} Bootstraps: {} total, {} on the critical path.",
+            color::Fg(color::LightGreen),
+            color::Fg(color::Reset),
+            circuit_ptxt.count_bootstraps(),
+            circuit_ptxt.count_critical_bootstraps()
+        );
+        return;
+    }
+
+    if matches.get_flag("sweep-widths") {
+        let Some(arithmetic_type) = &arithmetic else {
+            panic!(
+                "{}[!]{} --sweep-widths needs an arithmetic circuit.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        };
+        let input_wire_map = read_input_wire_map(arithmetic_type);
+        let input_wire_map = helm::rename_wires(input_wire_map, &input_aliases);
+        let input_wire_map = helm::fill_unspecified_inputs(
+            input_wire_map,
+            &input_wires,
+            &dff_outputs,
+            default_input.map_or("0", String::as_str),
+            arithmetic_type,
+        );
 
-    if let Some(arithmetic_type) = arithmetic {
-        println!(
+        let widths = ["u8", "u16", "u32", "u64", "u128", "u256"];
+        let mismatches = circuit_ptxt.sweep_widths(&input_wire_map, &widths);
+        for mismatch in mismatches.iter() {
+            info_println!(
+                " {} at {}: {} (expected {})",
+                mismatch.wire,
+                mismatch.width,
+                mismatch.actual,
+                mismatch.expected
+            );
+        }
+        if mismatches.is_empty() {
+            info_println!(
+                "{}[✓]{} Outputs agree across {}.",
+                color::Fg(color::LightGreen),
+                color::Fg(color::Reset),
+                widths.join(", ")
+            );
+        } else {
+            info_println!(
+                "{}[!]{} {} outputs differ from their {} value.",
+                color::Fg(color::LightYellow),
+                color::Fg(color::Reset),
+                mismatches.len(),
+                widths[0]
+            );
+        }
+        return;
+    }
+
+    if let Some(reset_wire) = matches.get_one::<String>("test-reset") {
+        if arithmetic.is_some() {
+            panic!(
+                "{}[!]{} Reset testing is only supported in gates and LUTs modes.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        let input_wire_map = read_input_wire_map("bool");
+        let mut input_wire_map = helm::rename_wires(input_wire_map, &input_aliases);
+        input_wire_map.remove("dummy");
+
+        let trials = 8;
+        let violations = circuit_ptxt.test_reset(reset_wire, &input_wire_map, trials);
+        for violation in violations.iter() {
+            info_println!(
+                " {}: {} after reset from random state {} (expected {})",
+                violation.register,
+                violation.actual,
+                violation.trial,
+                violation.expected
+            );
+        }
+        if violations.is_empty() {
+            info_println!(
+                "{}[✓]{} All {} registers reset to their initial value.",
+                color::Fg(color::LightGreen),
+                color::Fg(color::Reset),
+                dff_outputs.len()
+            );
+        } else {
+            info_println!(
+                "{}[!]{} {} of {} registers don't reset to their initial value.",
+                color::Fg(color::LightYellow),
+                color::Fg(color::Reset),
+                violations.len(),
+                dff_outputs.len()
+            );
+        }
+        return;
+    }
+
+    if let Some(min_fanout) = matches.get_one::<usize>("fanout-report") {
+        let fanouts = circuit_ptxt.fanout_map();
+        info_println!("Nets with fanout of at least {}:", min_fanout);
+        for (wire, fanout) in fanouts
+            .iter()
+            .filter(|(_, fanout)| *fanout >= min_fanout)
+            .sorted_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)))
+        {
+            info_println!(" {}: {}", wire, fanout);
+        }
+        info_println!(
+            "{}[✓]{} Maximum fanout: {}.",
+            color::Fg(color::LightGreen),
+            color::Fg(color::Reset),
+            fanouts.values().max().copied().unwrap_or(0)
+        );
+        return;
+    }
+
+    if let Some(parts) = matches.get_one::<usize>("partition") {
+        if *parts == 0 {
+            panic!(
+                "{}[!]{} The number of partitions must be positive.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        let (partitions, cross_wires) = circuit_ptxt.partition(*parts);
+        let prefix = file_name.strip_suffix(".v").unwrap_or(file_name);
+        let module_name =
+            verilog_parser::read_module_name(file_name).unwrap_or_else(|| "helm".to_owned());
+        for (part, partition) in partitions.iter().enumerate() {
+            let part_file = format!("{}.part{}.v", prefix, part);
+            verilog_parser::write_verilog_file(
+                &part_file,
+                &format!("{}_part{}", module_name, part),
+                &partition.gates,
+                &partition.input_wires,
+                &partition.output_wires,
+            );
+            info_println!(
+                " {}: {} gates, {} inputs, {} outputs",
+                part_file,
+                partition.gates.len(),
+                partition.input_wires.len(),
+                partition.output_wires.len()
+            );
+        }
+        let cross_wires_file = format!("{}.partitions.csv", prefix);
+        verilog_parser::write_cross_wires(&cross_wires_file, &cross_wires);
+        info_println!(
+            "{}[✓]{} {} partitions with a cut of {} cross-partition wires, listed in {}.",
+            color::Fg(color::LightGreen),
+            color::Fg(color::Reset),
+            partitions.len(),
+            cross_wires.len(),
+            cross_wires_file
+        );
+        return;
+    }
+
+    if let Some(cone_args) = matches.get_many::<String>("dump-cone") {
+        let (wire, cone_file) = cone_args.collect_tuple().expect("WIRE and FILE");
+        let wire = helm::rename_wire(wire, &input_aliases);
+        let cone = circuit_ptxt.cone(&wire);
+        if cone.gates.is_empty() {
+            panic!(
+                "{}[!]{} Wire \"{}\" is not driven by a combinational gate.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                wire
+            );
+        }
+        let module_name =
+            verilog_parser::read_module_name(file_name).unwrap_or_else(|| "helm".to_owned());
+        verilog_parser::write_verilog_file(
+            cone_file,
+            &format!("{}_cone", module_name),
+            &cone.gates,
+            &cone.input_wires,
+            &cone.output_wires,
+        );
+        info_println!(
+            "{}[✓]{} Wrote the cone of {} ({} gates, {} inputs) to {}.",
+            color::Fg(color::LightGreen),
+            color::Fg(color::Reset),
+            wire,
+            cone.gates.len(),
+            cone.input_wires.len(),
+            cone_file
+        );
+        return;
+    }
+
+    if let Some(cnf_file) = matches.get_one::<String>("emit-cnf") {
+        let cnf = circuit_ptxt.to_cnf();
+        let variables_file = format!("{}.vars.csv", cnf_file);
+        verilog_parser::write_cnf(cnf_file, &variables_file, &cnf);
+        info_println!(
+            "{}[✓]{} {} variables and {} clauses written to {}, variables listed in {}.",
+            color::Fg(color::LightGreen),
+            color::Fg(color::Reset),
+            cnf.variables.len(),
+            cnf.clauses.len(),
+            cnf_file,
+            variables_file
+        );
+        return;
+    }
+
+    if matches.get_flag("activity") || matches.get_flag("coverage") {
+        if arithmetic.is_some() {
+            panic!(
+                "{}[!]{} Switching activity and coverage are only supported in gates and LUTs \
+                    modes.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        let fill_inputs = |inputs: HashMap<String, PtxtType>| {
+            let inputs = helm::rename_wires(inputs, &input_aliases);
+            match default_input {
+                Some(value) => {
+                    helm::fill_unspecified_inputs(inputs, &input_wires, &dff_outputs, value, "bool")
+                }
+                None => inputs,
+            }
+        };
+        let vectors = match batch_vectors_file {
+            Some(batch_file) => {
+                if is_sequential {
+                    panic!(
+                        "{}[!]{} Sequential circuits are compared cycle to cycle, from a single \
+                            input vector.",
+                        color::Fg(color::LightRed),
+                        color::Fg(color::Reset)
+                    );
+                }
+                verilog_parser::read_input_vectors(batch_file, "bool")
+                    .into_iter()
+                    .map(fill_inputs)
+                    .collect::<Vec<_>>()
+            }
+            None => {
+                let input_wire_map = fill_inputs(read_input_wire_map("bool"));
+                if input_wire_map.contains_key("dummy") {
+                    vec![HashMap::new()]
+                } else {
+                    vec![input_wire_map]
+                }
+            }
+        };
+
+        // Sequential circuits are compared cycle to cycle, combinational ones
+        // from one input vector to the next.
+        let mut snapshots = vec![];
+        if is_sequential {
+            let mut wire_map = circuit_ptxt.initialize_wire_map(&wire_set, &vectors[0], "bool");
+            for _ in 0..num_cycles {
+                wire_map = circuit_ptxt.evaluate(&wire_map);
+                snapshots.push(wire_map.clone());
+            }
+        } else {
+            if vectors.len() < 2 {
+                info_println!(
+                    "{}[!]{} Only one input vector: give several with --batch-vectors to see \
+                        wires toggle.",
+                    color::Fg(color::LightYellow),
+                    color::Fg(color::Reset)
+                );
+            }
+            for inputs in vectors.iter() {
+                let wire_map = circuit_ptxt.initialize_wire_map(&wire_set, inputs, "bool");
+                snapshots.push(circuit_ptxt.evaluate(&wire_map));
+            }
+        }
+
+        if matches.get_flag("activity") {
+            let mut toggle_counts = HashMap::new();
+            for pair in snapshots.windows(2) {
+                for wire in circuit::toggled_wires(&pair[0], &pair[1]) {
+                    *toggle_counts.entry(wire).or_insert(0) += 1;
+                }
+            }
+            info_println!("Switching activity:");
+            for (wire, toggles) in toggle_counts
+                .iter()
+                .sorted_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)))
+            {
+                info_println!(" {}: {}", wire, toggles);
+            }
+            info_println!(
+                "{}[✓]{} {} toggles in total.",
+                color::Fg(color::LightGreen),
+                color::Fg(color::Reset),
+                toggle_counts.values().sum::<usize>()
+            );
+        }
+        if matches.get_flag("coverage") {
+            let coverage = circuit_ptxt.coverage(&snapshots);
+            info_println!("Stuck gates:");
+            for gate in coverage.stuck.iter() {
+                info_println!(" {}", gate);
+            }
+            info_println!(
+                "{}[✓]{} Toggle coverage: {}/{} gates ({:.1}%).",
+                color::Fg(color::LightGreen),
+                color::Fg(color::Reset),
+                coverage.toggled.len(),
+                coverage.toggled.len() + coverage.stuck.len(),
+                coverage.percentage()
+            );
+        }
+        return;
+    }
+
+    // Everything below evaluates encrypted, so reject the unknown constants
+    // before generating any key.
+    if has_unknown_constants {
+        panic!(
+            "{}[!]{} Unknown (x) constants can only be evaluated in plaintext, e.g. with \
+                --activity.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset)
+        );
+    }
+
+    // With --verify, the outputs are checked against a plaintext evaluation
+    // of the same inputs, and a failure leaves a repro/ directory behind.
+    let verify = matches.get_flag("verify");
+    let compare_backends = matches.get_flag("compare-backends");
+    if compare_backends && arithmetic.is_none() {
+        panic!(
+            "{}[!]{} --compare-backends needs an arithmetic circuit.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset)
+        );
+    }
+    let adder = match matches.get_one::<String>("adder").unwrap().as_str() {
+        "cla" => circuit::AdderArchitecture::CarryLookahead,
+        "carry-select" => circuit::AdderArchitecture::CarrySelect,
+        _ => circuit::AdderArchitecture::Ripple,
+    };
+    // With --evaluate-twice-check, the encrypted inputs are evaluated again
+    // and both decryptions must agree.
+    let evaluate_twice = matches.get_flag("evaluate-twice-check");
+    let loose_width = matches.get_flag("loose-width-compare");
+    let check_determinism = |first: &HashMap<String, PtxtType>,
+                             second: HashMap<String, PtxtType>| {
+        let second = helm::rename_wires(second, &output_aliases);
+        let mismatches = circuit::compare_outputs(first, &second, loose_width);
+        if mismatches.is_empty() {
+            info_println!(
+                "{}[✓]{} A second evaluation gave the same outputs.",
+                color::Fg(color::LightGreen),
+                color::Fg(color::Reset)
+            );
+            return;
+        }
+        for mismatch in mismatches.iter() {
+            info_println!(
+                " {}: {} (first evaluation {})",
+                mismatch.wire,
+                mismatch.actual,
+                mismatch.expected
+            );
+        }
+        panic!(
+            "{}[!]{} {} outputs differ between two evaluations of the same inputs.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            mismatches.len()
+        );
+    };
+    // The repro gets every argument that shapes the netlist, its inputs or
+    // the evaluation, with copies of the files they name.
+    // --only-combinational conflicts with --cycles and runs one cycle anyway.
+    let mut repro_args = if matches.get_flag("only-combinational") {
+        vec![]
+    } else {
+        vec!["--cycles".to_owned(), num_cycles.to_string()]
+    };
+    if let Some(arithmetic_type) = &arithmetic {
+        repro_args.extend(["--arithmetic".to_owned(), arithmetic_type.clone()]);
+    }
+    if let Some(value) = default_input {
+        repro_args.extend(["--default-input".to_owned(), value.clone()]);
+    }
+    for wire in matches
+        .get_many::<String>("trivial-input")
+        .unwrap_or_default()
+    {
+        repro_args.extend(["--trivial-input".to_owned(), wire.clone()]);
+    }
+    for flag in [
+        "only-combinational",
+        "no-optimize",
+        "lazy-encryption",
+        "work-stealing",
+        "loose-width-compare",
+    ] {
+        if matches.get_flag(flag) {
+            repro_args.push(format!("--{}", flag));
+        }
+    }
+    if gpu_eval {
+        repro_args.push("--gpu".to_owned());
+    }
+    let repro_files = ["wire-aliases", "constraints", "patch", "schedule"]
+        .into_iter()
+        .filter_map(|arg| {
+            let file = matches.get_one::<String>(arg)?;
+            Some((format!("--{}", arg), file.clone()))
+        })
+        .collect::<Vec<_>>();
+    let check_outputs =
+        |reference: &Option<(HashMap<String, PtxtType>, HashMap<String, PtxtType>)>,
+         decrypted_outputs: &HashMap<String, PtxtType>| {
+            let Some((inputs, expected)) = reference else {
+                return;
+            };
+            let expected = helm::rename_wires(expected.clone(), &output_aliases);
+            let mismatches = circuit::compare_outputs(&expected, decrypted_outputs, loose_width);
+            if mismatches.is_empty() {
+                info_println!(
+                    "{}[✓]{} The outputs match the plaintext evaluation.",
+                    color::Fg(color::LightGreen),
+                    color::Fg(color::Reset)
+                );
+                return;
+            }
+            for mismatch in mismatches.iter() {
+                info_println!(
+                    " {}: {} (expected {})",
+                    mismatch.wire,
+                    mismatch.actual,
+                    mismatch.expected
+                );
+            }
+            // The inputs go back to the names of --wire-aliases.
+            let inputs = helm::rename_wires(inputs.clone(), &output_aliases);
+            verilog_parser::write_repro(
+                "repro",
+                file_name,
+                &inputs,
+                &mismatches,
+                &repro_args,
+                &repro_files,
+            );
+            panic!(
+                "{}[!]{} {} outputs differ from the plaintext evaluation, reproduction written to \
+                repro/.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                mismatches.len()
+            );
+        };
+
+    if let Some(arithmetic_type) = &arithmetic {
+        info_println!(
             "{} -- Arithmetic mode with {} -- {}",
             color::Fg(color::LightYellow),
             arithmetic_type,
@@ -73,61 +882,197 @@ fn main() {
         );
         let arithmetic_type = arithmetic_type.as_str();
         match arithmetic_type {
-            "u8" | "u16" | "u32" | "u64" | "u128" => {}
+            "u8" | "u16" | "u32" | "u64" | "u128" | "u256" => {}
             _ => unreachable!(),
         }
 
+        let input_wire_map = read_input_wire_map(arithmetic_type);
+        let mut input_wire_map = helm::rename_wires(input_wire_map, &input_aliases);
+        if let Some(value) = default_input {
+            input_wire_map = helm::fill_unspecified_inputs(
+                input_wire_map,
+                &input_wires,
+                &dff_outputs,
+                value,
+                arithmetic_type,
+            );
+        }
+        let reference = verify.then(|| {
+            let expected =
+                circuit_ptxt.reference_outputs(&input_wire_map, num_cycles, arithmetic_type);
+            (input_wire_map.clone(), expected)
+        });
+        let bit_blasted = compare_backends.then(|| {
+            if !dff_outputs.is_empty() {
+                panic!(
+                    "{}[!]{} Only combinational circuits are compared across backends.",
+                    color::Fg(color::LightRed),
+                    color::Fg(color::Reset)
+                );
+            }
+            let width = arithmetic_type[1..].parse::<usize>().unwrap();
+            circuit_ptxt.bit_blast(width, adder)
+        });
+
         // Arithmetic mode
-        let config = ConfigBuilder::all_disabled()
-            .enable_custom_integers(
-                tfhe::shortint::parameters::PARAM_MULTI_BIT_MESSAGE_2_CARRY_2_GROUP_3_KS_PBS,
-                None,
-            )
-            .build();
         let mut start = Instant::now();
-        let (client_key, server_key) = generate_keys(config); // integer ctxt
+        let (client_key, server_key) = match key_set.generate() {
+            circuit::KeyPair::Integer(client_key, server_key) => (client_key, server_key),
+            _ => unreachable!(),
+        };
         let mut circuit = circuit::ArithCircuit::new(client_key, server_key, circuit_ptxt);
-        println!("KeyGen done in {} seconds.", start.elapsed().as_secs_f64());
-
-        let input_wire_map =
-            helm::get_input_wire_map(inputs_filename, wire_inputs, arithmetic_type);
+        info_println!("KeyGen done in {} seconds.", start.elapsed().as_secs_f64());
 
         // Client encrypts their inputs
         start = Instant::now();
         let mut enc_wire_map =
             EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
-        println!(
+        info_println!(
             "Encryption done in {} seconds.",
             start.elapsed().as_secs_f64()
         );
+        let enc_inputs = evaluate_twice.then(|| enc_wire_map.clone());
 
-        // TODO: Add cycles here
         start = Instant::now();
-        enc_wire_map =
-            EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, arithmetic_type);
-        println!(
-            "Evaluation done in {} seconds.\n",
-            start.elapsed().as_secs_f64()
-        );
+        for cycle in 0..num_cycles {
+            let cycle_start = Instant::now();
+            enc_wire_map = EvalCircuit::evaluate_encrypted(
+                &mut circuit,
+                &enc_wire_map,
+                cycle + 1,
+                arithmetic_type,
+            );
+            if num_cycles > 1 {
+                info_println!(
+                    "Cycle {}) Evaluation done in {} seconds.",
+                    cycle,
+                    cycle_start.elapsed().as_secs_f64()
+                );
+            }
+        }
+        let integer_seconds = start.elapsed().as_secs_f64();
+        info_println!("Evaluation done in {} seconds.\n", integer_seconds);
+        eval_seconds += integer_seconds;
 
         // Client decrypts the output of the circuit
         start = Instant::now();
-        println!("Encrypted Evaluation:");
+        info_println!("Encrypted Evaluation:");
         let decrypted_outputs = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, verbose);
-        verilog_parser::write_output_wires(outputs_filename, &decrypted_outputs);
-        println!(
+        let decrypted_outputs = helm::rename_wires(decrypted_outputs, &output_aliases);
+        verilog_parser::write_output_wires(
+            outputs_filename,
+            &decrypted_outputs,
+            &output_order,
+            hex_output,
+        );
+        if let Some(binary_file) = output_binary_file {
+            verilog_parser::write_binary_wires(binary_file, &decrypted_outputs);
+        }
+        check_outputs(&reference, &decrypted_outputs);
+        info_println!(
             "Decryption done in {} seconds.",
             start.elapsed().as_secs_f64()
         );
+        if let Some(enc_inputs) = enc_inputs {
+            let outputs = circuit::evaluate_and_decrypt(
+                &mut circuit,
+                &enc_inputs,
+                num_cycles,
+                arithmetic_type,
+            );
+            check_determinism(&decrypted_outputs, outputs);
+        }
+
+        if let Some(bit_blasted) = bit_blasted {
+            info_println!(
+                "\n{} -- Gates mode, bit-blasted -- {}",
+                color::Fg(color::LightYellow),
+                color::Fg(color::Reset)
+            );
+            let mut circuit_bits = circuit::Circuit::new(
+                bit_blasted.gates.clone(),
+                &bit_blasted.input_wires,
+                &bit_blasted.output_wires,
+                &bit_blasted.dff_outputs,
+            );
+            circuit_bits.sort_circuit();
+            circuit_bits.compute_levels();
+            let (bit_depth, bit_bootstraps) =
+                (circuit_bits.depth(), circuit_bits.count_bootstraps());
+            let (client_key, server_key) = match circuit::KeySet::Boolean.generate() {
+                circuit::KeyPair::Boolean(client_key, server_key) => (client_key, server_key),
+                _ => unreachable!(),
+            };
+            let mut circuit = circuit::GateCircuit::new(client_key, server_key, circuit_bits);
+            let enc_wire_map = EvalCircuit::encrypt_inputs(
+                &mut circuit,
+                &bit_blasted.wire_set,
+                &bit_blasted.split_inputs(&input_wire_map),
+            );
+            start = Instant::now();
+            let enc_wire_map =
+                EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+            let boolean_seconds = start.elapsed().as_secs_f64();
+            let output_bits = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false);
+            let boolean_outputs = helm::rename_wires(
+                bit_blasted.join_outputs(&output_bits, arithmetic_type),
+                &output_aliases,
+            );
+
+            let mismatches =
+                circuit::compare_outputs(&decrypted_outputs, &boolean_outputs, loose_width);
+            for mismatch in mismatches.iter() {
+                info_println!(
+                    " {}: {} on the boolean backend, {} on the integer backend",
+                    mismatch.wire,
+                    mismatch.actual,
+                    mismatch.expected
+                );
+            }
+            if !mismatches.is_empty() {
+                panic!(
+                    "{}[!]{} {} outputs differ between the backends.",
+                    color::Fg(color::LightRed),
+                    color::Fg(color::Reset),
+                    mismatches.len()
+                );
+            }
+            info_println!(
+                "{}[✓]{} The backends agree: {:.3} seconds on integers, {:.3} seconds on bits ({} \
+                    gates, {} bootstraps, depth {} with {:?} adders).",
+                color::Fg(color::LightGreen),
+                color::Fg(color::Reset),
+                integer_seconds,
+                boolean_seconds,
+                bit_blasted.gates.len(),
+                bit_bootstraps,
+                bit_depth,
+                adder
+            );
+        }
     } else {
         let arithmetic_type = "bool";
         // Initialization of inputs
-        let input_wire_map =
-            helm::get_input_wire_map(inputs_filename, wire_inputs, arithmetic_type);
+        let input_wire_map = read_input_wire_map(arithmetic_type);
+        let mut input_wire_map = helm::rename_wires(input_wire_map, &input_aliases);
+        if let Some(value) = default_input {
+            input_wire_map = helm::fill_unspecified_inputs(
+                input_wire_map,
+                &input_wires,
+                &dff_outputs,
+                value,
+                arithmetic_type,
+            );
+        }
+        let reference = verify.then(|| {
+            let expected =
+                circuit_ptxt.reference_outputs(&input_wire_map, num_cycles, arithmetic_type);
+            (input_wire_map.clone(), expected)
+        });
 
         // Encrypted Evaluation
         if !has_luts {
-            println!(
+            info_println!(
                 "{} -- Gates mode -- {}",
                 color::Fg(color::LightYellow),
                 color::Fg(color::Reset)
@@ -202,13 +1147,13 @@ fn main() {
                         noise,
                     );
 
-                    println!("KeyGen done in {} seconds.", start.elapsed().as_secs_f64());
+                    info_println!("KeyGen done in {} seconds.", start.elapsed().as_secs_f64());
 
                     // Client encrypts their inputs
                     start = Instant::now();
                     let mut enc_wire_map =
                         EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
-                    println!(
+                    info_println!(
                         "Encryption done in {} seconds.",
                         start.elapsed().as_secs_f64()
                     );
@@ -219,18 +1164,29 @@ fn main() {
                         1,
                         arithmetic_type,
                     );
-                    println!(
+                    info_println!(
                         "GPU Evaluation done in {} seconds.\n",
                         start.elapsed().as_secs_f64()
                     );
+                    eval_seconds += start.elapsed().as_secs_f64();
 
                     // Client decrypts the output of the circuit
                     start = Instant::now();
-                    println!("Encrypted Evaluation:");
+                    info_println!("Encrypted Evaluation:");
                     let decrypted_outputs =
                         EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, verbose);
-                    verilog_parser::write_output_wires(outputs_filename, &decrypted_outputs);
-                    println!(
+                    let decrypted_outputs = helm::rename_wires(decrypted_outputs, &output_aliases);
+                    verilog_parser::write_output_wires(
+                        outputs_filename,
+                        &decrypted_outputs,
+                        &output_order,
+                        hex_output,
+                    );
+                    if let Some(binary_file) = output_binary_file {
+                        verilog_parser::write_binary_wires(binary_file, &decrypted_outputs);
+                    }
+                    check_outputs(&reference, &decrypted_outputs);
+                    info_println!(
                         "Decryption done in {} seconds.",
                         start.elapsed().as_secs_f64()
                     );
@@ -238,92 +1194,239 @@ fn main() {
             } else {
                 // Gate mode
                 let mut start = Instant::now();
-                let (client_key, server_key) = gen_keys();
-                println!("KeyGen done in {} seconds.", start.elapsed().as_secs_f64());
+                let (client_key, server_key) = match key_set.generate() {
+                    circuit::KeyPair::Boolean(client_key, server_key) => (client_key, server_key),
+                    _ => unreachable!(),
+                };
+                info_println!("KeyGen done in {} seconds.", start.elapsed().as_secs_f64());
                 let mut circuit = circuit::GateCircuit::new(client_key, server_key, circuit_ptxt);
+                circuit.set_checkpoints(&checkpoints);
+                circuit.set_trivial_inputs(&trivial_inputs);
+                if let Some(stream_file) = stream_outputs_file {
+                    circuit.set_output_stream(stream_file);
+                }
 
-                // Client encrypts their inputs
-                start = Instant::now();
-                let mut enc_wire_map =
-                    EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
-                println!(
-                    "Encryption done in {} seconds.",
-                    start.elapsed().as_secs_f64()
-                );
+                if let Some(batch_file) = batch_vectors_file {
+                    let vectors = verilog_parser::read_input_vectors(batch_file, arithmetic_type)
+                        .into_iter()
+                        .map(|inputs| helm::rename_wires(inputs, &input_aliases))
+                        .collect::<Vec<_>>();
+                    start = Instant::now();
+                    let outputs = circuit.evaluate_batch(&wire_set, &vectors, num_cycles);
+                    info_println!(
+                        "Evaluated {} vectors in {} seconds.",
+                        vectors.len(),
+                        start.elapsed().as_secs_f64()
+                    );
+                    eval_seconds += start.elapsed().as_secs_f64();
+                    let outputs = outputs
+                        .into_iter()
+                        .map(|outputs| helm::rename_wires(outputs, &output_aliases))
+                        .collect::<Vec<_>>();
+                    match &outputs_filename {
+                        Some(outputs_filename) => verilog_parser::write_output_vectors(
+                            outputs_filename,
+                            &outputs,
+                            &output_order,
+                        ),
+                        None => {
+                            for (vector, outputs) in outputs.iter().enumerate() {
+                                info_println!("Vector {}:", vector);
+                                for (wire, value) in outputs.iter().sorted() {
+                                    info_println!(" {}: {}", wire, value);
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    // Client encrypts their inputs, or leaves them to the first
+                    // cycle in lazy mode.
+                    let lazy_encryption = matches.get_flag("lazy-encryption");
+                    let mut enc_wire_map = HashMap::new();
+                    if !lazy_encryption {
+                        start = Instant::now();
+                        enc_wire_map =
+                            EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
+                        info_println!(
+                            "Encryption done in {} seconds.",
+                            start.elapsed().as_secs_f64()
+                        );
+                    }
+                    let enc_inputs = evaluate_twice.then(|| enc_wire_map.clone());
+
+                    let mut state_trace = vec![];
+                    for cycle in 0..num_cycles {
+                        start = Instant::now();
+                        enc_wire_map = if lazy_encryption && cycle == 0 {
+                            circuit.evaluate_encrypted_lazily(&wire_set, &input_wire_map, 1)
+                        } else {
+                            EvalCircuit::evaluate_encrypted(
+                                &mut circuit,
+                                &enc_wire_map,
+                                cycle + 1,
+                                arithmetic_type,
+                            )
+                        };
+                        info_println!(
+                            "Cycle {}) Evaluation done in {} seconds.\n",
+                            cycle,
+                            start.elapsed().as_secs_f64()
+                        );
+                        eval_seconds += start.elapsed().as_secs_f64();
+                        if state_trace_file.is_some() {
+                            let registers = circuit.decrypt_registers(&enc_wire_map);
+                            let registers = helm::rename_wires(registers, &output_aliases);
+                            state_trace.push(verilog_parser::pack_register_state(&registers));
+                        }
+                    }
+                    if let Some(state_trace_file) = state_trace_file {
+                        verilog_parser::write_state_trace(state_trace_file, &state_trace);
+                    }
 
-                for cycle in 0..num_cycles {
+                    // Client decrypts the output of the circuit
                     start = Instant::now();
-                    enc_wire_map = EvalCircuit::evaluate_encrypted(
-                        &mut circuit,
-                        &enc_wire_map,
-                        1,
-                        arithmetic_type,
+                    info_println!("Encrypted Evaluation:");
+                    let decrypted_outputs =
+                        EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, verbose);
+                    let decrypted_outputs = helm::rename_wires(decrypted_outputs, &output_aliases);
+                    verilog_parser::write_output_wires(
+                        outputs_filename,
+                        &decrypted_outputs,
+                        &output_order,
+                        hex_output,
                     );
-                    println!(
-                        "Cycle {}) Evaluation done in {} seconds.\n",
-                        cycle,
+                    if let Some(binary_file) = output_binary_file {
+                        verilog_parser::write_binary_wires(binary_file, &decrypted_outputs);
+                    }
+                    check_outputs(&reference, &decrypted_outputs);
+                    info_println!(
+                        "Decryption done in {} seconds.",
                         start.elapsed().as_secs_f64()
                     );
+                    if let Some(enc_inputs) = enc_inputs {
+                        let outputs = circuit::evaluate_and_decrypt(
+                            &mut circuit,
+                            &enc_inputs,
+                            num_cycles,
+                            arithmetic_type,
+                        );
+                        check_determinism(&decrypted_outputs, outputs);
+                    }
                 }
-
-                // Client decrypts the output of the circuit
-                start = Instant::now();
-                println!("Encrypted Evaluation:");
-                let decrypted_outputs =
-                    EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, verbose);
-                verilog_parser::write_output_wires(outputs_filename, &decrypted_outputs);
-                println!(
-                    "Decryption done in {} seconds.",
-                    start.elapsed().as_secs_f64()
-                );
             }
         } else {
-            println!(
+            info_println!(
                 "{} -- LUTs mode -- {}",
                 color::Fg(color::LightYellow),
                 color::Fg(color::Reset)
             );
             // LUT mode
             let mut start = Instant::now();
-            let (client_key, server_key) = tfhe::shortint::gen_keys(PARAM_MESSAGE_1_CARRY_1_KS_PBS); // single bit ctxt
+            let (client_key, server_key) = match key_set.generate() {
+                circuit::KeyPair::ShortInt(client_key, server_key) => (client_key, server_key),
+                _ => unreachable!(),
+            };
             let mut circuit = circuit::LutCircuit::new(client_key, server_key, circuit_ptxt);
-            println!("KeyGen done in {} seconds.", start.elapsed().as_secs_f64());
+            info_println!("KeyGen done in {} seconds.", start.elapsed().as_secs_f64());
 
             // Client encrypts their inputs
             start = Instant::now();
             let mut enc_wire_map =
                 EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
-            println!(
+            info_println!(
                 "Encryption done in {} seconds.",
                 start.elapsed().as_secs_f64()
             );
+            let enc_inputs = evaluate_twice.then(|| enc_wire_map.clone());
 
+            let mut state_trace = vec![];
             for cycle in 0..num_cycles {
                 start = Instant::now();
                 enc_wire_map = EvalCircuit::evaluate_encrypted(
                     &mut circuit,
                     &enc_wire_map,
-                    1,
+                    cycle + 1,
                     arithmetic_type,
                 );
-                println!(
+                info_println!(
                     "Cycle {}) Evaluation done in {} seconds.\n",
                     cycle,
                     start.elapsed().as_secs_f64()
                 );
+                eval_seconds += start.elapsed().as_secs_f64();
+                if state_trace_file.is_some() {
+                    let registers = circuit.decrypt_registers(&enc_wire_map);
+                    let registers = helm::rename_wires(registers, &output_aliases);
+                    state_trace.push(verilog_parser::pack_register_state(&registers));
+                }
+            }
+            if let Some(state_trace_file) = state_trace_file {
+                verilog_parser::write_state_trace(state_trace_file, &state_trace);
             }
 
             // Client decrypts the output of the circuit
             start = Instant::now();
-            println!("Encrypted Evaluation:");
+            info_println!("Encrypted Evaluation:");
             let decrypted_outputs =
                 EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, verbose);
-            verilog_parser::write_output_wires(outputs_filename, &decrypted_outputs);
-            println!(
+            let decrypted_outputs = helm::rename_wires(decrypted_outputs, &output_aliases);
+            verilog_parser::write_output_wires(
+                outputs_filename,
+                &decrypted_outputs,
+                &output_order,
+                hex_output,
+            );
+            if let Some(binary_file) = output_binary_file {
+                verilog_parser::write_binary_wires(binary_file, &decrypted_outputs);
+            }
+            check_outputs(&reference, &decrypted_outputs);
+            info_println!(
                 "Decryption done in {} seconds.",
                 start.elapsed().as_secs_f64()
             );
+            if let Some(enc_inputs) = enc_inputs {
+                let outputs = circuit::evaluate_and_decrypt(
+                    &mut circuit,
+                    &enc_inputs,
+                    num_cycles,
+                    arithmetic_type,
+                );
+                check_determinism(&decrypted_outputs, outputs);
+            }
         }
     }
-    println!();
+
+    if let Some(stats_file) = matches.get_one::<String>("emit-stats-csv") {
+        let stats = manifest::RunStats {
+            name: file_name.to_owned(),
+            gates: gate_count,
+            levels: level_count,
+            bootstraps: bootstrap_count,
+            eval_seconds,
+        };
+        stats.append(stats_file);
+    }
+
+    if let Some(manifest_file) = matches.get_one::<String>("manifest") {
+        let parameter_set = if gpu_eval {
+            "concrete-core (GPU)"
+        } else {
+            key_set.parameter_set_name()
+        };
+        let input_file = inputs_filename.or_else(|| input_binary_file.cloned());
+        let manifest = manifest::RunManifest {
+            netlist: file_name.to_owned(),
+            netlist_hash: manifest::hash_file(file_name),
+            arithmetic,
+            parameter_set: parameter_set.to_owned(),
+            cycles: num_cycles,
+            input_file_hash: input_file.as_deref().map(manifest::hash_file),
+            input_file,
+            gates: gate_count,
+            bootstraps: bootstrap_count,
+            wall_clock_seconds: run_start.elapsed().as_secs_f64(),
+        };
+        manifest.write(manifest_file);
+    }
+    info_println!();
 }
@@ -6,8 +6,17 @@ use concrete_core::specification::parameters::LweDimension;
 use itertools::Itertools;
 use rayon::prelude::*;
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
-    sync::{Arc, RwLock},
+    borrow::Cow,
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet},
+    fmt,
+    fs::File,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::Duration,
     vec,
 };
 use termion::color;
@@ -23,10 +32,12 @@ use tfhe::{
         ciphertext::Ciphertext as CtxtShortInt, wopbs::WopbsKey as WopbsKeyShortInt,
         ClientKey as ClientKeyShortInt, ServerKey as ServerKeyShortInt,
     },
-    unset_server_key, FheUint128, FheUint16, FheUint32, FheUint64, FheUint8,
+    unset_server_key, FheUint128, FheUint16, FheUint256, FheUint32, FheUint64, FheUint8,
 };
 
-use crate::{FheType, PtxtType};
+use thiserror::Error;
+
+use crate::{FheType, PtxtType, U256};
 
 #[cfg(feature = "gpu")]
 /// The plaintext associated with true: 1/8 (for concrete-core Boolean)
@@ -36,6 +47,776 @@ static PLAINTEXT_TRUE: u32 = 1 << (32 - 3);
 /// The plaintext associated with false: -1/8 (for concrete-core Boolean)
 static PLAINTEXT_FALSE: u32 = 7 << (32 - 3);
 
+#[derive(Debug, Error)]
+pub enum CircuitError {
+    #[error("Circuit depth {depth} exceeds the maximum depth of {max_depth}")]
+    MaxDepthExceeded { depth: usize, max_depth: usize },
+    #[error("Gate {gate} did not finish within {time_limit:?}")]
+    GateTimeout { gate: String, time_limit: Duration },
+    #[error(
+        "Bootstrap budget of {budget} exceeded after {used} bootstraps, at level {level} of \
+        {total_levels}"
+    )]
+    BootstrapBudgetExceeded {
+        budget: usize,
+        used: usize,
+        level: usize,
+        total_levels: usize,
+    },
+    #[error("Invalid schedule at gate {gate}: {reason}")]
+    InvalidSchedule { gate: String, reason: String },
+    #[error("Gate {gate} mixes {wire} ({width} bits) with {other_wire} ({other_width} bits)")]
+    WidthMismatch {
+        gate: String,
+        wire: String,
+        width: usize,
+        other_wire: String,
+        other_width: usize,
+    },
+}
+
+/// Run the evaluation of `gate` on a worker thread and wait for it for at
+/// most `time_limit`. A hung worker is left behind, so the caller should
+/// abort on a timeout.
+pub fn evaluate_with_time_limit<T, F>(
+    gate: &str,
+    time_limit: Duration,
+    evaluate: F,
+) -> Result<T, CircuitError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // The receiver is gone if the watchdog gave up on this gate.
+        let _ = sender.send(evaluate());
+    });
+
+    match receiver.recv_timeout(time_limit) {
+        Ok(output) => Ok(output),
+        Err(RecvTimeoutError::Timeout) => Err(CircuitError::GateTimeout {
+            gate: gate.to_owned(),
+            time_limit,
+        }),
+        Err(RecvTimeoutError::Disconnected) => panic!(
+            "{}[!]{} Evaluation of gate {} panicked.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            gate
+        ),
+    }
+}
+
+/// Abort on a gate that hit the time limit of `evaluate_with_time_limit`.
+fn unwrap_gate_output<T>(output: Result<T, CircuitError>) -> T {
+    output.unwrap_or_else(|error| {
+        panic!(
+            "{}[!]{} {}.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            error
+        )
+    })
+}
+
+/// Reject a gate time limit on a backend that has no watchdog.
+fn assert_no_time_limit(circuit: &Circuit, backend: &str) {
+    if circuit.time_limit_per_gate.is_some() {
+        panic!(
+            "{}[!]{} The gate time limit is not supported in {} mode.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            backend
+        );
+    }
+}
+
+/// The key material needed to evaluate a circuit: Boolean keys for gates,
+/// shortint keys for LUTs and integer keys for arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeySet {
+    Boolean,
+    ShortInt,
+    Integer,
+}
+
+pub enum KeyPair {
+    Boolean(ClientKey, ServerKey),
+    ShortInt(ClientKeyShortInt, ServerKeyShortInt),
+    Integer(tfhe::ClientKey, tfhe::ServerKey),
+}
+
+impl KeySet {
+    pub fn from_gates<'g>(gates: impl IntoIterator<Item = &'g Gate>) -> KeySet {
+        let mut key_set = KeySet::Boolean;
+        for gate in gates {
+            if gate.get_gate_type().is_arithmetic() {
+                return KeySet::Integer;
+            } else if gate.get_gate_type() == GateType::Lut {
+                key_set = KeySet::ShortInt;
+            }
+        }
+
+        key_set
+    }
+
+    /// Generate only the keys of this key set.
+    pub fn generate(self) -> KeyPair {
+        match self {
+            KeySet::Boolean => {
+                let (client_key, server_key) = tfhe::boolean::gen_keys();
+                KeyPair::Boolean(client_key, server_key)
+            }
+            KeySet::ShortInt => {
+                // single bit ctxt
+                let (client_key, server_key) = tfhe::shortint::gen_keys(
+                    tfhe::shortint::parameters::PARAM_MESSAGE_1_CARRY_1_KS_PBS,
+                );
+                KeyPair::ShortInt(client_key, server_key)
+            }
+            KeySet::Integer => {
+                let config = tfhe::ConfigBuilder::all_disabled()
+                    .enable_custom_integers(
+                        tfhe::shortint::parameters::PARAM_MULTI_BIT_MESSAGE_2_CARRY_2_GROUP_3_KS_PBS,
+                        None,
+                    )
+                    .build();
+                let (client_key, server_key) = tfhe::generate_keys(config); // integer ctxt
+                KeyPair::Integer(client_key, server_key)
+            }
+        }
+    }
+
+    /// Name of the tfhe parameter set the keys are generated with.
+    pub fn parameter_set_name(self) -> &'static str {
+        match self {
+            KeySet::Boolean => "boolean::DEFAULT_PARAMETERS",
+            KeySet::ShortInt => "shortint::PARAM_MESSAGE_1_CARRY_1_KS_PBS",
+            KeySet::Integer => "shortint::PARAM_MULTI_BIT_MESSAGE_2_CARRY_2_GROUP_3_KS_PBS",
+        }
+    }
+}
+
+/// A wire whose value differs from the reference model for an input vector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub vector: usize,
+    pub wire: String,
+    pub expected: PtxtType,
+    pub actual: PtxtType,
+}
+
+/// A register that doesn't hold its initial value after a reset cycle, from
+/// the randomized state `trial`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResetViolation {
+    pub register: String,
+    pub trial: usize,
+    pub expected: bool,
+    pub actual: bool,
+}
+
+/// An output whose value at `width` differs from its value at the narrowest
+/// width of a sweep, both masked to the narrowest width.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WidthMismatch {
+    pub wire: String,
+    pub width: String,
+    pub expected: u128,
+    pub actual: u128,
+}
+
+/// One part of a partitioned circuit, with the wires it reads from the
+/// primary inputs or earlier parts and the wires it drives for the primary
+/// outputs or other parts.
+#[derive(Clone, Debug)]
+pub struct Partition {
+    pub gates: Vec<Gate>,
+    pub input_wires: Vec<String>,
+    pub output_wires: Vec<String>,
+}
+
+/// The gates being partitioned as a hypergraph, with a net per gate output
+/// read by other gates. A net costs one cross wire per part other than its
+/// driver's that reads it, and the cut size is the total cost.
+struct PartitionNets {
+    part_of: Vec<usize>,
+    sizes: Vec<usize>,
+    // Driver of each net.
+    drivers: Vec<usize>,
+    // Nets each gate is a pin of, and whether it reads the net.
+    pins: Vec<Vec<(usize, bool)>>,
+    // Number of readers of each net in each part.
+    readers: Vec<Vec<usize>>,
+}
+
+impl PartitionNets {
+    fn cost(&self, net: usize) -> usize {
+        let driver_part = self.part_of[self.drivers[net]];
+        self.readers[net]
+            .iter()
+            .enumerate()
+            .filter(|(part, count)| **count > 0 && *part != driver_part)
+            .count()
+    }
+
+    fn cut_size(&self) -> usize {
+        (0..self.drivers.len()).map(|net| self.cost(net)).sum()
+    }
+
+    fn move_gate(&mut self, gate: usize, part: usize) {
+        let from = self.part_of[gate];
+        for &(net, reads) in self.pins[gate].iter() {
+            if reads {
+                self.readers[net][from] -= 1;
+                self.readers[net][part] += 1;
+            }
+        }
+        self.sizes[from] -= 1;
+        self.sizes[part] += 1;
+        self.part_of[gate] = part;
+    }
+
+    /// How much moving `gate` to `part` would shrink the cut.
+    fn gain(&mut self, gate: usize, part: usize) -> isize {
+        let pin_cost = |nets: &Self| {
+            nets.pins[gate]
+                .iter()
+                .map(|&(net, _)| nets.cost(net) as isize)
+                .sum::<isize>()
+        };
+        let before = pin_cost(self);
+        let from = self.part_of[gate];
+        self.move_gate(gate, part);
+        let after = pin_cost(self);
+        self.move_gate(gate, from);
+
+        before - after
+    }
+}
+
+/// An arithmetic circuit lowered to boolean gates, where each word `w` is
+/// spread over the bits `w[0]` (the least significant) to `w[width - 1]`.
+#[derive(Clone, Debug)]
+pub struct BitBlasted {
+    pub gates: HashSet<Gate>,
+    pub wire_set: HashSet<String>,
+    pub input_wires: Vec<String>,
+    pub output_wires: Vec<String>,
+    pub dff_outputs: Vec<String>,
+    // The words of the outputs, before they were split into bits.
+    output_words: Vec<String>,
+    width: usize,
+}
+
+impl BitBlasted {
+    /// Split each word of `inputs` into its bits.
+    pub fn split_inputs(&self, inputs: &HashMap<String, PtxtType>) -> HashMap<String, PtxtType> {
+        inputs
+            .iter()
+            .filter(|(wire, _)| wire.as_str() != "dummy")
+            .flat_map(|(wire, value)| {
+                (0..self.width)
+                    .map(move |bit| (bit_wire(wire, bit), PtxtType::Bool(value.bit(bit))))
+            })
+            .collect()
+    }
+
+    /// Put the output words back together from the output bits in `outputs`.
+    pub fn join_outputs(
+        &self,
+        outputs: &HashMap<String, PtxtType>,
+        ptxt_type: &str,
+    ) -> HashMap<String, PtxtType> {
+        self.output_words
+            .iter()
+            .map(|word| {
+                let bits = (0..self.width)
+                    .map(|bit| outputs[&bit_wire(word, bit)] == PtxtType::Bool(true))
+                    .collect::<Vec<_>>();
+                (word.to_string(), PtxtType::from_bits(&bits, ptxt_type))
+            })
+            .collect()
+    }
+}
+
+fn bit_wire(word: &str, bit: usize) -> String {
+    format!("{}[{}]", word, bit)
+}
+
+/// How bit-blasted adders compute their carries, trading gates for depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AdderArchitecture {
+    /// One carry after the other: the fewest gates, depth linear in the width.
+    Ripple,
+    /// Kogge-Stone parallel prefix carries: depth logarithmic in the width.
+    CarryLookahead,
+    /// Ripple-carry blocks computed for both carries in, then selected.
+    CarrySelect,
+}
+
+// Bits per block of a carry-select adder.
+const CARRY_SELECT_BLOCK: usize = 4;
+
+/// Lowers arithmetic gates one at a time into the gates of `gates`.
+struct BitBlaster {
+    width: usize,
+    adder: AdderArchitecture,
+    gates: HashSet<Gate>,
+}
+
+impl BitBlaster {
+    fn push(&mut self, name: String, gate_type: GateType, inputs: Vec<String>, output: &str) {
+        self.gates.insert(Gate::new(
+            name,
+            gate_type,
+            inputs,
+            None,
+            output.to_owned(),
+            0,
+        ));
+    }
+
+    /// An internal wire of `gate`, driven by a gate of the same name.
+    fn node(&mut self, gate: &str, part: &str, gate_type: GateType, inputs: Vec<String>) -> String {
+        let wire = format!("{}_{}", gate, part);
+        self.push(wire.clone(), gate_type, inputs, &wire);
+        wire
+    }
+
+    fn constant(&mut self, value: bool) -> String {
+        let (wire, gate_type) = if value {
+            ("bit_blast_one", GateType::ConstOne)
+        } else {
+            ("bit_blast_zero", GateType::ConstZero)
+        };
+        self.push(wire.to_owned(), gate_type, vec![], wire);
+        wire.to_owned()
+    }
+
+    /// The bits of an operand, or constants for a numeric literal.
+    fn bits(&mut self, word: &str) -> Vec<String> {
+        match word.parse::<U256>() {
+            Ok(value) => (0..self.width)
+                .map(|bit| self.constant(PtxtType::U256(value).bit(bit)))
+                .collect(),
+            Err(_) => (0..self.width).map(|bit| bit_wire(word, bit)).collect(),
+        }
+    }
+
+    /// Adder of `a` and `b`, with an optional carry in, into `output`. The
+    /// carry out is dropped, so the sum wraps.
+    fn add(
+        &mut self,
+        gate: &str,
+        operands: (&[String], &[String]),
+        carry_in: Option<String>,
+        output: &[String],
+    ) {
+        match self.adder {
+            AdderArchitecture::Ripple => {
+                self.ripple_carry(gate, operands, carry_in, output, false);
+            }
+            AdderArchitecture::CarryLookahead => {
+                self.carry_lookahead(gate, operands, carry_in, output)
+            }
+            AdderArchitecture::CarrySelect => self.carry_select(gate, operands, carry_in, output),
+        }
+    }
+
+    /// Ripple-carry adder, which returns its carry out if `carry_out`.
+    fn ripple_carry(
+        &mut self,
+        gate: &str,
+        (a, b): (&[String], &[String]),
+        carry_in: Option<String>,
+        output: &[String],
+        carry_out: bool,
+    ) -> Option<String> {
+        let mut carry = carry_in;
+        for (bit, output) in output.iter().enumerate() {
+            let inputs = vec![a[bit].clone(), b[bit].clone()];
+            let propagate = match carry.take() {
+                None => {
+                    self.push(
+                        format!("{}_s{}", gate, bit),
+                        GateType::Xor,
+                        inputs.clone(),
+                        output,
+                    );
+                    None
+                }
+                Some(carry) => {
+                    let propagate =
+                        self.node(gate, &format!("p{}", bit), GateType::Xor, inputs.clone());
+                    self.push(
+                        format!("{}_s{}", gate, bit),
+                        GateType::Xor,
+                        vec![propagate.clone(), carry.clone()],
+                        output,
+                    );
+                    Some((propagate, carry))
+                }
+            };
+            if bit + 1 == a.len() && !carry_out {
+                break;
+            }
+            let generate = self.node(gate, &format!("g{}", bit), GateType::And, inputs);
+            carry = Some(match propagate {
+                None => generate,
+                Some((propagate, carry)) => {
+                    let through = self.node(
+                        gate,
+                        &format!("t{}", bit),
+                        GateType::And,
+                        vec![propagate, carry],
+                    );
+                    self.node(
+                        gate,
+                        &format!("c{}", bit),
+                        GateType::Or,
+                        vec![generate, through],
+                    )
+                }
+            });
+        }
+
+        carry
+    }
+
+    /// Carry-lookahead adder with Kogge-Stone prefixes: after the round with
+    /// distance d, the generate and propagate of bit i cover the 2d bits up to
+    /// i, so the carries take log2(width) rounds.
+    fn carry_lookahead(
+        &mut self,
+        gate: &str,
+        (a, b): (&[String], &[String]),
+        carry_in: Option<String>,
+        output: &[String],
+    ) {
+        let propagate = (0..a.len())
+            .map(|bit| {
+                let inputs = vec![a[bit].clone(), b[bit].clone()];
+                self.node(gate, &format!("p{}", bit), GateType::Xor, inputs)
+            })
+            .collect::<Vec<_>>();
+        let mut generate = (0..a.len())
+            .map(|bit| {
+                let inputs = vec![a[bit].clone(), b[bit].clone()];
+                self.node(gate, &format!("g{}", bit), GateType::And, inputs)
+            })
+            .collect::<Vec<_>>();
+        // The carry in is generated below bit 0.
+        if let Some(carry_in) = &carry_in {
+            let through = self.node(
+                gate,
+                "t",
+                GateType::And,
+                vec![propagate[0].clone(), carry_in.clone()],
+            );
+            generate[0] = self.node(gate, "gc", GateType::Or, vec![generate[0].clone(), through]);
+        }
+
+        let mut group_propagate = propagate.clone();
+        let mut distance = 1;
+        while distance < a.len() {
+            let (mut next_generate, mut next_propagate) =
+                (generate.clone(), group_propagate.clone());
+            for bit in distance..a.len() {
+                let inputs = vec![
+                    group_propagate[bit].clone(),
+                    generate[bit - distance].clone(),
+                ];
+                let through = self.node(
+                    gate,
+                    &format!("t{}_{}", distance, bit),
+                    GateType::And,
+                    inputs,
+                );
+                next_generate[bit] = self.node(
+                    gate,
+                    &format!("g{}_{}", distance, bit),
+                    GateType::Or,
+                    vec![generate[bit].clone(), through],
+                );
+                // The generates of the bits below twice the distance are final
+                // after this round, so their propagates are never read again.
+                if bit >= 2 * distance {
+                    let inputs = vec![
+                        group_propagate[bit].clone(),
+                        group_propagate[bit - distance].clone(),
+                    ];
+                    next_propagate[bit] = self.node(
+                        gate,
+                        &format!("p{}_{}", distance, bit),
+                        GateType::And,
+                        inputs,
+                    );
+                }
+            }
+            (generate, group_propagate) = (next_generate, next_propagate);
+            distance *= 2;
+        }
+
+        for (bit, output) in output.iter().enumerate() {
+            let carry = match bit {
+                0 => carry_in.clone(),
+                _ => Some(generate[bit - 1].clone()),
+            };
+            let name = format!("{}_s{}", gate, bit);
+            match carry {
+                Some(carry) => self.push(
+                    name,
+                    GateType::Xor,
+                    vec![propagate[bit].clone(), carry],
+                    output,
+                ),
+                None => self.push(name, GateType::Buf, vec![propagate[bit].clone()], output),
+            }
+        }
+    }
+
+    /// Carry-select adder: every block after the first adds its bits for
+    /// both carries in, and the carry out of the block below picks one.
+    fn carry_select(
+        &mut self,
+        gate: &str,
+        (a, b): (&[String], &[String]),
+        carry_in: Option<String>,
+        output: &[String],
+    ) {
+        let mut carry = carry_in;
+        for (block, start) in (0..a.len()).step_by(CARRY_SELECT_BLOCK).enumerate() {
+            let bits = start..std::cmp::min(start + CARRY_SELECT_BLOCK, a.len());
+            let last = bits.end == a.len();
+            let operands = (&a[bits.clone()], &b[bits.clone()]);
+            if block == 0 {
+                let name = format!("{}_k{}", gate, block);
+                carry = self.ripple_carry(&name, operands, carry, &output[bits], !last);
+                continue;
+            }
+            let carry_in = carry.take().unwrap();
+
+            let sums = [("z", None), ("o", Some(self.constant(true)))].map(|(case, carry)| {
+                let name = format!("{}_k{}{}", gate, block, case);
+                let sum = (0..bits.len())
+                    .map(|bit| format!("{}_s{}", name, bit))
+                    .collect::<Vec<_>>();
+                let carry = self.ripple_carry(&name, operands, carry, &sum, !last);
+                (sum, carry)
+            });
+            let [(zero_sum, zero_carry), (one_sum, one_carry)] = sums;
+            for (i, output) in output[bits.clone()].iter().enumerate() {
+                // sel = 1 picks the first input.
+                let inputs = vec![one_sum[i].clone(), zero_sum[i].clone(), carry_in.clone()];
+                self.push(
+                    format!("{}_k{}m{}", gate, block, i),
+                    GateType::Mux,
+                    inputs,
+                    output,
+                );
+            }
+            if let (Some(one_carry), Some(zero_carry)) = (one_carry, zero_carry) {
+                let inputs = vec![one_carry, zero_carry, carry_in];
+                carry = Some(self.node(gate, &format!("k{}c", block), GateType::Mux, inputs));
+            }
+        }
+    }
+
+    fn buffer(&mut self, gate: &str, bits: &[String], output: &[String]) {
+        for (bit, (input, output)) in bits.iter().zip(output).enumerate() {
+            self.push(
+                format!("{}_b{}", gate, bit),
+                GateType::Buf,
+                vec![input.clone()],
+                output,
+            );
+        }
+    }
+
+    fn lower(&mut self, gate: &Gate) {
+        let name = gate.get_gate_name();
+        let inputs = gate
+            .get_input_wires()
+            .iter()
+            .map(|wire| self.bits(wire))
+            .collect::<Vec<_>>();
+        let output = self.bits(&gate.get_output_wire());
+        let width = self.width;
+        match gate.get_gate_type() {
+            GateType::Add => self.add(&name, (&inputs[0], &inputs[1]), None, &output),
+            GateType::Sub => {
+                // a - b = a + !b + 1
+                let not_b = inputs[1]
+                    .iter()
+                    .enumerate()
+                    .map(|(bit, b)| {
+                        self.node(&name, &format!("n{}", bit), GateType::Not, vec![b.clone()])
+                    })
+                    .collect::<Vec<_>>();
+                let one = self.constant(true);
+                self.add(&name, (&inputs[0], &not_b), Some(one), &output);
+            }
+            GateType::Mult => {
+                // Shift and add: row j adds a * b[j] to the bits from j up.
+                let (a, b) = (&inputs[0], &inputs[1]);
+                let mut product = Vec::new();
+                for row in 0..width {
+                    let partial = (0..width - row)
+                        .map(|bit| {
+                            let inputs = vec![a[bit].clone(), b[row].clone()];
+                            self.node(&name, &format!("r{}_{}", row, bit), GateType::And, inputs)
+                        })
+                        .collect::<Vec<_>>();
+                    if row == 0 {
+                        product = partial;
+                        continue;
+                    }
+                    let sum = (row..width)
+                        .map(|bit| format!("{}_sum{}_{}", name, row, bit))
+                        .collect::<Vec<_>>();
+                    let adder = format!("{}_add{}", name, row);
+                    self.add(&adder, (&product[row..], &partial), None, &sum);
+                    product.truncate(row);
+                    product.extend(sum);
+                }
+                self.buffer(&name, &product, &output);
+            }
+            gate_type @ (GateType::Shl | GateType::Shr) => {
+                // A barrel shifter: stage k shifts by 2^k if bit k of the
+                // amount is set, so the amount is taken modulo the width.
+                let zero = self.constant(false);
+                let mut value = inputs[0].clone();
+                for stage in 0..width.trailing_zeros() as usize {
+                    let amount = 1 << stage;
+                    let select = inputs[1][stage].clone();
+                    let shifted = (0..width)
+                        .map(|bit| {
+                            let from = if gate_type == GateType::Shl {
+                                bit.checked_sub(amount)
+                            } else {
+                                Some(bit + amount).filter(|from| *from < width)
+                            };
+                            let shifted =
+                                from.map_or_else(|| zero.clone(), |from| value[from].clone());
+                            // sel = 1 picks the first input.
+                            let inputs = vec![shifted, value[bit].clone(), select.clone()];
+                            self.node(&name, &format!("x{}_{}", stage, bit), GateType::Mux, inputs)
+                        })
+                        .collect::<Vec<_>>();
+                    value = shifted;
+                }
+                self.buffer(&name, &value, &output);
+            }
+            GateType::Copy => self.buffer(&name, &inputs[0], &output),
+            GateType::MuxN => {
+                // A Boolean MUXN per bit, selected by the low bits of the index.
+                let select_width = (inputs.len() - 1).trailing_zeros() as usize;
+                let selected = (0..width)
+                    .map(|bit| {
+                        let mut mux_inputs = inputs[0][..select_width].to_vec();
+                        mux_inputs.extend(inputs[1..].iter().map(|data| data[bit].clone()));
+                        self.node(&name, &format!("m{}", bit), GateType::MuxN, mux_inputs)
+                    })
+                    .collect::<Vec<_>>();
+                self.buffer(&name, &selected, &output);
+            }
+            GateType::PriorityEncoder => {
+                // Boolean priority encoders grant the low bits of the
+                // requests, and each index bit ORs the grants with it set.
+                let requests = inputs
+                    .iter()
+                    .map(|request| request[0].clone())
+                    .collect::<Vec<_>>();
+                let last = requests.len() - 1;
+                let grants = (0..last)
+                    .map(|i| {
+                        let inputs = requests[i..].to_vec();
+                        self.node(&name, &format!("g{}", i), GateType::PriorityEncoder, inputs)
+                    })
+                    .chain([requests[last].clone()])
+                    .collect::<Vec<_>>();
+                let index = (0..width)
+                    .map(|bit| {
+                        let mut terms = grants
+                            .iter()
+                            .enumerate()
+                            .filter(|(i, _)| (i >> bit) & 1 == 1)
+                            .map(|(_, grant)| grant.clone());
+                        let Some(first) = terms.next() else {
+                            return self.constant(false);
+                        };
+                        terms.enumerate().fold(first, |index, (j, term)| {
+                            let part = format!("i{}_{}", bit, j);
+                            self.node(&name, &part, GateType::Or, vec![index, term])
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                self.buffer(&name, &index, &output);
+            }
+            gate_type => panic!(
+                "{}[!]{} Gate {} ({:?}) can't be bit-blasted.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                name,
+                gate_type
+            ),
+        }
+    }
+}
+
+/// A wire driven in part `from` and read in part `to`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CrossWire {
+    pub wire: String,
+    pub from: usize,
+    pub to: usize,
+}
+
+/// An edit of the parsed netlist, read from a `--patch` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatchEdit {
+    // type GATE TYPE: evaluate GATE as a TYPE gate on the same wires.
+    SetType {
+        gate: String,
+        gate_type: GateType,
+    },
+    // rewire GATE INDEX WIRE: input INDEX (from 0) of GATE reads WIRE.
+    Rewire {
+        gate: String,
+        input: usize,
+        wire: String,
+    },
+    // tie WIRE 0|1: WIRE is driven by a constant instead.
+    Tie {
+        wire: String,
+        value: bool,
+    },
+}
+
+/// A Boolean formula in conjunctive normal form, numbered as in DIMACS:
+/// variables start from 1 and a negative literal negates its variable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cnf {
+    // The wire of variable `i` is at index `i - 1`.
+    pub variables: Vec<String>,
+    pub clauses: Vec<Vec<i64>>,
+}
+
+impl Cnf {
+    /// Whether every clause holds when each wire has its value in
+    /// `assignment`. Wires missing from it are false.
+    pub fn is_satisfied_by(&self, assignment: &HashMap<String, bool>) -> bool {
+        self.clauses.iter().all(|clause| {
+            clause.iter().any(|&literal| {
+                let wire = &self.variables[literal.unsigned_abs() as usize - 1];
+                assignment.get(wire).copied().unwrap_or(false) == (literal > 0)
+            })
+        })
+    }
+}
+
 pub trait EvalCircuit<C> {
     fn encrypt_inputs(
         &mut self,
@@ -55,21 +836,54 @@ pub trait EvalCircuit<C> {
         enc_wire_map: &HashMap<String, C>,
         verbose: bool,
     ) -> HashMap<String, PtxtType>;
+
+    /// Forget the ciphertexts the gates computed so the next evaluation
+    /// starts afresh.
+    fn clear_encrypted_outputs(&mut self);
 }
 
 pub struct Circuit<'a> {
     gates: HashSet<Gate>,
-    input_wires: &'a Vec<String>,
-    output_wires: &'a Vec<String>,
-    dff_outputs: &'a Vec<String>,
+    // Borrowed from the parser, owned once circuits are merged.
+    input_wires: Cow<'a, Vec<String>>,
+    output_wires: Cow<'a, Vec<String>>,
+    dff_outputs: Cow<'a, Vec<String>>,
     ordered_gates: Vec<Gate>,
     level_map: HashMap<usize, Vec<Gate>>,
+    merge_count: usize,
+    // Initial values of the registers that don't start from zero.
+    register_init: HashMap<String, PtxtType>,
+    time_limit_per_gate: Option<Duration>,
+    bootstrap_budget: Option<usize>,
+    module_name: Option<String>,
+    // Outputs of merged registers, mapped to the register that replaced them.
+    register_aliases: HashMap<String, String>,
+    // Bootstraps spent by the encrypted evaluations so far, across cycles.
+    bootstraps_used: usize,
+    work_stealing: bool,
 }
 
 pub struct GateCircuit<'a> {
     circuit: Circuit<'a>,
     client_key: ClientKey,
     server_key: ServerKey,
+    input_encryptions: AtomicUsize,
+    constant_encryptions: AtomicUsize,
+    // Wires decrypted and printed as soon as they are computed.
+    checkpoints: HashSet<String>,
+    // Public inputs, encrypted trivially.
+    trivial_inputs: HashSet<String>,
+    output_stream: Option<OutputStream>,
+}
+
+/// CSV file that gets each primary output as soon as it is computed.
+struct OutputStream {
+    file_name: String,
+    writer: Mutex<csv::Writer<File>>,
+    // Outputs that no gate computes, e.g. inputs wired straight to an
+    // output, written at the end of each cycle.
+    undriven: Vec<String>,
+    outputs: HashSet<String>,
 }
 
 pub struct LutCircuit<'a> {
@@ -105,50 +919,867 @@ pub struct HighPrecisionLutCircuit<'a> {
     server_intkey: ServerKeyInt,
 }
 
+/// Toggle coverage of the gates of a circuit over a set of evaluations: a
+/// gate is toggled if its output was observed both at 0 and at 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coverage {
+    pub toggled: Vec<String>,
+    pub stuck: Vec<String>,
+}
+
+impl Coverage {
+    pub fn percentage(&self) -> f64 {
+        let total = self.toggled.len() + self.stuck.len();
+        if total == 0 {
+            return 100.0;
+        }
+
+        100.0 * self.toggled.len() as f64 / total as f64
+    }
+}
+
 fn is_numeric_string(s: &str) -> bool {
     s.chars().all(|c| c.is_ascii_digit())
 }
 
-impl<'a> Circuit<'a> {
-    pub fn new(
-        gates: HashSet<Gate>,
-        input_wires: &'a Vec<String>,
-        output_wires: &'a Vec<String>,
-        dff_outputs: &'a Vec<String>,
-    ) -> Circuit<'a> {
-        Circuit {
-            gates,
-            input_wires,
-            output_wires,
-            dff_outputs,
-            ordered_gates: Vec::new(),
-            level_map: HashMap::new(),
+/// Wires whose value changed between two evaluations of a circuit, sorted by
+/// name. The number of toggles is a standard proxy for switching power.
+pub fn toggled_wires(
+    previous: &HashMap<String, PtxtType>,
+    current: &HashMap<String, PtxtType>,
+) -> Vec<String> {
+    current
+        .iter()
+        .filter(|(wire, value)| matches!(previous.get(*wire), Some(prev) if prev != *value))
+        .map(|(wire, _)| wire.clone())
+        .sorted()
+        .collect()
+}
+
+/// The outputs of an encrypted evaluation that differ from the `expected`
+/// plaintext ones, sorted by wire. LUTs mode decrypts bits as integers, so
+/// there 1 matches true. Numbers of different widths only match with
+/// `loose_width`, e.g. `U8(5)` and `U32(5)`.
+pub fn compare_outputs(
+    expected: &HashMap<String, PtxtType>,
+    actual: &HashMap<String, PtxtType>,
+    loose_width: bool,
+) -> Vec<Mismatch> {
+    expected
+        .iter()
+        .sorted()
+        .filter_map(|(wire, expected)| {
+            let actual = actual.get(wire).copied().unwrap_or(PtxtType::None);
+            let matches = match (expected, actual) {
+                (PtxtType::Bool(bit), PtxtType::U64(value)) => value == *bit as u64,
+                _ if loose_width => expected.value_eq(&actual),
+                _ => *expected == actual,
+            };
+            (!matches).then(|| Mismatch {
+                vector: 0,
+                wire: wire.to_string(),
+                expected: *expected,
+                actual,
+            })
+        })
+        .collect()
+}
+
+/// Evaluate `cycles` cycles from the encrypted inputs `enc_wire_map` and
+/// decrypt the outputs, e.g. to repeat an evaluation and compare the results.
+/// The gates' cached ciphertexts are cleared first so every gate is
+/// evaluated again.
+pub fn evaluate_and_decrypt<C>(
+    circuit: &mut impl EvalCircuit<C>,
+    enc_wire_map: &HashMap<String, C>,
+    cycles: usize,
+    ptxt_type: &str,
+) -> HashMap<String, PtxtType> {
+    circuit.clear_encrypted_outputs();
+    let mut enc_wire_map = circuit.evaluate_encrypted(enc_wire_map, 1, ptxt_type);
+    for cycle in 2..=cycles {
+        enc_wire_map = circuit.evaluate_encrypted(&enc_wire_map, cycle, ptxt_type);
+    }
+
+    circuit.decrypt_outputs(&enc_wire_map, false)
+}
+
+/// Cut a sequential circuit at its registers: the flip-flops are removed,
+/// their outputs become primary inputs and their data inputs become primary
+/// outputs. What is left is the combinational part, evaluated in one cycle.
+pub fn cut_at_registers(
+    gates: HashSet<Gate>,
+    input_wires: &mut Vec<String>,
+    output_wires: &mut Vec<String>,
+    dff_outputs: &mut Vec<String>,
+) -> HashSet<Gate> {
+    let (dffs, gates): (HashSet<Gate>, HashSet<Gate>) = gates
+        .into_iter()
+        .partition(|gate| gate.get_gate_type() == GateType::Dff);
+    for dff in dffs.iter().sorted() {
+        let output = dff.get_output_wire();
+        if !input_wires.contains(&output) {
+            input_wires.push(output);
+        }
+        let data_input = &dff.get_input_wires()[0];
+        if !output_wires.contains(data_input) {
+            output_wires.push(data_input.to_string());
         }
     }
+    dff_outputs.clear();
 
-    // Topologically sort the gates
-    pub fn sort_circuit(&mut self) {
-        assert!(!self.gates.is_empty());
-        assert!(self.ordered_gates.is_empty());
-        let mut wire_status = HashSet::new();
-        self.input_wires.iter().for_each(|input| {
-            wire_status.insert(input.clone());
+    gates
+}
+
+/// Tie primary inputs to constants, e.g. from the case analysis of a
+/// constraints file. A port ties the input of that name or every bit of the
+/// bus; the tied inputs are driven by constant gates that get folded into the
+/// logic reading them.
+pub fn tie_inputs(
+    mut gates: HashSet<Gate>,
+    input_wires: &mut Vec<String>,
+    wire_set: &mut HashSet<String>,
+    constants: &HashMap<String, bool>,
+) -> HashSet<Gate> {
+    // Register outputs are inputs of the netlist too, but not ports.
+    let driven = gates
+        .iter()
+        .map(|gate| gate.get_output_wire())
+        .collect::<HashSet<_>>();
+    for (port, value) in constants.iter().sorted() {
+        let tied = input_wires
+            .iter()
+            .filter(|wire| !driven.contains(*wire))
+            .filter(|wire| {
+                *wire == port
+                    || wire
+                        .strip_prefix(port.as_str())
+                        .is_some_and(|bit| bit.starts_with('['))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if tied.is_empty() {
+            panic!(
+                "{}[!]{} Case analysis on \"{}\", which is not an input.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                port
+            );
+        }
+        input_wires.retain(|wire| !tied.contains(wire));
+        for wire in tied {
+            let gate_type = if *value {
+                GateType::ConstOne
+            } else {
+                GateType::ConstZero
+            };
+            gates.insert(Gate::new(
+                format!("case_analysis_{}", wire),
+                gate_type,
+                vec![],
+                None,
+                wire.clone(),
+                0,
+            ));
+            wire_set.insert(wire);
+        }
+    }
+
+    gates
+}
+
+fn patch_error(message: String) -> ! {
+    panic!(
+        "{}[!]{} {}.",
+        color::Fg(color::LightRed),
+        color::Fg(color::Reset),
+        message
+    )
+}
+
+/// Remove the gate named `name` to edit it.
+fn take_gate(gates: &mut HashSet<Gate>, name: &str) -> Gate {
+    let gate = gates
+        .iter()
+        .find(|gate| gate.get_gate_name() == name)
+        .cloned()
+        .unwrap_or_else(|| {
+            patch_error(format!(
+                "The patch edits gate \"{}\", which doesn't exist",
+                name
+            ))
         });
 
-        let mut dff_level = Vec::new();
-        let mut const_level = Vec::new();
-        while !self.gates.is_empty() {
-            // Temporary vector to store retained gates
-            let mut level = Vec::new();
-            let mut next_wire_status = HashSet::new();
-            let mut ready = false;
-            self.gates.retain(|gate| {
-                if gate.get_gate_type() == GateType::Dff {
-                    next_wire_status.insert(gate.get_output_wire());
-                    dff_level.push(gate.clone());
+    gates.take(&gate).unwrap()
+}
+
+/// Apply the edits of a patch file to the parsed gates, in order. A gate can
+/// be rewired to a gate output, a primary input or a tied wire. A tied wire
+/// loses its driver, or stops being a primary input.
+pub fn apply_patch(
+    mut gates: HashSet<Gate>,
+    input_wires: &mut Vec<String>,
+    wire_set: &HashSet<String>,
+    edits: &[PatchEdit],
+) -> HashSet<Gate> {
+    for edit in edits {
+        match edit {
+            PatchEdit::SetType { gate, gate_type } => {
+                let old_gate = take_gate(&mut gates, gate);
+                let num_inputs = old_gate.get_input_wires().len();
+                let fits = match gate_type {
+                    GateType::Not | GateType::Buf => num_inputs == 1,
+                    GateType::Mux => num_inputs == 3,
+                    GateType::And
+                    | GateType::Nand
+                    | GateType::Nor
+                    | GateType::Or
+                    | GateType::Xnor
+                    | GateType::Xor => num_inputs == 2,
+                    _ => false,
+                };
+                if old_gate.get_gate_type() == GateType::Dff || !fits {
+                    patch_error(format!(
+                        "Gate {} ({:?} with {} inputs) can't become a {:?} gate",
+                        gate,
+                        old_gate.get_gate_type(),
+                        num_inputs,
+                        gate_type
+                    ));
+                }
+                gates.insert(Gate::new(
+                    old_gate.get_gate_name(),
+                    gate_type.clone(),
+                    old_gate.get_input_wires().clone(),
+                    None,
+                    old_gate.get_output_wire(),
+                    0,
+                ));
+            }
+            PatchEdit::Rewire { gate, input, wire } => {
+                let mut new_gate = take_gate(&mut gates, gate);
+                let mut inputs = new_gate.get_input_wires().clone();
+                if *input >= inputs.len() {
+                    patch_error(format!(
+                        "Gate {} has no input {} (it has {})",
+                        gate,
+                        input,
+                        inputs.len()
+                    ));
+                }
+                let exists = wire_set.contains(wire)
+                    || input_wires.contains(wire)
+                    || gates.iter().any(|gate| gate.get_output_wire() == *wire);
+                if !exists {
+                    patch_error(format!(
+                        "The patch reads wire \"{}\", which doesn't exist",
+                        wire
+                    ));
+                }
+                inputs[*input] = wire.clone();
+                new_gate.set_input_wires(inputs);
+                gates.insert(new_gate);
+            }
+            PatchEdit::Tie { wire, value } => {
+                let driver = gates
+                    .iter()
+                    .find(|gate| gate.get_output_wire() == *wire)
+                    .cloned();
+                let gate_name = match driver {
+                    Some(driver) if driver.get_gate_type() == GateType::Dff => {
+                        patch_error(format!(
+                            "Wire \"{}\" is a register output, tie the data input of {} instead",
+                            wire,
+                            driver.get_gate_name()
+                        ))
+                    }
+                    Some(driver) => {
+                        gates.remove(&driver);
+                        driver.get_gate_name()
+                    }
+                    None if input_wires.contains(wire) => {
+                        input_wires.retain(|input| input != wire);
+                        format!("patch_tie_{}", wire)
+                    }
+                    None => patch_error(format!(
+                        "The patch ties wire \"{}\", which doesn't exist",
+                        wire
+                    )),
+                };
+                let gate_type = if *value {
+                    GateType::ConstOne
+                } else {
+                    GateType::ConstZero
+                };
+                gates.insert(Gate::new(
+                    gate_name,
+                    gate_type,
+                    vec![],
+                    None,
+                    wire.clone(),
+                    0,
+                ));
+            }
+        }
+    }
+
+    gates
+}
+
+/// The gates of one cycle with, for each one, the gates reading its output and
+/// the number of its inputs that are not ready yet.
+struct Dataflow<'g, 'e, C, F> {
+    gates: Vec<Mutex<&'g mut Gate>>,
+    dependents: Vec<Vec<usize>>,
+    pending: Vec<AtomicUsize>,
+    eval_values: &'e HashMap<String, Arc<RwLock<C>>>,
+    evaluate: F,
+}
+
+impl<'g, 'e, C, F> Dataflow<'g, 'e, C, F>
+where
+    C: Clone + Send + Sync,
+    F: Fn(&mut Gate, &[C]) -> C + Sync,
+{
+    /// Evaluate gate `i`, then spawn the gates it was the last input of.
+    fn run<'s>(&'s self, scope: &rayon::Scope<'s>, i: usize) {
+        let mut gate = self.gates[i].lock().unwrap();
+        let input_values: Vec<C> = gate
+            .get_input_wires()
+            .iter()
+            .map(|input| self.eval_values[input].read().unwrap().clone())
+            .collect();
+        let output_value = (self.evaluate)(&mut **gate, &input_values[..]);
+        *self.eval_values[&gate.get_output_wire()].write().unwrap() = output_value;
+        drop(gate);
+
+        for &dependent in self.dependents[i].iter() {
+            if self.pending[dependent].fetch_sub(1, Ordering::AcqRel) == 1 {
+                scope.spawn(move |scope| self.run(scope, dependent));
+            }
+        }
+    }
+}
+
+/// Evaluate one cycle with a work-stealing scheduler: every gate waits for
+/// the gates driving its inputs and is spawned on the rayon pool as soon as
+/// the last of them is done, so narrow levels don't leave cores idle. The
+/// registers are updated at the end, once every gate has read them.
+fn evaluate_work_stealing<C, F>(
+    gates: Vec<&mut Gate>,
+    eval_values: &HashMap<String, Arc<RwLock<C>>>,
+    evaluate: F,
+) where
+    C: Clone + Send + Sync,
+    F: Fn(&mut Gate, &[C]) -> C + Sync,
+{
+    let (dffs, gates): (Vec<_>, Vec<_>) = gates
+        .into_iter()
+        .partition(|gate| gate.get_gate_type() == GateType::Dff);
+    let drivers = gates
+        .iter()
+        .enumerate()
+        .map(|(i, gate)| (gate.get_output_wire(), i))
+        .collect::<HashMap<_, _>>();
+    let mut dependents = vec![Vec::new(); gates.len()];
+    let pending = gates
+        .iter()
+        .enumerate()
+        .map(|(i, gate)| {
+            let inputs = gate
+                .get_input_wires()
+                .iter()
+                .filter_map(|input| drivers.get(input).copied())
+                .unique()
+                .collect::<Vec<_>>();
+            for driver in inputs.iter() {
+                dependents[*driver].push(i);
+            }
+            AtomicUsize::new(inputs.len())
+        })
+        .collect::<Vec<_>>();
+    // Collect them before any gate runs and makes more of them ready.
+    let ready = pending
+        .iter()
+        .positions(|pending| pending.load(Ordering::Acquire) == 0)
+        .collect::<Vec<_>>();
+
+    let dataflow = Dataflow {
+        gates: gates.into_iter().map(Mutex::new).collect(),
+        dependents,
+        pending,
+        eval_values,
+        evaluate,
+    };
+    rayon::scope(|scope| {
+        for i in ready {
+            let dataflow = &dataflow;
+            scope.spawn(move |scope| dataflow.run(scope, i));
+        }
+    });
+
+    dffs.into_par_iter().for_each(|gate| {
+        let input_values: Vec<C> = gate
+            .get_input_wires()
+            .iter()
+            .map(|input| eval_values[input].read().unwrap().clone())
+            .collect();
+        let output_value = (dataflow.evaluate)(gate, &input_values[..]);
+        *eval_values[&gate.get_output_wire()].write().unwrap() = output_value;
+    });
+}
+
+impl<'a> Circuit<'a> {
+    pub fn new(
+        gates: HashSet<Gate>,
+        input_wires: &'a Vec<String>,
+        output_wires: &'a Vec<String>,
+        dff_outputs: &'a Vec<String>,
+    ) -> Circuit<'a> {
+        Circuit {
+            gates,
+            input_wires: Cow::Borrowed(input_wires),
+            output_wires: Cow::Borrowed(output_wires),
+            dff_outputs: Cow::Borrowed(dff_outputs),
+            ordered_gates: Vec::new(),
+            level_map: HashMap::new(),
+            merge_count: 0,
+            register_init: HashMap::new(),
+            time_limit_per_gate: None,
+            bootstrap_budget: None,
+            bootstraps_used: 0,
+            module_name: None,
+            register_aliases: HashMap::new(),
+            work_stealing: false,
+        }
+    }
+
+    /// The input wires other than the register outputs, which the circuit
+    /// sets to their initial values itself.
+    fn primary_inputs(&self) -> impl Iterator<Item = &String> {
+        self.input_wires
+            .iter()
+            .filter(|wire| !self.dff_outputs.contains(wire))
+    }
+
+    /// Name of the Verilog module, shown in the summary of the circuit.
+    pub fn set_module_name(&mut self, module_name: &str) {
+        self.module_name = Some(module_name.to_owned());
+    }
+
+    /// Evaluate each gate as soon as its inputs are ready instead of level by
+    /// level. Applies to the plaintext and gate backends; with a bootstrap
+    /// budget, the gate backend still goes level by level to check it.
+    pub fn set_work_stealing(&mut self, work_stealing: bool) {
+        self.work_stealing = work_stealing;
+    }
+
+    /// Abort the encrypted evaluation before the total number of bootstraps
+    /// goes over `budget`. Applies to the gate backend on the CPU.
+    pub fn set_bootstrap_budget(&mut self, budget: usize) {
+        self.bootstrap_budget = Some(budget);
+    }
+
+    /// Account for the bootstraps of the gates of `level`, or abort if they
+    /// don't fit in the remaining budget.
+    fn spend_bootstraps(
+        budget: Option<usize>,
+        used: &mut usize,
+        gates: &[Gate],
+        level: usize,
+        total_levels: usize,
+    ) {
+        let cost = gates
+            .iter()
+            .map(|gate| gate.bootstrap_count())
+            .sum::<usize>();
+        if let Some(budget) = budget {
+            if *used + cost > budget {
+                let error = CircuitError::BootstrapBudgetExceeded {
+                    budget,
+                    used: *used,
+                    level,
+                    total_levels,
+                };
+                panic!(
+                    "{}[!]{} {}.",
+                    color::Fg(color::LightRed),
+                    color::Fg(color::Reset),
+                    error
+                );
+            }
+        }
+        *used += cost;
+    }
+
+    /// Abort the encrypted evaluation if a single gate takes longer than
+    /// `time_limit` instead of hanging. Applies to the gate and LUT backends.
+    pub fn set_time_limit_per_gate(&mut self, time_limit: Duration) {
+        self.time_limit_per_gate = Some(time_limit);
+    }
+
+    /// Set the values the registers hold before the first cycle. Registers
+    /// missing from `init_values` start from zero.
+    pub fn set_register_init_values(&mut self, init_values: HashMap<String, PtxtType>) {
+        self.register_init = init_values;
+    }
+
+    /// Initial value of the register driving `wire`, as a bit.
+    fn register_init_bit(&self, wire: &str) -> bool {
+        self.register_init.get(wire) == Some(&PtxtType::Bool(true))
+    }
+
+    /// Splice `other` into this circuit. Each `(wire, input)` connection
+    /// drives the input `input` of `other` with the wire `wire` of this
+    /// circuit. All the other wires and gates of `other` are namespaced with
+    /// a `merge{N}.` prefix, N counting the merges into this circuit, so its
+    /// unconnected inputs and its outputs become `merge{N}.name`.
+    pub fn merge(&mut self, other: Circuit, connections: &[(String, String)]) {
+        // Make sure the sort circuit function hasn't run yet.
+        assert!(self.ordered_gates.is_empty() && self.level_map.is_empty());
+        assert!(other.ordered_gates.is_empty() && other.level_map.is_empty());
+
+        let own_wires = self.wire_set();
+        let mut renames = HashMap::new();
+        for (wire, input) in connections {
+            if !own_wires.contains(wire) && !self.input_wires.contains(wire) {
+                panic!(
+                    "{}[!]{} Wire \"{}\" not found in the circuit.",
+                    color::Fg(color::LightRed),
+                    color::Fg(color::Reset),
+                    wire
+                );
+            }
+            if !other.input_wires.contains(input) || other.dff_outputs.contains(input) {
+                panic!(
+                    "{}[!]{} \"{}\" is not an input of the merged circuit.",
+                    color::Fg(color::LightRed),
+                    color::Fg(color::Reset),
+                    input
+                );
+            }
+            renames.insert(input.to_string(), wire.to_string());
+        }
+
+        let namespace = format!("merge{}.", self.merge_count);
+        self.merge_count += 1;
+        let rename = |wire: &String| match renames.get(wire) {
+            Some(connected) => connected.to_string(),
+            // Numeric literals aren't wires.
+            None if wire.parse::<u32>().is_ok() => wire.to_string(),
+            None => format!("{}{}", namespace, wire),
+        };
+
+        for input in other.input_wires.iter() {
+            if !renames.contains_key(input) {
+                self.input_wires.to_mut().push(rename(input));
+            }
+        }
+        self.output_wires
+            .to_mut()
+            .extend(other.output_wires.iter().map(&rename));
+        self.dff_outputs
+            .to_mut()
+            .extend(other.dff_outputs.iter().map(&rename));
+        self.register_init.extend(
+            other
+                .register_init
+                .iter()
+                .map(|(wire, value)| (rename(wire), *value)),
+        );
+        self.register_aliases.extend(
+            other
+                .register_aliases
+                .iter()
+                .map(|(alias, wire)| (rename(alias), rename(wire))),
+        );
+        for gate in other.gates {
+            let input_wires = gate.get_input_wires().iter().map(&rename).collect();
+            self.gates.insert(Gate::new(
+                format!("{}{}", namespace, gate.get_gate_name()),
+                gate.get_gate_type(),
+                input_wires,
+                gate.get_lut_const(),
+                rename(&gate.get_output_wire()),
+                0,
+            ));
+        }
+    }
+
+    /// Output wires of all the gates of the circuit.
+    pub fn wire_set(&self) -> HashSet<String> {
+        self.all_gates()
+            .map(|gate| gate.get_output_wire())
+            .collect()
+    }
+
+    /// Run the rewrite passes on the parsed gates. Skip this to evaluate the
+    /// circuit exactly as parsed.
+    pub fn optimize(&mut self) {
+        // Make sure the sort circuit function hasn't run yet.
+        assert!(self.ordered_gates.is_empty() && self.level_map.is_empty());
+
+        self.fold_constants();
+        self.share_constants();
+        self.merge_equivalent_registers();
+    }
+
+    /// Keep a single register per data input and initial value. The gates
+    /// reading a merged register read the one that replaced it, and its output
+    /// wire is copied from it after every cycle so that its name is kept.
+    fn merge_equivalent_registers(&mut self) {
+        let mut kept = HashMap::new();
+        for gate in self
+            .gates
+            .iter()
+            .filter(|gate| gate.get_gate_type() == GateType::Dff)
+        {
+            let output = gate.get_output_wire();
+            let key = (
+                gate.get_input_wires()[0].to_string(),
+                self.register_init_bit(&output),
+            );
+            kept.entry(key)
+                .and_modify(|wire: &mut String| {
+                    *wire = std::cmp::min(wire.to_string(), output.clone())
+                })
+                .or_insert(output);
+        }
+
+        let mut renames = HashMap::new();
+        for gate in self
+            .gates
+            .iter()
+            .filter(|gate| gate.get_gate_type() == GateType::Dff)
+        {
+            let output = gate.get_output_wire();
+            let key = (
+                gate.get_input_wires()[0].to_string(),
+                self.register_init_bit(&output),
+            );
+            if kept[&key] != output {
+                renames.insert(output, kept[&key].to_string());
+            }
+        }
+        if renames.is_empty() {
+            return;
+        }
+
+        self.gates = self
+            .gates
+            .drain()
+            .filter(|gate| !renames.contains_key(&gate.get_output_wire()))
+            .map(|mut gate| {
+                let input_wires = gate
+                    .get_input_wires()
+                    .iter()
+                    .map(|input| renames.get(input).unwrap_or(input).to_string())
+                    .collect();
+                gate.set_input_wires(input_wires);
+                gate
+            })
+            .collect();
+        self.register_aliases.extend(renames);
+    }
+
+    /// Forget the ciphertexts cached in every gate.
+    fn clear_encrypted_outputs(&mut self) {
+        for gate in self.level_map.values_mut().flatten() {
+            gate.clear_encrypted_output();
+        }
+    }
+
+    /// Copy the value of each merged register from the one that replaced it.
+    fn copy_register_aliases<C: Clone>(&self, eval_values: &HashMap<String, Arc<RwLock<C>>>) {
+        for (alias, wire) in self.register_aliases.iter() {
+            let value = eval_values[wire].read().unwrap().clone();
+            *eval_values[alias].write().unwrap() = value;
+        }
+    }
+
+    /// Keep a single gate per constant value, so that all the gates reading a
+    /// constant share one trivial ciphertext instead of each constant gate
+    /// holding its own. Constants driving an output or a register keep their
+    /// own gate.
+    fn share_constants(&mut self) {
+        let mut shared = HashMap::new();
+        for gate in self.gates.iter().filter(|gate| gate.is_constant()) {
+            shared
+                .entry(gate.get_gate_type() == GateType::ConstOne)
+                .and_modify(|wire: &mut String| {
+                    *wire = std::cmp::min(wire.to_string(), gate.get_output_wire())
+                })
+                .or_insert_with(|| gate.get_output_wire());
+        }
+
+        let mut renames = HashMap::new();
+        for gate in self.gates.iter().filter(|gate| gate.is_constant()) {
+            let output = gate.get_output_wire();
+            let wire = &shared[&(gate.get_gate_type() == GateType::ConstOne)];
+            if output != *wire
+                && !self.output_wires.contains(&output)
+                && !self.dff_outputs.contains(&output)
+            {
+                renames.insert(output, wire.to_string());
+            }
+        }
+        if renames.is_empty() {
+            return;
+        }
+
+        self.gates = self
+            .gates
+            .drain()
+            .filter(|gate| !renames.contains_key(&gate.get_output_wire()))
+            .map(|mut gate| {
+                let input_wires = gate
+                    .get_input_wires()
+                    .iter()
+                    .map(|input| renames.get(input).unwrap_or(input).to_string())
+                    .collect();
+                gate.set_input_wires(input_wires);
+                gate
+            })
+            .collect();
+    }
+
+    /// Replace the gates whose inputs are all constant with constant gates,
+    /// and the muxes with a constant select with buffers. Constant gates that
+    /// end up driving nothing are dropped.
+    fn fold_constants(&mut self) {
+        let mut constants = self
+            .gates
+            .iter()
+            .filter_map(|gate| match gate.get_gate_type() {
+                GateType::ConstOne => Some((gate.get_output_wire(), true)),
+                GateType::ConstZero => Some((gate.get_output_wire(), false)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        if constants.is_empty() {
+            return;
+        }
+
+        loop {
+            let mut folded = Vec::new();
+            for gate in self.gates.iter() {
+                let foldable = matches!(
+                    gate.get_gate_type(),
+                    GateType::And
+                        | GateType::Nand
+                        | GateType::Or
+                        | GateType::Nor
+                        | GateType::Xor
+                        | GateType::Xnor
+                        | GateType::Not
+                        | GateType::Buf
+                        | GateType::Mux
+                        | GateType::MuxN
+                        | GateType::PriorityEncoder
+                );
+                if !foldable || constants.contains_key(&gate.get_output_wire()) {
+                    continue;
+                }
+
+                let input_values = gate
+                    .get_input_wires()
+                    .iter()
+                    .map(|input| constants.get(input).map(|value| PtxtType::Bool(*value)))
+                    .collect::<Option<Vec<_>>>();
+                if let Some(input_values) = input_values {
+                    let value = gate.clone().evaluate(&input_values) == PtxtType::Bool(true);
+                    folded.push(Gate::new(
+                        gate.get_gate_name(),
+                        if value {
+                            GateType::ConstOne
+                        } else {
+                            GateType::ConstZero
+                        },
+                        vec![],
+                        None,
+                        gate.get_output_wire(),
+                        0,
+                    ));
+                } else if gate.get_gate_type() == GateType::Mux {
+                    let input_wires = gate.get_input_wires();
+                    if let Some(select) = constants.get(&input_wires[2]) {
+                        // sel = 1 picks the first input.
+                        let input = if *select {
+                            &input_wires[0]
+                        } else {
+                            &input_wires[1]
+                        };
+                        folded.push(Gate::new(
+                            gate.get_gate_name(),
+                            GateType::Buf,
+                            vec![input.to_string()],
+                            None,
+                            gate.get_output_wire(),
+                            0,
+                        ));
+                    }
+                }
+            }
+            if folded.is_empty() {
+                break;
+            }
+
+            for gate in folded {
+                match gate.get_gate_type() {
+                    GateType::ConstOne => {
+                        constants.insert(gate.get_output_wire(), true);
+                    }
+                    GateType::ConstZero => {
+                        constants.insert(gate.get_output_wire(), false);
+                    }
+                    _ => {}
+                }
+                self.gates.replace(gate);
+            }
+        }
+
+        let read_wires = self
+            .gates
+            .iter()
+            .flat_map(|gate| gate.get_input_wires().clone())
+            .collect::<HashSet<_>>();
+        self.gates.retain(|gate| {
+            !constants.contains_key(&gate.get_output_wire())
+                || read_wires.contains(&gate.get_output_wire())
+                || self.output_wires.contains(&gate.get_output_wire())
+                || self.dff_outputs.contains(&gate.get_output_wire())
+        });
+    }
+
+    /// Number of gates in the circuit, whichever stage it is in.
+    pub fn gate_count(&self) -> usize {
+        self.all_gates().count()
+    }
+
+    // Topologically sort the gates
+    pub fn sort_circuit(&mut self) {
+        assert!(!self.gates.is_empty());
+        assert!(self.ordered_gates.is_empty());
+        let mut wire_status = HashSet::new();
+        self.input_wires.iter().for_each(|input| {
+            wire_status.insert(input.clone());
+        });
+
+        let mut dff_level = Vec::new();
+        let mut const_level = Vec::new();
+        while !self.gates.is_empty() {
+            // Temporary vector to store retained gates
+            let mut level = Vec::new();
+            let mut next_wire_status = HashSet::new();
+            let mut ready = false;
+            self.gates.retain(|gate| {
+                if gate.get_gate_type() == GateType::Dff {
+                    next_wire_status.insert(gate.get_output_wire());
+                    dff_level.push(gate.clone());
                     ready = true;
                 } else if gate.get_gate_type() == GateType::ConstOne
                     || gate.get_gate_type() == GateType::ConstZero
+                    || gate.get_gate_type() == GateType::ConstUnknown
                 {
                     next_wire_status.insert(gate.get_output_wire());
                     const_level.push(gate.clone());
@@ -172,6 +1803,9 @@ impl<'a> Circuit<'a> {
             level.sort();
             self.ordered_gates.extend(level);
         }
+        // Constants don't depend on anything, so they go first.
+        const_level.sort();
+        self.ordered_gates.splice(0..0, const_level);
         self.ordered_gates.extend(dff_level);
         // Remove all the gates after sorting is done. Use ordered_gates from
         // now on.
@@ -185,7 +1819,7 @@ impl<'a> Circuit<'a> {
         assert!(!self.ordered_gates.is_empty());
         // Initialization of input_wires to true
         let mut wire_levels = HashMap::new();
-        for input in self.input_wires {
+        for input in self.input_wires.iter() {
             wire_levels.insert(input.to_string(), 0);
         }
         for gate in &mut self.ordered_gates {
@@ -262,7 +1896,7 @@ impl<'a> Circuit<'a> {
             // wire_map.insert(key.clone(), PtxtType::Bool(false));
             wire_map.insert(key.clone(), PtxtType::None);
         }
-        for input_wire in self.input_wires {
+        for input_wire in self.primary_inputs() {
             // if no inputs are provided, initialize it to false
             if user_inputs.is_empty() {
                 match ptxt_type {
@@ -284,6 +1918,9 @@ impl<'a> Circuit<'a> {
                     "u128" => {
                         wire_map.insert(input_wire.to_string(), PtxtType::U128(0));
                     }
+                    "u256" => {
+                        wire_map.insert(input_wire.to_string(), PtxtType::U256(U256::default()));
+                    }
                     _ => unreachable!(),
                 }
             } else if !user_inputs.contains_key(input_wire) {
@@ -308,13 +1945,16 @@ impl<'a> Circuit<'a> {
                     "u128" => {
                         wire_map.insert(input_wire.to_string(), *user_value);
                     }
+                    "u256" => {
+                        wire_map.insert(input_wire.to_string(), *user_value);
+                    }
                     _ => unreachable!(),
                 }
             } else {
                 panic!("\n Input wire \"{}\" not in input wires!", input_wire);
             }
         }
-        for wire in self.dff_outputs {
+        for wire in self.dff_outputs.iter() {
             match ptxt_type {
                 "bool" => {
                     wire_map.insert(wire.to_string(), PtxtType::Bool(false));
@@ -334,23 +1974,909 @@ impl<'a> Circuit<'a> {
                 "u128" => {
                     wire_map.insert(wire.to_string(), PtxtType::U128(0));
                 }
+                "u256" => {
+                    wire_map.insert(wire.to_string(), PtxtType::U256(U256::default()));
+                }
                 _ => unreachable!(),
             }
         }
-        wire_map
+        // Registers with an initial value start from it.
+        for wire in self.dff_outputs.iter() {
+            if let Some(value) = self.register_init.get(wire) {
+                wire_map.insert(wire.to_string(), *value);
+            }
+        }
+        wire_map
+    }
+
+    pub fn print_level_map(&self) {
+        for level in self.level_map.keys().sorted() {
+            info_println!("Level {}:", level);
+            for gate in &self.level_map[level] {
+                info_println!("  {:?}", gate);
+            }
+        }
+    }
+
+    pub fn print_gate_truth_tables(&self) {
+        for gate in self.all_gates().sorted_by_key(|gate| gate.get_gate_name()) {
+            let Some(table) = gate.truth_table() else {
+                continue;
+            };
+            info_println!(
+                "{} {:?}({}) -> {}:",
+                gate.get_gate_name(),
+                gate.get_gate_type(),
+                gate.get_input_wires().join(", "),
+                gate.get_output_wire()
+            );
+            for (inputs, output) in table {
+                info_println!(
+                    "  {} | {}",
+                    inputs
+                        .iter()
+                        .map(|&input| if input { '1' } else { '0' })
+                        .collect::<String>(),
+                    output as u8
+                );
+            }
+        }
+    }
+
+    pub fn get_ordered_gates(&self) -> &Vec<Gate> {
+        &self.ordered_gates
+    }
+
+    /// Iterate over every gate regardless of whether the circuit has been
+    /// sorted and levelized yet.
+    fn all_gates(&self) -> impl Iterator<Item = &Gate> {
+        self.gates
+            .iter()
+            .chain(self.ordered_gates.iter())
+            .chain(self.level_map.values().flatten())
+    }
+
+    /// Evaluate one cycle in plaintext without touching the gates, starting
+    /// from the input wire values (DFF outputs default to their initial
+    /// value, or false). Returns the value of every wire, internal ones
+    /// included, with the registers holding their next state.
+    pub fn simulate_plaintext(
+        &self,
+        inputs: &HashMap<String, PtxtType>,
+    ) -> HashMap<String, PtxtType> {
+        // Make sure the compute_levels function has run.
+        assert!(!self.level_map.is_empty());
+
+        let mut wire_map = inputs.clone();
+        for wire in self.dff_outputs.iter() {
+            let init = PtxtType::Bool(self.register_init_bit(wire));
+            wire_map.entry(wire.to_string()).or_insert(init);
+        }
+        for input_wire in self.input_wires.iter() {
+            if !wire_map.contains_key(input_wire) {
+                panic!("\n Input wire \"{}\" not in input wires!", input_wire);
+            }
+        }
+
+        for level in self.level_map.keys().sorted() {
+            let outputs = self.level_map[level]
+                .par_iter()
+                .map(|gate| {
+                    let input_values: Vec<PtxtType> = gate
+                        .get_input_wires()
+                        .iter()
+                        .map(|input| wire_map[input])
+                        .collect();
+                    (gate.get_output_wire(), gate.clone().evaluate(&input_values))
+                })
+                .collect::<Vec<_>>();
+            wire_map.extend(outputs);
+        }
+        for (alias, wire) in self.register_aliases.iter() {
+            let value = wire_map[wire];
+            wire_map.insert(alias.to_string(), value);
+        }
+
+        wire_map
+    }
+
+    /// Plaintext value of the outputs after `cycles` cycles from `inputs`, to
+    /// check an encrypted evaluation against. As there, missing inputs are
+    /// zero and registers start from their initial value (zero in
+    /// arithmetic mode).
+    pub fn reference_outputs(
+        &self,
+        inputs: &HashMap<String, PtxtType>,
+        cycles: usize,
+        ptxt_type: &str,
+    ) -> HashMap<String, PtxtType> {
+        let zero = PtxtType::from_u128_wrapping(0, ptxt_type);
+        let mut wire_map = inputs.clone();
+        wire_map.remove("dummy");
+        for wire in self.input_wires.iter() {
+            if ptxt_type != "bool" || !self.dff_outputs.contains(wire) {
+                wire_map.entry(wire.to_string()).or_insert(zero);
+            }
+        }
+        for _ in 0..cycles {
+            wire_map = self.simulate_plaintext(&wire_map);
+        }
+
+        self.output_wires
+            .iter()
+            .map(|wire| (wire.to_string(), wire_map[wire]))
+            .collect()
+    }
+
+    /// Evaluate the circuit on each input vector and compare every wire
+    /// returned by the `reference` model against the circuit's value.
+    pub fn verify_against(
+        &self,
+        reference: impl Fn(&HashMap<String, PtxtType>) -> HashMap<String, PtxtType>,
+        vectors: &[HashMap<String, PtxtType>],
+    ) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        for (vector, inputs) in vectors.iter().enumerate() {
+            let wire_map = self.simulate_plaintext(inputs);
+            for (wire, expected) in reference(inputs).into_iter().sorted() {
+                let actual = wire_map.get(&wire).copied().unwrap_or(PtxtType::None);
+                if actual != expected {
+                    mismatches.push(Mismatch {
+                        vector,
+                        wire,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    /// Evaluate the circuit in plaintext once per width of `widths`
+    /// (narrowest first), with `inputs` truncated to each width, and compare
+    /// the outputs masked to the narrowest width.
+    pub fn sweep_widths(
+        &self,
+        inputs: &HashMap<String, PtxtType>,
+        widths: &[&str],
+    ) -> Vec<WidthMismatch> {
+        let bits = |width: &str| match width {
+            "bool" => 1,
+            _ => width
+                .trim_start_matches('u')
+                .parse::<u32>()
+                .unwrap_or_else(|_| panic!("Invalid plaintext width \"{}\"", width)),
+        };
+        let narrowest = widths[0];
+        let mask = match bits(narrowest) {
+            bits if bits >= u128::BITS => u128::MAX,
+            bits => (1 << bits) - 1,
+        };
+
+        let outputs_at = |width: &str| {
+            let inputs = inputs
+                .iter()
+                .map(|(wire, value)| {
+                    let value = PtxtType::from_u128_wrapping(value.low_u128(), width);
+                    (wire.to_string(), value)
+                })
+                .collect::<HashMap<_, _>>();
+            let wire_map = self.simulate_plaintext(&inputs);
+            self.output_wires
+                .iter()
+                .map(|wire| (wire.to_string(), wire_map[wire].low_u128() & mask))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let expected = outputs_at(narrowest);
+        let mut mismatches = Vec::new();
+        for width in widths.iter().skip(1) {
+            for (wire, actual) in outputs_at(width).into_iter().sorted() {
+                if actual != expected[&wire] {
+                    mismatches.push(WidthMismatch {
+                        wire,
+                        width: width.to_string(),
+                        expected: expected[&wire],
+                        actual,
+                    });
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    /// Split the gates into `parts` parts of about the same size with a
+    /// minimal cut, the number of wires read by another part than the one
+    /// driving them (one per reading part). The topological order is first
+    /// cut into runs, then refined by Fiduccia-Mattheyses passes: the gate
+    /// move with the highest gain is made, even if it grows the cut, until
+    /// every gate has moved once, and the pass keeps the moves up to the
+    /// smallest cut it went through. A part only reads gate outputs of
+    /// earlier parts (or registers), so the parts can be evaluated one after
+    /// the other. The cut size is the number of cross wires returned.
+    pub fn partition(&self, parts: usize) -> (Vec<Partition>, Vec<CrossWire>) {
+        // Make sure the compute_levels function has run.
+        assert!(!self.level_map.is_empty());
+        assert!(parts > 0);
+
+        let gates = self
+            .level_map
+            .keys()
+            .sorted()
+            .flat_map(|level| self.level_map[level].iter().sorted())
+            .collect::<Vec<_>>();
+        let parts = std::cmp::min(parts, gates.len());
+        let drivers = gates
+            .iter()
+            .enumerate()
+            .map(|(i, gate)| (gate.get_output_wire(), i))
+            .collect::<HashMap<_, _>>();
+
+        // A register's output is only read in the next cycle, so it connects
+        // gates without ordering them.
+        let mut fanin = vec![Vec::new(); gates.len()];
+        let mut fanout = vec![Vec::new(); gates.len()];
+        let mut readers = vec![Vec::new(); gates.len()];
+        for (i, gate) in gates.iter().enumerate() {
+            for input in gate.get_input_wires().iter().unique() {
+                if let Some(&driver) = drivers.get(input) {
+                    readers[driver].push(i);
+                    if gates[driver].get_gate_type() != GateType::Dff {
+                        fanin[i].push(driver);
+                        fanout[driver].push(i);
+                    }
+                }
+            }
+        }
+
+        let part_of = (0..gates.len())
+            .map(|i| i * parts / gates.len())
+            .collect::<Vec<_>>();
+        let mut nets = PartitionNets {
+            sizes: (0..parts)
+                .map(|part| part_of.iter().filter(|&&p| p == part).count())
+                .collect(),
+            part_of,
+            drivers: Vec::new(),
+            pins: vec![Vec::new(); gates.len()],
+            readers: Vec::new(),
+        };
+        for (driver, readers) in readers.iter().enumerate() {
+            if readers.is_empty() {
+                continue;
+            }
+            let net = nets.drivers.len();
+            nets.drivers.push(driver);
+            nets.pins[driver].push((net, false));
+            let mut counts = vec![0; parts];
+            for &reader in readers {
+                nets.pins[reader].push((net, true));
+                counts[nets.part_of[reader]] += 1;
+            }
+            nets.readers.push(counts);
+        }
+        let target_size = gates.len().div_ceil(parts);
+        let max_size = target_size + std::cmp::max(1, target_size / 10);
+
+        // The best move of a gate to a part that keeps the order and the
+        // balance of the parts, if any.
+        let best_move = |nets: &mut PartitionNets, gate: usize| {
+            let current = nets.part_of[gate];
+            if nets.sizes[current] <= 1 {
+                return None;
+            }
+            let earliest = fanin[gate].iter().map(|&j| nets.part_of[j]).max();
+            let latest = fanout[gate].iter().map(|&j| nets.part_of[j]).min();
+            (earliest.unwrap_or(0)..=latest.unwrap_or(parts - 1))
+                .filter(|&part| part != current && nets.sizes[part] < max_size)
+                .map(|part| (nets.gain(gate, part), std::cmp::Reverse(part)))
+                .max()
+                .map(|(gain, std::cmp::Reverse(part))| (gain, part))
+        };
+
+        // Every pass shrinks the cut, so this ends.
+        let mut cut_size = nets.cut_size() as isize;
+        loop {
+            let mut locked = vec![false; gates.len()];
+            let mut candidates = BinaryHeap::new();
+            for gate in 0..gates.len() {
+                if let Some((gain, part)) = best_move(&mut nets, gate) {
+                    candidates.push((gain, std::cmp::Reverse(gate), part));
+                }
+            }
+
+            let mut moves = Vec::new();
+            let (mut pass_cut, mut best_cut, mut best_moves) = (cut_size, cut_size, 0);
+            while let Some((gain, std::cmp::Reverse(gate), part)) = candidates.pop() {
+                if locked[gate] {
+                    continue;
+                }
+                // Gains change as the neighbours move, so check it is current.
+                match best_move(&mut nets, gate) {
+                    Some(current) if current == (gain, part) => (),
+                    Some((gain, part)) => {
+                        candidates.push((gain, std::cmp::Reverse(gate), part));
+                        continue;
+                    }
+                    None => continue,
+                }
+
+                moves.push((gate, nets.part_of[gate]));
+                nets.move_gate(gate, part);
+                locked[gate] = true;
+                pass_cut -= gain;
+                if pass_cut < best_cut {
+                    (best_cut, best_moves) = (pass_cut, moves.len());
+                }
+                for &(net, _) in nets.pins[gate].clone().iter() {
+                    let driver = nets.drivers[net];
+                    for neighbour in std::iter::once(driver).chain(readers[driver].iter().copied())
+                    {
+                        if locked[neighbour] {
+                            continue;
+                        }
+                        if let Some((gain, part)) = best_move(&mut nets, neighbour) {
+                            candidates.push((gain, std::cmp::Reverse(neighbour), part));
+                        }
+                    }
+                }
+            }
+
+            for &(gate, part) in moves[best_moves..].iter().rev() {
+                nets.move_gate(gate, part);
+            }
+            if best_cut >= cut_size {
+                break;
+            }
+            cut_size = best_cut;
+        }
+        let part_of = nets.part_of;
+
+        let mut partitions = vec![
+            Partition {
+                gates: Vec::new(),
+                input_wires: Vec::new(),
+                output_wires: Vec::new(),
+            };
+            parts
+        ];
+        for (gate, &part) in gates.iter().zip(&part_of) {
+            partitions[part].gates.push((*gate).clone());
+        }
+
+        let mut cross_wires = Vec::new();
+        for (part, partition) in partitions.iter_mut().enumerate() {
+            partition.input_wires = partition
+                .gates
+                .iter()
+                .flat_map(|gate| gate.get_input_wires())
+                .filter(|wire| wire.parse::<u32>().is_err())
+                .filter(
+                    |wire| !matches!(drivers.get(*wire), Some(&driver) if part_of[driver] == part),
+                )
+                .unique()
+                .sorted()
+                .cloned()
+                .collect();
+            for wire in partition.input_wires.iter() {
+                if let Some(&driver) = drivers.get(wire) {
+                    cross_wires.push(CrossWire {
+                        wire: wire.to_string(),
+                        from: part_of[driver],
+                        to: part,
+                    });
+                }
+            }
+        }
+        cross_wires.sort();
+
+        for (part, partition) in partitions.iter_mut().enumerate() {
+            partition.output_wires = partition
+                .gates
+                .iter()
+                .map(|gate| gate.get_output_wire())
+                .filter(|wire| {
+                    self.output_wires.contains(wire)
+                        || cross_wires
+                            .iter()
+                            .any(|cross_wire| cross_wire.from == part && &cross_wire.wire == wire)
+                })
+                .sorted()
+                .collect();
+        }
+
+        (partitions, cross_wires)
+    }
+
+    /// The transitive fan-in of `wire`, in topological order, cut at the
+    /// primary inputs and the register outputs, which become the inputs of
+    /// the cone.
+    pub fn cone(&self, wire: &str) -> Partition {
+        // Make sure the compute_levels function has run.
+        assert!(!self.level_map.is_empty());
+
+        let gates = self
+            .level_map
+            .keys()
+            .sorted()
+            .flat_map(|level| self.level_map[level].iter().sorted())
+            .collect::<Vec<_>>();
+        let drivers = gates
+            .iter()
+            .filter(|gate| gate.get_gate_type() != GateType::Dff)
+            .map(|gate| (gate.get_output_wire(), *gate))
+            .collect::<HashMap<_, _>>();
+
+        let mut cone_gates = HashSet::new();
+        let mut input_wires = BTreeSet::new();
+        let mut pending = vec![wire.to_owned()];
+        while let Some(wire) = pending.pop() {
+            match drivers.get(&wire) {
+                Some(gate) => {
+                    if cone_gates.insert(gate.get_gate_name()) {
+                        pending.extend(gate.get_input_wires().iter().cloned());
+                    }
+                }
+                None if wire.parse::<u32>().is_err() => {
+                    input_wires.insert(wire);
+                }
+                None => {}
+            }
+        }
+
+        Partition {
+            gates: gates
+                .into_iter()
+                .filter(|gate| cone_gates.contains(&gate.get_gate_name()))
+                .cloned()
+                .collect(),
+            input_wires: input_wires.into_iter().collect(),
+            output_wires: vec![wire.to_owned()],
+        }
+    }
+
+    /// Lower the arithmetic gates to boolean gates on `width`-bit words, with
+    /// adders of the given architecture, so the circuit also runs on the
+    /// boolean backend. Only combinational
+    /// circuits of additions, subtractions, multiplications, shifts and copies
+    /// are lowered.
+    pub fn bit_blast(&self, width: usize, adder: AdderArchitecture) -> BitBlasted {
+        let mut blaster = BitBlaster {
+            width,
+            adder,
+            gates: HashSet::new(),
+        };
+        for gate in self.all_gates().sorted() {
+            blaster.lower(gate);
+        }
+
+        let bits = |words: &[String]| {
+            words
+                .iter()
+                .flat_map(|word| (0..width).map(move |bit| bit_wire(word, bit)))
+                .collect::<Vec<_>>()
+        };
+        BitBlasted {
+            wire_set: blaster
+                .gates
+                .iter()
+                .map(|gate| gate.get_output_wire())
+                .collect(),
+            gates: blaster.gates,
+            input_wires: bits(&self.input_wires),
+            output_wires: bits(&self.output_wires),
+            dff_outputs: bits(&self.dff_outputs),
+            output_words: self.output_wires.to_vec(),
+            width,
+        }
+    }
+
+    /// Check that asserting `reset_wire` for one cycle brings every register
+    /// to its initial value. The registers start from `trials` random states
+    /// and from the complement of each, so a register that ignores the reset
+    /// is caught whatever its initial value. Other inputs keep their values
+    /// from `inputs`. Reports the first violation of each register.
+    pub fn test_reset(
+        &self,
+        reset_wire: &str,
+        inputs: &HashMap<String, PtxtType>,
+        trials: usize,
+    ) -> Vec<ResetViolation> {
+        if !self.input_wires.iter().any(|wire| wire == reset_wire)
+            || self.dff_outputs.iter().any(|wire| wire == reset_wire)
+        {
+            panic!(
+                "{}[!]{} Reset wire \"{}\" is not a primary input.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                reset_wire
+            );
+        }
+
+        let mut violations: Vec<ResetViolation> = Vec::new();
+        let mut state: HashMap<String, bool> = HashMap::new();
+        for trial in 0..2 * trials {
+            // Odd trials invert the state of the trial before them.
+            state = self
+                .dff_outputs
+                .iter()
+                .map(|wire| {
+                    let value = if trial % 2 == 0 {
+                        rand::random::<bool>()
+                    } else {
+                        !state[wire]
+                    };
+                    (wire.to_string(), value)
+                })
+                .collect();
+            let mut wire_map = self
+                .input_wires
+                .iter()
+                .map(|wire| (wire.to_string(), PtxtType::Bool(false)))
+                .collect::<HashMap<_, _>>();
+            wire_map.extend(
+                inputs
+                    .iter()
+                    .map(|(wire, value)| (wire.to_string(), *value)),
+            );
+            wire_map.insert(reset_wire.to_string(), PtxtType::Bool(true));
+            wire_map.extend(
+                state
+                    .iter()
+                    .map(|(wire, value)| (wire.to_string(), PtxtType::Bool(*value))),
+            );
+
+            let wire_map = self.simulate_plaintext(&wire_map);
+            for register in self.dff_outputs.iter().sorted() {
+                let expected = self.register_init_bit(register);
+                let actual = wire_map[register] == PtxtType::Bool(true);
+                if actual != expected && !violations.iter().any(|v| &v.register == register) {
+                    violations.push(ResetViolation {
+                        register: register.to_string(),
+                        trial,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Tseitin encoding of the combinational logic as CNF: one variable per
+    /// wire and a few clauses per gate that hold iff the output of the gate
+    /// matches its inputs. Registers are cut, so their outputs are free
+    /// variables, and so are unknown constants.
+    pub fn to_cnf(&self) -> Cnf {
+        let gates = self
+            .all_gates()
+            .filter(|gate| gate.get_gate_type() != GateType::Dff)
+            .sorted_by_key(|gate| gate.get_gate_name())
+            .collect::<Vec<_>>();
+        if let Some(gate) = gates
+            .iter()
+            .find(|gate| gate.get_gate_type().is_arithmetic())
+        {
+            panic!(
+                "{}[!]{} Arithmetic gate {} has no CNF encoding.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                gate.get_gate_name()
+            );
+        }
+
+        let variables = gates
+            .iter()
+            .flat_map(|gate| {
+                gate.get_input_wires()
+                    .iter()
+                    .cloned()
+                    .chain([gate.get_output_wire()])
+                    .collect::<Vec<_>>()
+            })
+            .unique()
+            .sorted()
+            .collect::<Vec<_>>();
+        let index = variables
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| (wire.as_str(), i as i64 + 1))
+            .collect::<HashMap<_, _>>();
+
+        let mut clauses = vec![];
+        for gate in gates {
+            let output = index[gate.get_output_wire().as_str()];
+            let inputs = gate
+                .get_input_wires()
+                .iter()
+                .map(|wire| index[wire.as_str()])
+                .collect::<Vec<_>>();
+            match gate.get_gate_type() {
+                // output = AND(inputs), or its negation for NAND.
+                GateType::And | GateType::Nand => {
+                    let output = if gate.get_gate_type() == GateType::And {
+                        output
+                    } else {
+                        -output
+                    };
+                    clauses.extend(inputs.iter().map(|&input| vec![-output, input]));
+                    clauses.push(inputs.iter().map(|&input| -input).chain([output]).collect());
+                }
+                // output = OR(inputs), or its negation for NOR.
+                GateType::Or | GateType::Nor => {
+                    let output = if gate.get_gate_type() == GateType::Or {
+                        output
+                    } else {
+                        -output
+                    };
+                    clauses.extend(inputs.iter().map(|&input| vec![output, -input]));
+                    clauses.push(inputs.iter().copied().chain([-output]).collect());
+                }
+                GateType::Buf => {
+                    clauses.push(vec![-output, inputs[0]]);
+                    clauses.push(vec![output, -inputs[0]]);
+                }
+                GateType::Not => {
+                    clauses.push(vec![output, inputs[0]]);
+                    clauses.push(vec![-output, -inputs[0]]);
+                }
+                GateType::ConstOne => clauses.push(vec![output]),
+                GateType::ConstZero => clauses.push(vec![-output]),
+                GateType::ConstUnknown => {}
+                // One clause per row of the truth table, ruling out the
+                // wrong output for that row.
+                _ => {
+                    for (row, value) in gate.truth_table().unwrap() {
+                        clauses.push(
+                            inputs
+                                .iter()
+                                .zip(row)
+                                .map(|(&input, bit)| if bit { -input } else { input })
+                                .chain([if value { output } else { -output }])
+                                .collect(),
+                        );
+                    }
+                }
+            }
+        }
+
+        Cnf { variables, clauses }
+    }
+
+    /// Number of levels on the critical path of one cycle (DFFs excluded).
+    pub fn depth(&self) -> usize {
+        // Make sure the compute_levels function has run.
+        assert!(!self.level_map.is_empty());
+
+        self.level_map
+            .iter()
+            .filter(|(_, gates)| gates.iter().any(|g| g.get_gate_type() != GateType::Dff))
+            .map(|(level, _)| *level)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Evaluate the gates one at a time in the order of `schedule`, which
+    /// names every gate but the registers. A gate may only read the inputs
+    /// and the wires of the gates before it, so the order must be
+    /// topological. The registers still update at the end of the cycle.
+    pub fn set_schedule(&mut self, schedule: &[String]) -> Result<(), CircuitError> {
+        // Make sure the compute_levels function has run.
+        assert!(!self.level_map.is_empty());
+
+        let invalid = |gate: &str, reason: &str| CircuitError::InvalidSchedule {
+            gate: gate.to_owned(),
+            reason: reason.to_owned(),
+        };
+        let mut gates = self
+            .level_map
+            .values()
+            .flatten()
+            .map(|gate| (gate.get_gate_name(), gate.clone()))
+            .collect::<HashMap<_, _>>();
+        let mut computed = self.input_wires.iter().cloned().collect::<HashSet<_>>();
+        let mut scheduled = Vec::new();
+        for name in schedule {
+            let Some(gate) = gates.remove(name) else {
+                let listed = scheduled
+                    .iter()
+                    .any(|gate: &Gate| &gate.get_gate_name() == name);
+                let reason = if listed {
+                    "it is listed twice"
+                } else {
+                    "it is not a gate of the circuit"
+                };
+                return Err(invalid(name, reason));
+            };
+            if gate.get_gate_type() == GateType::Dff {
+                return Err(invalid(
+                    name,
+                    "it is a register, which updates at the end of the cycle",
+                ));
+            }
+            if let Some(wire) = gate
+                .get_input_wires()
+                .iter()
+                .find(|wire| !computed.contains(*wire) && wire.parse::<u32>().is_err())
+            {
+                return Err(invalid(
+                    name,
+                    &format!("it reads {} before it is computed", wire),
+                ));
+            }
+            computed.insert(gate.get_output_wire());
+            scheduled.push(gate);
+        }
+        if let Some((name, _)) = gates
+            .iter()
+            .filter(|(_, gate)| gate.get_gate_type() != GateType::Dff)
+            .min_by_key(|(name, _)| *name)
+        {
+            return Err(invalid(name, "it is missing from the schedule"));
+        }
+
+        self.level_map.clear();
+        let registers = gates.into_values().sorted().collect::<Vec<_>>();
+        for (level, mut gate) in (1..).zip(scheduled) {
+            gate.set_level(level);
+            self.level_map.insert(level, vec![gate]);
+        }
+        if !registers.is_empty() {
+            let level = self.level_map.len() + 1;
+            let registers = registers
+                .into_iter()
+                .map(|mut gate| {
+                    gate.set_level(level);
+                    gate
+                })
+                .collect();
+            self.level_map.insert(level, registers);
+        }
+
+        Ok(())
+    }
+
+    pub fn check_max_depth(&self, max_depth: usize) -> Result<(), CircuitError> {
+        let depth = self.depth();
+        if depth > max_depth {
+            return Err(CircuitError::MaxDepthExceeded { depth, max_depth });
+        }
+
+        Ok(())
+    }
+
+    /// Check that the operands and the output of every arithmetic gate have
+    /// the same width. Wires declared without a range take the width of the
+    /// gates they connect, copy gates are the only casts, and shift amounts
+    /// can have any width.
+    pub fn check_widths(
+        &self,
+        declared_widths: &HashMap<String, usize>,
+    ) -> Result<(), CircuitError> {
+        let gates = self
+            .all_gates()
+            .filter(|gate| {
+                gate.get_gate_type().is_arithmetic() && gate.get_gate_type() != GateType::Copy
+            })
+            .sorted_by_key(|gate| gate.get_gate_name())
+            .collect::<Vec<_>>();
+
+        let mut widths = declared_widths.clone();
+        loop {
+            let mut inferred = false;
+            for gate in &gates {
+                let mut wires = gate.get_input_wires().clone();
+                if matches!(gate.get_gate_type(), GateType::Shl | GateType::Shr) {
+                    wires.truncate(1);
+                }
+                wires.push(gate.get_output_wire());
+
+                let mut known = wires
+                    .iter()
+                    .filter_map(|wire| widths.get(wire).map(|width| (wire, *width)));
+                let Some((wire, width)) = known.next() else {
+                    continue;
+                };
+                if let Some((other_wire, other_width)) = known.find(|(_, other)| *other != width) {
+                    return Err(CircuitError::WidthMismatch {
+                        gate: gate.get_gate_name(),
+                        wire: wire.clone(),
+                        width,
+                        other_wire: other_wire.clone(),
+                        other_width,
+                    });
+                }
+                for wire in wires {
+                    if let Entry::Vacant(entry) = widths.entry(wire) {
+                        entry.insert(width);
+                        inferred = true;
+                    }
+                }
+            }
+
+            if !inferred {
+                return Ok(());
+            }
+        }
     }
 
-    pub fn print_level_map(&self) {
+    /// Number of gates each wire drives.
+    pub fn fanout_map(&self) -> HashMap<String, usize> {
+        let mut fanouts = HashMap::new();
+        for gate in self.all_gates() {
+            for input in gate.get_input_wires().iter().unique() {
+                *fanouts.entry(input.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        fanouts
+    }
+
+    /// Toggle coverage of the gates over the wire maps of several evaluations,
+    /// e.g. one per cycle or per input vector.
+    pub fn coverage(&self, snapshots: &[HashMap<String, PtxtType>]) -> Coverage {
+        let (toggled, stuck) = self
+            .all_gates()
+            .filter(|gate| !gate.get_gate_type().is_arithmetic())
+            .sorted_by_key(|gate| gate.get_gate_name())
+            .partition::<Vec<_>, _>(|gate| {
+                let observed = |value| {
+                    snapshots
+                        .iter()
+                        .any(|wire_map| wire_map.get(&gate.get_output_wire()) == Some(&value))
+                };
+                observed(PtxtType::Bool(false)) && observed(PtxtType::Bool(true))
+            });
+
+        Coverage {
+            toggled: toggled.iter().map(|gate| gate.get_gate_name()).collect(),
+            stuck: stuck.iter().map(|gate| gate.get_gate_name()).collect(),
+        }
+    }
+
+    /// Number of gates driven by `wire`.
+    pub fn fanout(&self, wire: &str) -> usize {
+        self.all_gates()
+            .filter(|gate| gate.get_input_wires().iter().any(|input| input == wire))
+            .count()
+    }
+
+    /// Total number of bootstraps needed to evaluate one cycle.
+    pub fn count_bootstraps(&self) -> usize {
+        self.all_gates().map(|gate| gate.bootstrap_count()).sum()
+    }
+
+    /// Number of bootstraps along the most expensive path of one cycle. With
+    /// enough threads, this is what bounds the latency of the evaluation.
+    pub fn count_critical_bootstraps(&self) -> usize {
+        // Make sure the compute_levels function has run.
+        assert!(!self.level_map.is_empty());
+
+        let mut wire_bootstraps: HashMap<String, usize> = HashMap::new();
+        let mut critical_bootstraps = 0;
         for level in self.level_map.keys().sorted() {
-            println!("Level {}:", level);
             for gate in &self.level_map[level] {
-                println!("  {:?}", gate);
+                // DFF outputs start a new path in the next cycle.
+                if gate.get_gate_type() == GateType::Dff {
+                    continue;
+                }
+                let bootstraps = gate
+                    .get_input_wires()
+                    .iter()
+                    .map(|input| wire_bootstraps.get(input).copied().unwrap_or(0))
+                    .max()
+                    .unwrap_or(0)
+                    + gate.bootstrap_count();
+                critical_bootstraps = std::cmp::max(critical_bootstraps, bootstraps);
+                wire_bootstraps.insert(gate.get_output_wire(), bootstraps);
             }
         }
-    }
 
-    pub fn get_ordered_gates(&self) -> &Vec<Gate> {
-        &self.ordered_gates
+        critical_bootstraps
     }
 
     pub fn evaluate(&mut self, wire_map: &HashMap<String, PtxtType>) -> HashMap<String, PtxtType> {
@@ -364,23 +2890,32 @@ impl<'a> Circuit<'a> {
             .map(|(key, &value)| (key.clone(), Arc::new(RwLock::new(value))))
             .collect::<HashMap<_, _>>();
 
-        // For each level
-        for (_level, gates) in self.level_map.iter_mut().sorted_by_key(|(level, _)| *level) {
-            // Evaluate all the gates in the level in parallel
-            gates.par_iter_mut().for_each(|gate| {
-                let input_values: Vec<PtxtType> = gate
-                    .get_input_wires()
-                    .iter()
-                    .map(|input| *eval_values[input].read().unwrap())
-                    .collect();
-
-                // Update the value of the corresponding key
-                *eval_values[&gate.get_output_wire()]
-                    .write()
-                    .expect("Failed to acquire write lock") = gate.evaluate(&input_values);
+        if self.work_stealing {
+            let gates = self.level_map.values_mut().flatten().collect();
+            evaluate_work_stealing(gates, &eval_values, |gate, input_values| {
+                gate.evaluate(input_values)
             });
+        } else {
+            // For each level
+            for (_level, gates) in self.level_map.iter_mut().sorted_by_key(|(level, _)| *level) {
+                // Evaluate all the gates in the level in parallel
+                gates.par_iter_mut().for_each(|gate| {
+                    let input_values: Vec<PtxtType> = gate
+                        .get_input_wires()
+                        .iter()
+                        .map(|input| *eval_values[input].read().unwrap())
+                        .collect();
+
+                    // Update the value of the corresponding key
+                    *eval_values[&gate.get_output_wire()]
+                        .write()
+                        .expect("Failed to acquire write lock") = gate.evaluate(&input_values);
+                });
+            }
         }
 
+        self.copy_register_aliases(&eval_values);
+
         // Convert eval_values to the expected return type
         eval_values
             .iter()
@@ -389,13 +2924,418 @@ impl<'a> Circuit<'a> {
     }
 }
 
+/// A short summary of the circuit: its I/O counts, registers, and how many
+/// gates of each type it has.
+impl<'a> fmt::Display for Circuit<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut histogram = BTreeMap::new();
+        for gate in self.all_gates() {
+            *histogram
+                .entry(format!("{:?}", gate.get_gate_type()))
+                .or_insert(0) += 1;
+        }
+        let primary_inputs = self.primary_inputs().count();
+
+        writeln!(
+            f,
+            "Circuit {}",
+            self.module_name.as_deref().unwrap_or("(unnamed)")
+        )?;
+        writeln!(f, "  Inputs: {}", primary_inputs)?;
+        writeln!(f, "  Outputs: {}", self.output_wires.len())?;
+        writeln!(f, "  Registers: {}", self.dff_outputs.len())?;
+        write!(f, "  Gates: {}", self.gate_count())?;
+        for (gate_type, count) in histogram.iter() {
+            write!(f, "\n    {}: {}", gate_type, count)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl<'a> GateCircuit<'a> {
     pub fn new(client_key: ClientKey, server_key: ServerKey, circuit: Circuit) -> GateCircuit {
         GateCircuit {
             client_key,
             server_key,
             circuit,
+            input_encryptions: AtomicUsize::new(0),
+            constant_encryptions: AtomicUsize::new(0),
+            checkpoints: HashSet::new(),
+            trivial_inputs: HashSet::new(),
+            output_stream: None,
+        }
+    }
+
+    /// Decrypt and print each of `wires` when it is computed. This uses the
+    /// client key in the middle of the evaluation, so it's for debugging only.
+    pub fn set_checkpoints(&mut self, wires: &[String]) {
+        self.checkpoints = wires.iter().cloned().collect();
+    }
+
+    /// Encrypt each of `wires` trivially, without noise, which is faster but
+    /// leaves its value readable by the server. Only use it for public
+    /// constants.
+    pub fn set_trivial_inputs(&mut self, wires: &[String]) {
+        self.trivial_inputs = wires.iter().cloned().collect();
+    }
+
+    /// Decrypt each primary output as soon as a gate computes it and append
+    /// it to the CSV file `file_name` (cycle, wire, value), flushing every
+    /// row, so a consumer can read the early outputs before the evaluation
+    /// ends.
+    pub fn set_output_stream(&mut self, file_name: &str) {
+        let file = File::create(file_name).unwrap_or_else(|error| stream_error(file_name, error));
+        let mut writer = csv::Writer::from_writer(file);
+        writer
+            .write_record(["cycle", "wire", "value"])
+            .and_then(|_| writer.flush().map_err(csv::Error::from))
+            .unwrap_or_else(|error| stream_error(file_name, error));
+
+        let driven = self
+            .circuit
+            .all_gates()
+            .map(|gate| gate.get_output_wire())
+            .collect::<HashSet<_>>();
+        self.output_stream = Some(OutputStream {
+            file_name: file_name.to_owned(),
+            writer: Mutex::new(writer),
+            undriven: self
+                .circuit
+                .output_wires
+                .iter()
+                .filter(|wire| !driven.contains(*wire))
+                .cloned()
+                .collect(),
+            outputs: self.circuit.output_wires.iter().cloned().collect(),
+        });
+    }
+
+    /// Number of input wires this circuit has encrypted so far, not counting
+    /// the trivial ones.
+    pub fn input_encryption_count(&self) -> usize {
+        self.input_encryptions.load(Ordering::Relaxed)
+    }
+
+    /// Number of constant gates this circuit has encrypted so far.
+    pub fn constant_encryption_count(&self) -> usize {
+        self.constant_encryptions.load(Ordering::Relaxed)
+    }
+
+    /// Decrypt the output of every register.
+    pub fn decrypt_registers(
+        &self,
+        enc_wire_map: &HashMap<String, CtxtBool>,
+    ) -> HashMap<String, PtxtType> {
+        self.circuit
+            .dff_outputs
+            .iter()
+            .map(|wire| {
+                let value = self.client_key.decrypt(&enc_wire_map[wire]);
+                (wire.to_string(), PtxtType::Bool(value))
+            })
+            .collect()
+    }
+
+    fn encrypt_input(&self, wire: &str, value: bool) -> CtxtBool {
+        encrypt_input_bit(
+            (&self.client_key, &self.server_key),
+            &self.trivial_inputs,
+            &self.input_encryptions,
+            wire,
+            value,
+        )
+    }
+
+    /// Plaintext value of every input wire and register output.
+    fn input_plaintexts(
+        &self,
+        input_wire_map: &HashMap<String, PtxtType>,
+    ) -> HashMap<String, bool> {
+        let mut plaintexts = HashMap::new();
+        for input_wire in self.circuit.primary_inputs() {
+            // if no inputs are provided, initialize it to false
+            if input_wire_map.is_empty() || input_wire_map.contains_key("dummy") {
+                plaintexts.insert(input_wire.to_string(), false);
+            } else if !input_wire_map.contains_key(input_wire) {
+                panic!("\n Input wire \"{}\" not in input wires!", input_wire);
+            } else {
+                match input_wire_map[input_wire] {
+                    PtxtType::Bool(v) => {
+                        plaintexts.insert(input_wire.to_string(), v);
+                    }
+                    PtxtType::Unknown => unknown_input(input_wire),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        for wire in self.circuit.dff_outputs.iter() {
+            plaintexts.insert(wire.to_string(), self.circuit.register_init_bit(wire));
+        }
+
+        plaintexts
+    }
+
+    /// Evaluate independent input vectors concurrently, `cycles` cycles each,
+    /// and decrypt their outputs. The vectors share the circuit and the keys,
+    /// but each one evaluates its own copy of the gates, which keep the last
+    /// ciphertext they computed. Trivial inputs are honoured, but checkpoints,
+    /// the bootstrap budget and the gate time limit are not supported.
+    pub fn evaluate_batch(
+        &self,
+        wire_set: &HashSet<String>,
+        vectors: &[HashMap<String, PtxtType>],
+        cycles: usize,
+    ) -> Vec<HashMap<String, PtxtType>> {
+        // Make sure the compute_levels function has run.
+        assert!(!self.circuit.level_map.is_empty());
+        if !self.checkpoints.is_empty()
+            || self.circuit.bootstrap_budget.is_some()
+            || self.circuit.time_limit_per_gate.is_some()
+        {
+            panic!(
+                "{}[!]{} Checkpoints, the bootstrap budget and the gate time limit are not \
+                    supported with batched vectors.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+
+        vectors
+            .par_iter()
+            .map(|inputs| {
+                let mut level_map = self.circuit.level_map.clone();
+                let trivial_wires = wire_set
+                    .iter()
+                    .map(|wire| (wire.to_string(), self.server_key.trivial_encrypt(false)));
+                let input_wires = self
+                    .input_plaintexts(inputs)
+                    .into_iter()
+                    .map(|(wire, value)| {
+                        let value = self.encrypt_input(&wire, value);
+                        (wire, value)
+                    });
+                let eval_values = trivial_wires
+                    .chain(input_wires)
+                    .map(|(wire, value)| (wire, Arc::new(RwLock::new(value))))
+                    .collect::<HashMap<_, _>>();
+
+                for cycle in 1..=cycles {
+                    for (_level, gates) in level_map.iter_mut().sorted_by_key(|(level, _)| *level) {
+                        gates.par_iter_mut().for_each(|gate| {
+                            let input_values: Vec<CtxtBool> = gate
+                                .get_input_wires()
+                                .iter()
+                                .map(|input| eval_values[input].read().unwrap().clone())
+                                .collect();
+                            count_constant_encryption(gate, &self.constant_encryptions);
+                            let output_value =
+                                gate.evaluate_encrypted(&self.server_key, &input_values, cycle);
+                            *eval_values[&gate.get_output_wire()].write().unwrap() = output_value;
+                        });
+                    }
+                    self.circuit.copy_register_aliases(&eval_values);
+                }
+
+                self.circuit
+                    .output_wires
+                    .iter()
+                    .map(|wire| {
+                        let value = self.client_key.decrypt(&eval_values[wire].read().unwrap());
+                        (wire.to_string(), PtxtType::Bool(value))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Evaluate one cycle like `encrypt_inputs` followed by
+    /// `evaluate_encrypted`, but encrypt each input wire only when a gate
+    /// first reads it. Inputs that no gate or output reads are never
+    /// encrypted and are missing from the returned map. The gates are
+    /// evaluated level by level, without the gate time limit.
+    pub fn evaluate_encrypted_lazily(
+        &mut self,
+        wire_set: &HashSet<String>,
+        input_wire_map: &HashMap<String, PtxtType>,
+        cycle: usize,
+    ) -> HashMap<String, CtxtBool> {
+        // Make sure the sort circuit function has run.
+        assert!(self.circuit.gates.is_empty());
+        // Make sure the compute_levels function has run.
+        assert!(self.circuit.ordered_gates.is_empty());
+        if self.circuit.time_limit_per_gate.is_some() || self.circuit.work_stealing {
+            panic!(
+                "{}[!]{} The gate time limit and work stealing are not supported with lazy \
+                    encryption.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+
+        let plaintexts = self.input_plaintexts(input_wire_map);
+        let mut eval_values = wire_set
+            .iter()
+            .map(|wire| {
+                let value = Some(self.server_key.trivial_encrypt(false));
+                (wire.to_string(), Arc::new(RwLock::new(value)))
+            })
+            .collect::<HashMap<_, _>>();
+        for wire in plaintexts.keys() {
+            eval_values.insert(wire.to_string(), Arc::new(RwLock::new(None)));
+        }
+
+        let keys = (&self.client_key, &self.server_key);
+        let client_key = &self.client_key;
+        let (checkpoints, output_stream) = (&self.checkpoints, self.output_stream.as_ref());
+        let (trivial_inputs, input_encryptions) = (&self.trivial_inputs, &self.input_encryptions);
+        let constant_encryptions = &self.constant_encryptions;
+        let read_wire = |wire: &String| -> CtxtBool {
+            if let Some(value) = eval_values[wire].read().unwrap().as_ref() {
+                return value.clone();
+            }
+            eval_values[wire]
+                .write()
+                .unwrap()
+                .get_or_insert_with(|| {
+                    encrypt_input_bit(
+                        keys,
+                        trivial_inputs,
+                        input_encryptions,
+                        wire,
+                        plaintexts[wire],
+                    )
+                })
+                .clone()
+        };
+
+        // For each level
+        let total_levels = self.circuit.level_map.len();
+        for (level, gates) in self
+            .circuit
+            .level_map
+            .iter_mut()
+            .sorted_by_key(|(level, _)| *level)
+        {
+            Circuit::spend_bootstraps(
+                self.circuit.bootstrap_budget,
+                &mut self.circuit.bootstraps_used,
+                gates,
+                *level,
+                total_levels,
+            );
+            // Evaluate all the gates in the level in parallel
+            gates.par_iter_mut().for_each(|gate| {
+                let input_values: Vec<CtxtBool> =
+                    gate.get_input_wires().iter().map(&read_wire).collect();
+
+                count_constant_encryption(gate, constant_encryptions);
+                let output_value = gate.evaluate_encrypted(&self.server_key, &input_values, cycle);
+                print_checkpoint(
+                    checkpoints,
+                    client_key,
+                    &gate.get_output_wire(),
+                    &output_value,
+                );
+                if let Some(stream) = output_stream {
+                    stream.write(client_key, cycle, &gate.get_output_wire(), &output_value);
+                }
+
+                // Update the value of the corresponding key
+                *eval_values[&gate.get_output_wire()].write().unwrap() = Some(output_value);
+            });
+            info_println!("  Evaluated gates in level [{}/{}]", level, total_levels);
+        }
+        // Inputs that are also outputs are read by the client.
+        for output_wire in self.circuit.output_wires.iter() {
+            let value = read_wire(output_wire);
+            if let Some(stream) =
+                output_stream.filter(|stream| stream.undriven.contains(output_wire))
+            {
+                stream.write(client_key, cycle, output_wire, &value);
+            }
+        }
+        self.circuit.copy_register_aliases(&eval_values);
+
+        eval_values
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = value.read().unwrap().clone()?;
+                Some((key.to_string(), value))
+            })
+            .collect::<HashMap<_, _>>()
+    }
+}
+
+/// Decrypt and print `value` if `wire` is one of the checkpoints.
+fn print_checkpoint(
+    checkpoints: &HashSet<String>,
+    client_key: &ClientKey,
+    wire: &str,
+    value: &CtxtBool,
+) {
+    if checkpoints.contains(wire) {
+        // The values were asked for, so --quiet doesn't hide them.
+        println!("  Checkpoint {}: {}", wire, client_key.decrypt(value));
+    }
+}
+
+impl OutputStream {
+    /// Decrypt and write `value` if `wire` is a primary output.
+    fn write(&self, client_key: &ClientKey, cycle: usize, wire: &str, value: &CtxtBool) {
+        if !self.outputs.contains(wire) {
+            return;
         }
+        let value = client_key.decrypt(value) as u8;
+        let mut writer = self.writer.lock().unwrap();
+        writer
+            .write_record([cycle.to_string(), wire.to_owned(), value.to_string()])
+            .and_then(|_| writer.flush().map_err(csv::Error::from))
+            .unwrap_or_else(|error| stream_error(&self.file_name, error));
+    }
+}
+
+fn stream_error(file_name: &str, error: impl fmt::Display) -> ! {
+    panic!(
+        "{}[!]{} Failed to write output stream {}: {}.",
+        color::Fg(color::LightRed),
+        color::Fg(color::Reset),
+        file_name,
+        error
+    )
+}
+
+/// Count the encryption of a constant gate that has no ciphertext yet; the
+/// ones that do reuse it in every cycle.
+fn count_constant_encryption(gate: &Gate, constant_encryptions: &AtomicUsize) {
+    if gate.is_constant() && !gate.has_encrypted_output() {
+        constant_encryptions.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Reject an unknown (x) input, which has no ciphertext.
+fn unknown_input(input_wire: &str) -> ! {
+    panic!(
+        "{}[!]{} Input wire \"{}\" is unknown (x), which can't be encrypted.",
+        color::Fg(color::LightRed),
+        color::Fg(color::Reset),
+        input_wire
+    )
+}
+
+/// Encrypt the input bit of `wire`, trivially if it is a public input.
+fn encrypt_input_bit(
+    (client_key, server_key): (&ClientKey, &ServerKey),
+    trivial_inputs: &HashSet<String>,
+    input_encryptions: &AtomicUsize,
+    wire: &str,
+    value: bool,
+) -> CtxtBool {
+    if trivial_inputs.contains(wire) {
+        server_key.trivial_encrypt(value)
+    } else {
+        input_encryptions.fetch_add(1, Ordering::Relaxed);
+        client_key.encrypt(value)
     }
 }
 
@@ -411,6 +3351,21 @@ impl<'a> LutCircuit<'a> {
             circuit,
         }
     }
+
+    /// Decrypt the output of every register.
+    pub fn decrypt_registers(
+        &self,
+        enc_wire_map: &HashMap<String, CtxtShortInt>,
+    ) -> HashMap<String, PtxtType> {
+        self.circuit
+            .dff_outputs
+            .iter()
+            .map(|wire| {
+                let value = self.client_key.decrypt(&enc_wire_map[wire]);
+                (wire.to_string(), PtxtType::U64(value))
+            })
+            .collect()
+    }
 }
 
 #[cfg(feature = "gpu")]
@@ -444,6 +3399,7 @@ impl<'a> ArithCircuit<'a> {
         server_key: tfhe::ServerKey,
         circuit: Circuit,
     ) -> ArithCircuit {
+        assert_no_time_limit(&circuit, "arithmetic");
         ArithCircuit {
             client_key,
             server_key,
@@ -460,6 +3416,7 @@ impl<'a> HighPrecisionLutCircuit<'a> {
         server_intkey: ServerKeyInt,
         circuit: Circuit,
     ) -> HighPrecisionLutCircuit {
+        assert_no_time_limit(&circuit, "high-precision LUTs");
         HighPrecisionLutCircuit {
             wopbs_shortkey,
             wopbs_intkey,
@@ -481,23 +3438,9 @@ impl<'a> EvalCircuit<CtxtBool> for GateCircuit<'a> {
             .map(|wire| (wire.to_string(), self.server_key.trivial_encrypt(false)))
             .collect::<HashMap<_, _>>();
 
-        for input_wire in self.circuit.input_wires {
-            // if no inputs are provided, initialize it to false
-            if input_wire_map.is_empty() || input_wire_map.contains_key("dummy") {
-                enc_wire_map.insert(input_wire.to_string(), self.client_key.encrypt(false));
-            } else if !input_wire_map.contains_key(input_wire) {
-                panic!("\n Input wire \"{}\" not in input wires!", input_wire);
-            } else {
-                match input_wire_map[input_wire] {
-                    PtxtType::Bool(v) => {
-                        enc_wire_map.insert(input_wire.to_string(), self.client_key.encrypt(v));
-                    }
-                    _ => unreachable!(),
-                }
-            }
-        }
-        for wire in self.circuit.dff_outputs {
-            enc_wire_map.insert(wire.to_string(), self.client_key.encrypt(false));
+        for (wire, value) in self.input_plaintexts(input_wire_map) {
+            let value = self.encrypt_input(&wire, value);
+            enc_wire_map.insert(wire, value);
         }
 
         enc_wire_map
@@ -519,27 +3462,87 @@ impl<'a> EvalCircuit<CtxtBool> for GateCircuit<'a> {
             .map(|(key, value)| (key.clone(), Arc::new(RwLock::new(value.clone()))))
             .collect::<HashMap<_, _>>();
 
-        // For each level
+        // The watchdog evaluates each gate on a worker that owns its inputs.
+        let time_limit = self.circuit.time_limit_per_gate;
+        let worker_key = time_limit.map(|_| Arc::new(self.server_key.clone()));
+        let server_key = &self.server_key;
+        let (client_key, checkpoints) = (&self.client_key, &self.checkpoints);
+        let output_stream = self.output_stream.as_ref();
+        let constant_encryptions = &self.constant_encryptions;
+        let evaluate_gate = |gate: &mut Gate, input_values: &[CtxtBool]| {
+            count_constant_encryption(gate, constant_encryptions);
+            let output_value = match (time_limit, &worker_key) {
+                (Some(time_limit), Some(worker_key)) => {
+                    let server_key = worker_key.clone();
+                    let input_values = input_values.to_vec();
+                    let mut worker_gate = gate.clone();
+                    let (worker_gate, output_value) = unwrap_gate_output(evaluate_with_time_limit(
+                        &gate.get_gate_name(),
+                        time_limit,
+                        move || {
+                            let output_value =
+                                worker_gate.evaluate_encrypted(&server_key, &input_values, cycle);
+                            (worker_gate, output_value)
+                        },
+                    ));
+                    *gate = worker_gate;
+                    output_value
+                }
+                _ => gate.evaluate_encrypted(server_key, input_values, cycle),
+            };
+            print_checkpoint(
+                checkpoints,
+                client_key,
+                &gate.get_output_wire(),
+                &output_value,
+            );
+            if let Some(stream) = output_stream {
+                stream.write(client_key, cycle, &gate.get_output_wire(), &output_value);
+            }
+            output_value
+        };
+
+        // The bootstrap budget is checked level by level.
         let total_levels = self.circuit.level_map.len();
-        for (level, gates) in self
-            .circuit
-            .level_map
-            .iter_mut()
-            .sorted_by_key(|(level, _)| *level)
-        {
-            // Evaluate all the gates in the level in parallel
-            gates.par_iter_mut().for_each(|gate| {
-                let input_values: Vec<CtxtBool> = gate
-                    .get_input_wires()
-                    .iter()
-                    .map(|input| eval_values[input].read().unwrap().clone())
-                    .collect();
+        if self.circuit.work_stealing && self.circuit.bootstrap_budget.is_none() {
+            let gates = self.circuit.level_map.values_mut().flatten().collect();
+            evaluate_work_stealing(gates, &eval_values, evaluate_gate);
+            info_println!("  Evaluated gates in {} levels", total_levels);
+        } else {
+            // For each level
+            for (level, gates) in self
+                .circuit
+                .level_map
+                .iter_mut()
+                .sorted_by_key(|(level, _)| *level)
+            {
+                Circuit::spend_bootstraps(
+                    self.circuit.bootstrap_budget,
+                    &mut self.circuit.bootstraps_used,
+                    gates,
+                    *level,
+                    total_levels,
+                );
+                // Evaluate all the gates in the level in parallel
+                gates.par_iter_mut().for_each(|gate| {
+                    let input_values: Vec<CtxtBool> = gate
+                        .get_input_wires()
+                        .iter()
+                        .map(|input| eval_values[input].read().unwrap().clone())
+                        .collect();
+                    let output_value = evaluate_gate(gate, &input_values);
 
-                // Update the value of the corresponding key
-                *eval_values[&gate.get_output_wire()].write().unwrap() =
-                    gate.evaluate_encrypted(&self.server_key, &input_values, cycle);
-            });
-            println!("  Evaluated gates in level [{}/{}]", level, total_levels);
+                    // Update the value of the corresponding key
+                    *eval_values[&gate.get_output_wire()].write().unwrap() = output_value;
+                });
+                info_println!("  Evaluated gates in level [{}/{}]", level, total_levels);
+            }
+        }
+        self.circuit.copy_register_aliases(&eval_values);
+        if let Some(stream) = &self.output_stream {
+            for wire in &stream.undriven {
+                stream.write(client_key, cycle, wire, &eval_values[wire].read().unwrap());
+            }
         }
 
         eval_values
@@ -554,26 +3557,30 @@ impl<'a> EvalCircuit<CtxtBool> for GateCircuit<'a> {
         verbose: bool,
     ) -> HashMap<String, PtxtType> {
         let mut decrypted_outputs = HashMap::new();
-        for output_wire in self.circuit.output_wires {
+        for output_wire in self.circuit.output_wires.iter() {
             let decrypted_value = self.client_key.decrypt(&enc_wire_map[output_wire]);
             decrypted_outputs.insert(output_wire.clone(), PtxtType::Bool(decrypted_value));
         }
 
         for (i, (wire, val)) in decrypted_outputs.iter().sorted().enumerate() {
             if i > 10 && !verbose {
-                println!(
+                info_println!(
                     "{}[!]{} More than ten output_wires, pass `--verbose` to see output.",
                     color::Fg(color::LightYellow),
                     color::Fg(color::Reset)
                 );
                 break;
             } else {
-                println!(" {}: {}", wire, val);
+                info_println!(" {}: {}", wire, val);
             }
         }
 
         decrypted_outputs
     }
+
+    fn clear_encrypted_outputs(&mut self) {
+        self.circuit.clear_encrypted_outputs();
+    }
 }
 
 #[cfg(feature = "gpu")]
@@ -601,7 +3608,7 @@ impl<'a> EvalCircuit<LweCiphertext32> for CircuitCuda<'a> {
                 )
             })
             .collect::<HashMap<_, _>>();
-        for input_wire in self.circuit.input_wires {
+        for input_wire in self.circuit.primary_inputs() {
             // if no inputs are provided, initialize it to false
             if input_wire_map.is_empty() || input_wire_map.contains_key("dummy") {
                 enc_wire_map.insert(
@@ -631,11 +3638,12 @@ impl<'a> EvalCircuit<LweCiphertext32> for CircuitCuda<'a> {
                                 .unwrap(),
                         );
                     }
+                    PtxtType::Unknown => unknown_input(input_wire),
                     _ => unreachable!(),
                 }
             }
         }
-        for wire in self.circuit.dff_outputs {
+        for wire in self.circuit.dff_outputs.iter() {
             enc_wire_map.insert(
                 wire.to_string(),
                 self.default_engine
@@ -915,7 +3923,7 @@ impl<'a> EvalCircuit<LweCiphertext32> for CircuitCuda<'a> {
                 match err_chk {
                     Ok(_value) => {}
                     Err(error) => {
-                        println!("Error: {}", error);
+                        info_println!("Error: {}", error);
                     }
                 }
                 enc_wire_map_out
@@ -928,7 +3936,11 @@ impl<'a> EvalCircuit<LweCiphertext32> for CircuitCuda<'a> {
                     .and_modify(|ctr| *ctr += 1);
             }
 
-            println!("  Evaluated gates in level [{}/{}]", level, total_levels);
+            info_println!("  Evaluated gates in level [{}/{}]", level, total_levels);
+        }
+        for (alias, wire) in self.circuit.register_aliases.iter() {
+            let value = enc_wire_map_out[wire].clone();
+            enc_wire_map_out.insert(alias.to_string(), value);
         }
         enc_wire_map_out
     }
@@ -939,7 +3951,7 @@ impl<'a> EvalCircuit<LweCiphertext32> for CircuitCuda<'a> {
         verbose: bool,
     ) -> HashMap<String, PtxtType> {
         let mut decrypted_outputs = HashMap::new();
-        for output_wire in self.circuit.output_wires {
+        for output_wire in self.circuit.output_wires.iter() {
             let pt_extract = self
                 .default_engine
                 .decrypt_lwe_ciphertext(&self.host_client_key, &enc_wire_map[output_wire])
@@ -951,19 +3963,23 @@ impl<'a> EvalCircuit<LweCiphertext32> for CircuitCuda<'a> {
 
         for (i, (wire, val)) in decrypted_outputs.iter().sorted().enumerate() {
             if i > 10 && !verbose {
-                println!(
+                info_println!(
                     "{}[!]{} More than ten output_wires, pass `--verbose` to see output.",
                     color::Fg(color::LightYellow),
                     color::Fg(color::Reset)
                 );
                 break;
             } else {
-                println!(" {}: {}", wire, val);
+                info_println!(" {}: {}", wire, val);
             }
         }
 
         decrypted_outputs
     }
+
+    fn clear_encrypted_outputs(&mut self) {
+        self.circuit.clear_encrypted_outputs();
+    }
 }
 
 impl<'a> EvalCircuit<CtxtShortInt> for LutCircuit<'a> {
@@ -976,7 +3992,7 @@ impl<'a> EvalCircuit<CtxtShortInt> for LutCircuit<'a> {
             .iter()
             .map(|wire| (wire.to_string(), self.server_key.create_trivial(0)))
             .collect::<HashMap<_, _>>();
-        for input_wire in self.circuit.input_wires {
+        for input_wire in self.circuit.primary_inputs() {
             // if no inputs are provided, initialize it to false
             if input_wire_map.is_empty() || input_wire_map.contains_key("dummy") {
                 enc_wire_map.insert(input_wire.to_string(), self.client_key.encrypt(0));
@@ -988,12 +4004,14 @@ impl<'a> EvalCircuit<CtxtShortInt> for LutCircuit<'a> {
                         enc_wire_map
                             .insert(input_wire.to_string(), self.client_key.encrypt(v as u64));
                     }
+                    PtxtType::Unknown => unknown_input(input_wire),
                     _ => unreachable!(),
                 }
             }
         }
-        for wire in self.circuit.dff_outputs {
-            enc_wire_map.insert(wire.to_string(), self.client_key.encrypt(0));
+        for wire in self.circuit.dff_outputs.iter() {
+            let init = self.circuit.register_init_bit(wire) as u64;
+            enc_wire_map.insert(wire.to_string(), self.client_key.encrypt(init));
         }
 
         enc_wire_map
@@ -1015,6 +4033,22 @@ impl<'a> EvalCircuit<CtxtShortInt> for LutCircuit<'a> {
             .map(|(key, value)| (key.clone(), Arc::new(RwLock::new(value.clone()))))
             .collect::<HashMap<_, _>>();
 
+        // The watchdog evaluates each gate on a worker that owns its inputs.
+        let time_limit = self.circuit.time_limit_per_gate;
+        let worker_key = time_limit.map(|_| Arc::new(self.server_key.clone()));
+        let evaluate_lut_gate =
+            move |gate: &mut Gate,
+                  server_key: &ServerKeyShortInt,
+                  mut input_values: Vec<CtxtShortInt>| {
+                if gate.get_gate_type() == GateType::Lut {
+                    gate.evaluate_encrypted_lut(server_key, &mut input_values, cycle)
+                } else if gate.is_constant() {
+                    gate.evaluate_encrypted_constant_lut(server_key)
+                } else {
+                    gate.evaluate_encrypted_dff(&input_values, cycle)
+                }
+            };
+
         // For each level
         let total_levels = self.circuit.level_map.len();
         for (level, gates) in self
@@ -1023,19 +4057,41 @@ impl<'a> EvalCircuit<CtxtShortInt> for LutCircuit<'a> {
             .iter_mut()
             .sorted_by_key(|(level, _)| *level)
         {
+            Circuit::spend_bootstraps(
+                self.circuit.bootstrap_budget,
+                &mut self.circuit.bootstraps_used,
+                gates,
+                *level,
+                total_levels,
+            );
             // Evaluate all the gates in the level in parallel
             gates.par_iter_mut().for_each(|gate| {
-                let mut input_values: Vec<CtxtShortInt> = gate
+                let input_values: Vec<CtxtShortInt> = gate
                     .get_input_wires()
                     .iter()
                     .map(|input| eval_values[input].read().unwrap().clone())
                     .collect();
-                let output_value = {
-                    if gate.get_gate_type() == GateType::Lut {
-                        gate.evaluate_encrypted_lut(&self.server_key, &mut input_values, cycle)
-                    } else {
-                        gate.evaluate_encrypted_dff(&input_values, cycle)
+                let output_value = match (time_limit, &worker_key) {
+                    (Some(time_limit), Some(worker_key)) => {
+                        let server_key = worker_key.clone();
+                        let mut worker_gate = gate.clone();
+                        let (worker_gate, output_value) =
+                            unwrap_gate_output(evaluate_with_time_limit(
+                                &gate.get_gate_name(),
+                                time_limit,
+                                move || {
+                                    let output_value = evaluate_lut_gate(
+                                        &mut worker_gate,
+                                        &server_key,
+                                        input_values,
+                                    );
+                                    (worker_gate, output_value)
+                                },
+                            ));
+                        *gate = worker_gate;
+                        output_value
                     }
+                    _ => evaluate_lut_gate(gate, &self.server_key, input_values),
                 };
 
                 // Update the value of the corresponding key
@@ -1043,8 +4099,9 @@ impl<'a> EvalCircuit<CtxtShortInt> for LutCircuit<'a> {
                     .write()
                     .expect("Failed to acquire write lock") = output_value;
             });
-            println!("  Evaluated gates in level [{}/{}]", level, total_levels);
+            info_println!("  Evaluated gates in level [{}/{}]", level, total_levels);
         }
+        self.circuit.copy_register_aliases(&eval_values);
 
         eval_values
             .iter()
@@ -1058,26 +4115,30 @@ impl<'a> EvalCircuit<CtxtShortInt> for LutCircuit<'a> {
         verbose: bool,
     ) -> HashMap<String, PtxtType> {
         let mut decrypted_outputs = HashMap::new();
-        for output_wire in self.circuit.output_wires {
+        for output_wire in self.circuit.output_wires.iter() {
             let decrypted_value = self.client_key.decrypt(&enc_wire_map[output_wire]);
             decrypted_outputs.insert(output_wire.clone(), PtxtType::U64(decrypted_value));
         }
 
         for (i, (wire, val)) in decrypted_outputs.iter().sorted().enumerate() {
             if i > 10 && !verbose {
-                println!(
+                info_println!(
                     "{}[!]{} More than ten output_wires, pass `--verbose` to see output.",
                     color::Fg(color::LightYellow),
                     color::Fg(color::Reset)
                 );
                 break;
             } else {
-                println!(" {}: {}", wire, val);
+                info_println!(" {}: {}", wire, val);
             }
         }
 
         decrypted_outputs
     }
+
+    fn clear_encrypted_outputs(&mut self) {
+        self.circuit.clear_encrypted_outputs();
+    }
 }
 
 impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
@@ -1092,13 +4153,14 @@ impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
             PtxtType::U32(_) => "u32",
             PtxtType::U64(_) => "u64",
             PtxtType::U128(_) => "u128",
+            PtxtType::U256(_) => "u256",
             _ => unreachable!(),
         };
         let mut enc_wire_map = HashMap::<String, _>::new();
         for wire in wire_set {
             enc_wire_map.insert(wire.to_string(), FheType::None);
         }
-        for input_wire in self.circuit.input_wires {
+        for input_wire in self.circuit.primary_inputs() {
             // if no inputs are provided, initialize it to false
             if input_wire_map.is_empty() || input_wire_map.contains_key("dummy") {
                 let encrypted_value = match ptxt_type {
@@ -1109,6 +4171,13 @@ impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
                     "u128" => {
                         FheType::U128(FheUint128::try_encrypt(0u128, &self.client_key).unwrap())
                     }
+                    "u256" => FheType::U256(
+                        FheUint256::try_encrypt(
+                            tfhe::integer::U256::from(U256::default()),
+                            &self.client_key,
+                        )
+                        .unwrap(),
+                    ),
                     _ => unreachable!(),
                 };
 
@@ -1132,19 +4201,33 @@ impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
                     PtxtType::U128(pt_val) => {
                         FheType::U128(FheUint128::try_encrypt(pt_val, &self.client_key).unwrap())
                     }
+                    PtxtType::U256(pt_val) => FheType::U256(
+                        FheUint256::try_encrypt(
+                            tfhe::integer::U256::from(pt_val),
+                            &self.client_key,
+                        )
+                        .unwrap(),
+                    ),
                     _ => unreachable!(),
                 };
 
                 enc_wire_map.insert(input_wire.to_string(), encrypted_value);
             }
         }
-        for wire in self.circuit.dff_outputs {
+        for wire in self.circuit.dff_outputs.iter() {
             let encrypted_value = match ptxt_type {
                 "u8" => FheType::U8(FheUint8::try_encrypt(0u8, &self.client_key).unwrap()),
                 "u16" => FheType::U16(FheUint16::try_encrypt(0u16, &self.client_key).unwrap()),
                 "u32" => FheType::U32(FheUint32::try_encrypt(0u32, &self.client_key).unwrap()),
                 "u64" => FheType::U64(FheUint64::try_encrypt(0u64, &self.client_key).unwrap()),
                 "u128" => FheType::U128(FheUint128::try_encrypt(0u128, &self.client_key).unwrap()),
+                "u256" => FheType::U256(
+                    FheUint256::try_encrypt(
+                        tfhe::integer::U256::from(U256::default()),
+                        &self.client_key,
+                    )
+                    .unwrap(),
+                ),
                 _ => unreachable!(),
             };
             enc_wire_map.insert(wire.to_string(), encrypted_value);
@@ -1201,6 +4284,9 @@ impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
                                     "u32" => PtxtType::U32(in_wire.parse::<u32>().unwrap_or(0)),
                                     "u64" => PtxtType::U64(in_wire.parse::<u64>().unwrap_or(0)),
                                     "u128" => PtxtType::U128(in_wire.parse::<u128>().unwrap_or(0)),
+                                    "u256" => {
+                                        PtxtType::U256(in_wire.parse::<U256>().unwrap_or_default())
+                                    }
                                     _ => unreachable!(),
                                 };
                             } else {
@@ -1214,6 +4300,7 @@ impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
                             FheType::U32(_) => ctxt_operand,
                             FheType::U64(_) => ctxt_operand,
                             FheType::U128(_) => ctxt_operand,
+                            FheType::U256(_) => ctxt_operand,
                             _ => panic!("Empty ctxt operand!"),
                         };
 
@@ -1281,8 +4368,20 @@ impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
                                 cycle,
                                 false,
                             )
-                        } else if gate.get_gate_type() == GateType::Copy {
+                        } else if gate.get_gate_type() == GateType::Copy
+                            || gate.get_gate_type() == GateType::Dff
+                        {
+                            // Registers are evaluated last, latching the
+                            // value their input took this cycle.
                             gate.evaluate_encrypted_copy_block(&input_values[0], cycle)
+                        } else if gate.get_gate_type() == GateType::MuxN {
+                            gate.evaluate_encrypted_muxn_block(
+                                &input_values[0],
+                                &input_values[1..],
+                                cycle,
+                            )
+                        } else if gate.get_gate_type() == GateType::PriorityEncoder {
+                            gate.evaluate_encrypted_priority_encoder_block(&input_values, cycle)
                         } else {
                             gate.evaluate_encrypted_mul_block(
                                 &input_values[0],
@@ -1298,11 +4397,12 @@ impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
                     .write()
                     .expect("Failed to acquire write lock") = output_value;
             });
-            println!("  Evaluated gates in level [{}/{}]", level, total_levels);
+            info_println!("  Evaluated gates in level [{}/{}]", level, total_levels);
         }
 
         rayon::broadcast(|_| unset_server_key());
         unset_server_key();
+        self.circuit.copy_register_aliases(&eval_values);
 
         eval_values
             .iter()
@@ -1317,26 +4417,30 @@ impl<'a> EvalCircuit<FheType> for ArithCircuit<'a> {
     ) -> HashMap<String, PtxtType> {
         let mut decrypted_outputs = HashMap::new();
 
-        for output_wire in self.circuit.output_wires {
+        for output_wire in self.circuit.output_wires.iter() {
             let decrypted = enc_wire_map[output_wire].decrypt(&self.client_key);
             decrypted_outputs.insert(output_wire.clone(), decrypted);
         }
 
         for (i, (wire, val)) in decrypted_outputs.iter().sorted().enumerate() {
             if i > 10 && !verbose {
-                println!(
+                info_println!(
                     "{}[!]{} More than ten output_wires, pass `--verbose` to see output.",
                     color::Fg(color::LightYellow),
                     color::Fg(color::Reset)
                 );
                 break;
             } else {
-                println!(" {}: {}", wire, val);
+                info_println!(" {}: {}", wire, val);
             }
         }
 
         decrypted_outputs
     }
+
+    fn clear_encrypted_outputs(&mut self) {
+        self.circuit.clear_encrypted_outputs();
+    }
 }
 
 impl<'a> EvalCircuit<CtxtShortInt> for HighPrecisionLutCircuit<'a> {
@@ -1349,7 +4453,7 @@ impl<'a> EvalCircuit<CtxtShortInt> for HighPrecisionLutCircuit<'a> {
             .iter()
             .map(|wire| (wire.to_string(), self.client_key.encrypt_one_block(0u64)))
             .collect::<HashMap<_, _>>();
-        for input_wire in self.circuit.input_wires {
+        for input_wire in self.circuit.primary_inputs() {
             // if no inputs are provided, initialize it to false
             if input_wire_map.is_empty() || input_wire_map.contains_key("dummy") {
                 enc_wire_map.insert(input_wire.to_string(), self.client_key.encrypt_one_block(0));
@@ -1363,12 +4467,14 @@ impl<'a> EvalCircuit<CtxtShortInt> for HighPrecisionLutCircuit<'a> {
                             self.client_key.encrypt_one_block(v as u64),
                         );
                     }
+                    PtxtType::Unknown => unknown_input(input_wire),
                     _ => unreachable!(),
                 }
             }
         }
-        for wire in self.circuit.dff_outputs {
-            enc_wire_map.insert(wire.to_string(), self.client_key.encrypt_one_block(0));
+        for wire in self.circuit.dff_outputs.iter() {
+            let init = self.circuit.register_init_bit(wire) as u64;
+            enc_wire_map.insert(wire.to_string(), self.client_key.encrypt_one_block(init));
         }
 
         enc_wire_map
@@ -1418,8 +4524,9 @@ impl<'a> EvalCircuit<CtxtShortInt> for HighPrecisionLutCircuit<'a> {
                         cycle,
                     );
             });
-            println!("  Evaluated gates in level [{}/{}]", level, total_levels);
+            info_println!("  Evaluated gates in level [{}/{}]", level, total_levels);
         }
+        self.circuit.copy_register_aliases(&eval_values);
 
         eval_values
             .iter()
@@ -1434,7 +4541,7 @@ impl<'a> EvalCircuit<CtxtShortInt> for HighPrecisionLutCircuit<'a> {
     ) -> HashMap<String, PtxtType> {
         let mut decrypted_outputs = HashMap::new();
 
-        for output_wire in self.circuit.output_wires {
+        for output_wire in self.circuit.output_wires.iter() {
             let decrypted = self
                 .client_key
                 .decrypt_one_block(&enc_wire_map[output_wire]);
@@ -1443,17 +4550,21 @@ impl<'a> EvalCircuit<CtxtShortInt> for HighPrecisionLutCircuit<'a> {
 
         for (i, (wire, val)) in decrypted_outputs.iter().sorted().enumerate() {
             if i > 10 && !verbose {
-                println!(
+                info_println!(
                     "{}[!]{} More than ten output_wires, pass `--verbose` to see output.",
                     color::Fg(color::LightYellow),
                     color::Fg(color::Reset)
                 );
                 break;
             } else {
-                println!(" {}: {}", wire, val);
+                info_println!(" {}: {}", wire, val);
             }
         }
 
         decrypted_outputs
     }
+
+    fn clear_encrypted_outputs(&mut self) {
+        self.circuit.clear_encrypted_outputs();
+    }
 }
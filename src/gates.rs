@@ -1,4 +1,5 @@
-use crate::{FheType, PtxtType};
+use crate::{FheType, PtxtType, U256};
+use itertools::Itertools;
 use std::time::Instant;
 use std::{
     cmp::Ordering,
@@ -14,34 +15,215 @@ use tfhe::{
         wopbs::{IntegerWopbsLUT, WopbsKey as WopbsKeyInt},
         IntegerCiphertext, ServerKey as ServerKeyInt,
     },
+    prelude::FheTryTrivialEncrypt,
     shortint::{
         wopbs::WopbsKey as WopbsKeyShortInt, Ciphertext as CiphertextBase,
         ServerKey as ServerKeyShortInt,
     },
 };
 
+/// Number of select wires and of data inputs of an N:1 mux with
+/// `num_inputs` inputs in total: either K select bits with K + 2^K =
+/// `num_inputs`, or a single integer index followed by N data inputs.
+pub fn muxn_layout(num_inputs: usize) -> (usize, usize) {
+    (1..usize::BITS as usize)
+        .find(|&width| width + (1 << width) == num_inputs)
+        .map(|width| (width, 1 << width))
+        .or_else(|| {
+            (num_inputs > 2 && (num_inputs - 1).is_power_of_two()).then(|| (1, num_inputs - 1))
+        })
+        .unwrap_or_else(|| panic!("Invalid number of MUXN inputs: {}", num_inputs))
+}
+
+/// Panic unless the MUXN inputs have a select bit per level of the tree; an
+/// integer index only exists in arithmetic mode.
+fn assert_select_bits(select_width: usize, arity: usize) {
+    if arity != 1 << select_width {
+        panic!("A MUXN with an integer index needs an arithmetic type!");
+    }
+}
+
+// Build the select tree of an arithmetic MUXN: at each level, one bit of the
+// index, least significant first, picks a half of the candidate inputs. The
+// bit is isolated by shifting it up to the top of the word and back down.
+macro_rules! integer_select_tree {
+    ($index:expr, $data:expr, $variant:ident, $bits:expr, $clear:expr) => {{
+        let mut candidates = $data
+            .iter()
+            .map(|value| match value {
+                FheType::$variant(value) => value.clone(),
+                _ => panic!("MUXN data inputs should have the type of the index"),
+            })
+            .collect::<Vec<_>>();
+        for bit in 0..candidates.len().trailing_zeros() {
+            let select = &($index << $clear($bits - 1 - bit)) >> $clear($bits - 1);
+            candidates = candidates
+                .chunks(2)
+                .map(|pair| select.if_then_else(&pair[1], &pair[0]))
+                .collect();
+        }
+        FheType::$variant(candidates.remove(0))
+    }};
+}
+
+// Build an arithmetic priority encoder: the low bit of every request, isolated
+// like in `integer_select_tree`, replaces the index found so far by its own
+// index. The indexes are encrypted trivially, so selecting one takes no
+// multiplication.
+macro_rules! integer_priority_encoder {
+    ($requests:expr, $variant:ident, $fhe:ident, $bits:expr, $clear:expr) => {{
+        let bits = $requests
+            .iter()
+            .map(|request| match request {
+                FheType::$variant(request) => &(request << $clear($bits - 1)) >> $clear($bits - 1),
+                _ => panic!("Priority encoder requests should all have the same type"),
+            })
+            .collect::<Vec<_>>();
+        let trivial = |i: u32| tfhe::$fhe::try_encrypt_trivial($clear(i)).unwrap();
+        let mut index = trivial(0);
+        for (i, bit) in bits.iter().enumerate().skip(1) {
+            index = bit.if_then_else(&trivial(i as u32), &index);
+        }
+        FheType::$variant(index)
+    }};
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum GateType {
-    And,       // and  ID(in0, in1, out);
-    Dff,       // dff  ID(in, out);
-    Lut,       // lut  ID(const, in0, ... , inN-1, out);
-    Mux,       // mux  ID(in0, in1, sel, out);
-    Nand,      // nand ID(in0, in1, out);
-    Nor,       // nor  ID(in0, in1, out);
-    Not,       // not  ID(in, out);
-    Or,        // or   ID(in0, in1, out);
-    Xnor,      // xnor ID(in0, in1, out);
-    Xor,       // xor  ID(in0, in1, out);
-    Buf,       // buf  ID(in, out);
-    ConstOne,  // one(out);
-    ConstZero, // zero(out);
-    Mult,      // mult ID(in0, in1, out);
-    Add,       // add  ID(in0, in1, out);
-    Sub,       // sub  ID(in0, in1, out);
-    Div,       // div  ID(in0, in1, out);
-    Shl,       // shl  ID(in0, in1, out);
-    Shr,       // shr  ID(in0, in1, out);
-    Copy,      // copy ID(in, out);
+    And,             // and  ID(in0, in1, out);
+    Dff,             // dff  ID(in, out);
+    Lut,             // lut  ID(const, in0, ... , inN-1, out);
+    Mux,             // mux  ID(in0, in1, sel, out);
+    MuxN,            // muxn ID(N, sel0, ... , selK-1 | index, in0, ... , inN-1, out);
+    Nand,            // nand ID(in0, in1, out);
+    Nor,             // nor  ID(in0, in1, out);
+    Not,             // not  ID(in, out);
+    Or,              // or   ID(in0, in1, out);
+    Xnor,            // xnor ID(in0, in1, out);
+    Xor,             // xor  ID(in0, in1, out);
+    Buf,             // buf  ID(in, out);
+    ConstOne,        // one(out);
+    ConstZero,       // zero(out);
+    ConstUnknown,    // cx ID(out); a `1'bx` or `1'bz` literal
+    Mult,            // mult ID(in0, in1, out);
+    Add,             // add  ID(in0, in1, out);
+    Sub,             // sub  ID(in0, in1, out);
+    Div,             // div  ID(in0, in1, out);
+    Shl,             // shl  ID(in0, in1, out);
+    Shr,             // shr  ID(in0, in1, out);
+    Copy,            // copy ID(in, out);
+    PriorityEncoder, // penc ID(N, in0, ... , inN-1, out);
+}
+
+// Wrapping arithmetic on two plaintexts of the same width.
+macro_rules! wrapping_op {
+    ($gate_type:expr, $lhs:expr, $rhs:expr, $shift:expr, $max:expr) => {
+        match $gate_type {
+            GateType::Add => $lhs.wrapping_add($rhs),
+            GateType::Sub => $lhs.wrapping_sub($rhs),
+            GateType::Mult => $lhs.wrapping_mul($rhs),
+            // Like tfhe, dividing by zero gives the maximum value.
+            GateType::Div => $lhs.checked_div($rhs).unwrap_or($max),
+            GateType::Shl => $lhs.wrapping_shl($shift),
+            GateType::Shr => $lhs.wrapping_shr($shift),
+            _ => panic!("{:?} is not a binary arithmetic gate", $gate_type),
+        }
+    };
+}
+
+impl GateType {
+    /// Whether the gate operates on words (arithmetic mode) instead of bits.
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            GateType::Mult
+                | GateType::Add
+                | GateType::Sub
+                | GateType::Div
+                | GateType::Shl
+                | GateType::Shr
+                | GateType::Copy
+        )
+    }
+
+    /// Evaluate a binary arithmetic gate on plaintexts with the modular
+    /// semantics of tfhe: results wrap around the width of the operands and
+    /// shift amounts are taken modulo the width.
+    pub fn wrapping_eval(&self, lhs: &PtxtType, rhs: &PtxtType) -> PtxtType {
+        match (*lhs, *rhs) {
+            (PtxtType::U8(a), PtxtType::U8(b)) => {
+                PtxtType::U8(wrapping_op!(self, a, b, b as u32, u8::MAX))
+            }
+            (PtxtType::U16(a), PtxtType::U16(b)) => {
+                PtxtType::U16(wrapping_op!(self, a, b, b as u32, u16::MAX))
+            }
+            (PtxtType::U32(a), PtxtType::U32(b)) => {
+                PtxtType::U32(wrapping_op!(self, a, b, b, u32::MAX))
+            }
+            (PtxtType::U64(a), PtxtType::U64(b)) => {
+                PtxtType::U64(wrapping_op!(self, a, b, b as u32, u64::MAX))
+            }
+            (PtxtType::U128(a), PtxtType::U128(b)) => {
+                PtxtType::U128(wrapping_op!(self, a, b, b as u32, u128::MAX))
+            }
+            (PtxtType::U256(a), PtxtType::U256(b)) => {
+                PtxtType::U256(wrapping_op!(self, a, b, b.low() as u32, U256::MAX))
+            }
+            _ => panic!(
+                "Operands of {:?} should be integers of the same width, found {} and {}",
+                self, lhs, rhs
+            ),
+        }
+    }
+}
+
+/// A boolean plaintext as a bit, None if it is unknown (`x` or `z`).
+fn to_bit(value: &PtxtType) -> Option<bool> {
+    match value {
+        PtxtType::Bool(b) => Some(*b),
+        PtxtType::Unknown => None,
+        _ => panic!("Expected PtxtType::Bool variant"),
+    }
+}
+
+fn from_bit(bit: Option<bool>) -> PtxtType {
+    bit.map_or(PtxtType::Unknown, PtxtType::Bool)
+}
+
+fn not_bit(value: PtxtType) -> PtxtType {
+    from_bit(to_bit(&value).map(|b| !b))
+}
+
+/// A zero input decides an AND, even next to unknown inputs.
+fn and_bits(input_values: &[PtxtType]) -> PtxtType {
+    let bits = input_values.iter().map(to_bit).collect::<Vec<_>>();
+    if bits.contains(&Some(false)) {
+        PtxtType::Bool(false)
+    } else if bits.contains(&None) {
+        PtxtType::Unknown
+    } else {
+        PtxtType::Bool(true)
+    }
+}
+
+/// A one input decides an OR, even next to unknown inputs.
+fn or_bits(input_values: &[PtxtType]) -> PtxtType {
+    let bits = input_values.iter().map(to_bit).collect::<Vec<_>>();
+    if bits.contains(&Some(true)) {
+        PtxtType::Bool(true)
+    } else if bits.contains(&None) {
+        PtxtType::Unknown
+    } else {
+        PtxtType::Bool(false)
+    }
+}
+
+fn xor_bits(input_values: &[PtxtType]) -> PtxtType {
+    input_values
+        .iter()
+        .map(to_bit)
+        .try_fold(false, |parity, bit| bit.map(|b| parity ^ b))
+        .map_or(PtxtType::Unknown, PtxtType::Bool)
 }
 
 #[derive(Clone)]
@@ -128,6 +310,10 @@ impl Gate {
         &self.input_wires
     }
 
+    pub fn set_input_wires(&mut self, input_wires: Vec<String>) {
+        self.input_wires = input_wires;
+    }
+
     pub fn get_output_wire(&self) -> String {
         self.output_wire.clone()
     }
@@ -148,91 +334,174 @@ impl Gate {
         self.level = level;
     }
 
+    pub fn is_constant(&self) -> bool {
+        self.gate_type == GateType::ConstOne || self.gate_type == GateType::ConstZero
+    }
+
+    /// Whether the gate holds the ciphertext it computed last.
+    pub fn has_encrypted_output(&self) -> bool {
+        self.encrypted_gate_output.is_some()
+    }
+
+    /// Forget the ciphertexts computed so far so the next encrypted
+    /// evaluation recomputes the gate. Constants keep theirs.
+    pub fn clear_encrypted_output(&mut self) {
+        if !self.is_constant() {
+            self.encrypted_gate_output = None;
+        }
+        self.encrypted_lut_output = None;
+        self.encrypted_multibit_output = FheType::None;
+        self.cycle = 0;
+    }
+
+    /// Number of bootstraps needed to evaluate this gate in gates or LUTs
+    /// mode. NOT, BUF, DFFs and constants are free.
+    pub fn bootstrap_count(&self) -> usize {
+        match self.gate_type {
+            GateType::And
+            | GateType::Lut
+            | GateType::Nand
+            | GateType::Nor
+            | GateType::Or
+            | GateType::Xnor
+            | GateType::Xor => 1,
+            GateType::Mux => 2,
+            // One mux per node of the select tree.
+            GateType::MuxN => 2 * (muxn_layout(self.input_wires.len()).1 - 1),
+            // An OR per later request, then the AND with the first.
+            GateType::PriorityEncoder => self.input_wires.len() - 1,
+            _ => 0,
+        }
+    }
+
+    /// Output of the gate for every combination of its inputs, with the first
+    /// input as the most significant bit. None for DFFs and arithmetic gates.
+    pub fn truth_table(&self) -> Option<Vec<(Vec<bool>, bool)>> {
+        if self.gate_type.is_arithmetic() || self.gate_type == GateType::Dff {
+            return None;
+        }
+
+        let num_inputs = self.input_wires.len();
+        let table = (0..1 << num_inputs)
+            .map(|row: usize| {
+                let inputs = (0..num_inputs)
+                    .map(|i| (row >> (num_inputs - 1 - i)) & 1 == 1)
+                    .collect::<Vec<_>>();
+                let input_values = inputs
+                    .iter()
+                    .map(|&input| PtxtType::Bool(input))
+                    .collect::<Vec<_>>();
+                let output = self.clone().evaluate(&input_values) == PtxtType::Bool(true);
+                (inputs, output)
+            })
+            .collect();
+
+        Some(table)
+    }
+
     pub fn evaluate(&mut self, input_values: &[PtxtType]) -> PtxtType {
         self.output = match self.gate_type {
             GateType::Dff => input_values[0],
-            GateType::And => PtxtType::Bool(input_values.iter().all(|v| match v {
-                PtxtType::Bool(b) => *b,
-                _ => panic!("Expected PtxtType::Bool variant"),
-            })),
+            GateType::And => and_bits(input_values),
             GateType::Lut => {
-                let mut shift_amt = 0;
+                let lut_const = match &self.lut_const {
+                    Some(lut_const) => lut_const,
+                    None => panic!("Lut const not provided"),
+                };
+                // An unknown input gives every row it could select; the
+                // output is known only if they agree.
                 let end = input_values.len() - 1;
-
+                let mut rows = vec![0];
                 for (input_idx, input_val) in input_values.iter().enumerate() {
-                    if let PtxtType::Bool(input_val_b) = input_val {
-                        if *input_val_b {
-                            shift_amt += 1 << (end - input_idx);
-                        }
-                    } else {
-                        println!(
-                            "LINE THAT ERRORS: {:?} --> {:?}, Gate: {:?}",
-                            input_values, input_val, self
-                        );
-                        panic!("Expected PtxtType::Bool variant");
-                    }
-                }
-
-                if let Some(lut_const) = &self.lut_const {
-                    PtxtType::Bool((lut_const[shift_amt] & 1) > 0)
-                } else {
-                    panic!("Lut const not provided");
+                    let weight = 1 << (end - input_idx);
+                    rows = match to_bit(input_val) {
+                        Some(true) => rows.iter().map(|row| row + weight).collect(),
+                        Some(false) => rows,
+                        None => rows.iter().flat_map(|row| [*row, row + weight]).collect(),
+                    };
                 }
+                let outputs = rows.iter().map(|row| (lut_const[*row] & 1) > 0);
+                from_bit(outputs.dedup().exactly_one().ok())
             }
-            GateType::Mult => unreachable!(),
-            GateType::Div => unreachable!(),
-            GateType::Add => unreachable!(),
-            GateType::Sub => unreachable!(),
-            GateType::Shl => unreachable!(),
-            GateType::Shr => unreachable!(),
-            GateType::Copy => unreachable!(),
-            GateType::Mux => match (&input_values[2], &input_values[0], &input_values[1]) {
-                (PtxtType::Bool(select), PtxtType::Bool(in_0), PtxtType::Bool(in_1)) => {
-                    PtxtType::Bool((*select && *in_0) || (!select && *in_1))
+            GateType::Mult
+            | GateType::Div
+            | GateType::Add
+            | GateType::Sub
+            | GateType::Shl
+            | GateType::Shr => self
+                .gate_type
+                .wrapping_eval(&input_values[0], &input_values[1]),
+            GateType::Copy => input_values[0],
+            GateType::Mux => match to_bit(&input_values[2]) {
+                // sel = 1 picks the first input.
+                Some(true) => from_bit(to_bit(&input_values[0])),
+                Some(false) => from_bit(to_bit(&input_values[1])),
+                None if to_bit(&input_values[0]) == to_bit(&input_values[1]) => {
+                    from_bit(to_bit(&input_values[0]))
                 }
-                _ => unreachable!(),
+                None => PtxtType::Unknown,
             },
-            GateType::Nand => PtxtType::Bool(!input_values.iter().all(|v| match v {
-                PtxtType::Bool(b) => *b,
-                _ => panic!("Expected PtxtType::Bool variant"),
-            })),
-            GateType::Nor => PtxtType::Bool(!input_values.iter().any(|v| match v {
-                PtxtType::Bool(b) => *b,
-                _ => panic!("Expected PtxtType::Bool variant"),
-            })),
-            GateType::Not => PtxtType::Bool(match input_values[0] {
-                PtxtType::Bool(b) => !b,
-                _ => panic!("Expected PtxtType::Bool variant"),
-            }),
-            GateType::Or => PtxtType::Bool(input_values.iter().any(|v| match v {
-                PtxtType::Bool(b) => *b,
-                _ => panic!("Expected PtxtType::Bool variant"),
-            })),
-            GateType::Xnor => PtxtType::Bool(
-                input_values
-                    .iter()
-                    .filter(|&v| match v {
-                        PtxtType::Bool(b) => *b,
-                        _ => panic!("Expected PtxtType::Bool variant"),
+            GateType::MuxN if !matches!(input_values[0], PtxtType::Bool(_) | PtxtType::Unknown) => {
+                // An integer index, taken modulo the number of data inputs
+                // like the select tree does.
+                let data = &input_values[1..];
+                data[(input_values[0].low_u128() % data.len() as u128) as usize]
+            }
+            GateType::MuxN => {
+                // The select bits come first, least significant first. An
+                // unknown select bit keeps both halves as candidates.
+                let (select_width, arity) = muxn_layout(input_values.len());
+                assert_select_bits(select_width, arity);
+                let (select, data) = input_values.split_at(select_width);
+                let candidates = (0..data.len())
+                    .filter(|index| {
+                        select.iter().enumerate().all(|(bit, v)| match to_bit(v) {
+                            Some(b) => ((index >> bit) & 1 == 1) == b,
+                            None => true,
+                        })
                     })
-                    .count()
-                    % 2
-                    != 1,
-            ),
-            GateType::Xor => PtxtType::Bool(
-                input_values
+                    .map(|index| to_bit(&data[index]))
+                    .dedup()
+                    .collect::<Vec<_>>();
+                match candidates[..] {
+                    [value] => from_bit(value),
+                    _ => PtxtType::Unknown,
+                }
+            }
+            GateType::PriorityEncoder
+                if !matches!(input_values[0], PtxtType::Bool(_) | PtxtType::Unknown) =>
+            {
+                // The index of the last request with its low bit set, or 0.
+                let index = input_values
                     .iter()
-                    .filter(|&v| match v {
-                        PtxtType::Bool(b) => *b,
-                        _ => panic!("Expected PtxtType::Bool variant"),
-                    })
-                    .count()
-                    % 2
-                    == 1,
-            ),
+                    .rposition(|request| request.bit(0))
+                    .unwrap_or(0);
+                let width = match input_values[0] {
+                    PtxtType::U8(_) => "u8",
+                    PtxtType::U16(_) => "u16",
+                    PtxtType::U32(_) => "u32",
+                    PtxtType::U64(_) => "u64",
+                    PtxtType::U128(_) => "u128",
+                    _ => "u256",
+                };
+                PtxtType::from_u128_wrapping(index as u128, width)
+            }
+            GateType::PriorityEncoder => {
+                // The first request is granted if none of the later ones is
+                // asserted.
+                let none_later = not_bit(or_bits(&input_values[1..]));
+                and_bits(&[input_values[0], none_later])
+            }
+            GateType::Nand => not_bit(and_bits(input_values)),
+            GateType::Nor => not_bit(or_bits(input_values)),
+            GateType::Not => not_bit(input_values[0]),
+            GateType::Or => or_bits(input_values),
+            GateType::Xnor => not_bit(xor_bits(input_values)),
+            GateType::Xor => xor_bits(input_values),
             GateType::Buf => input_values[0],
             GateType::ConstOne => PtxtType::Bool(true),
             GateType::ConstZero => PtxtType::Bool(false),
+            GateType::ConstUnknown => PtxtType::Unknown,
         };
 
         self.output
@@ -245,8 +514,9 @@ impl Gate {
         cycle: usize,
     ) -> Ciphertext {
         if let Some(encrypted_gate_output) = self.encrypted_gate_output.clone() {
-            if self.cycle == cycle {
-                println!("Found cycle {}, returning", cycle);
+            // Constants are encrypted once and reused in every cycle.
+            if self.cycle == cycle || self.is_constant() {
+                info_println!("Found cycle {}, returning", cycle);
                 return encrypted_gate_output;
             }
         }
@@ -263,6 +533,31 @@ impl Gate {
             GateType::Shr => panic!("Right shifts can't be mixed with Boolean ops!"),
             GateType::Copy => panic!("Arithmetic copies can't be mixed with Boolean ops!"),
             GateType::Mux => server_key.mux(&input_values[2], &input_values[0], &input_values[1]),
+            GateType::MuxN => {
+                // Select tree: each select bit, least significant first,
+                // halves the candidate inputs.
+                let (select_width, arity) = muxn_layout(input_values.len());
+                assert_select_bits(select_width, arity);
+                let (select, data) = input_values.split_at(select_width);
+                let mut candidates = data.to_vec();
+                for bit in select {
+                    candidates = candidates
+                        .chunks(2)
+                        .map(|pair| server_key.mux(bit, &pair[1], &pair[0]))
+                        .collect();
+                }
+                candidates.remove(0)
+            }
+            GateType::PriorityEncoder => match input_values.split_first() {
+                Some((request, [])) => request.clone(),
+                Some((request, [later, rest @ ..])) => {
+                    let any_later = rest
+                        .iter()
+                        .fold(later.clone(), |any, later| server_key.or(&any, later));
+                    server_key.and(request, &server_key.not(&any_later))
+                }
+                None => panic!("A priority encoder needs requests!"),
+            },
             GateType::Nand => server_key.nand(&input_values[0], &input_values[1]),
             GateType::Nor => server_key.nor(&input_values[0], &input_values[1]),
             GateType::Not => server_key.not(&input_values[0]),
@@ -270,8 +565,10 @@ impl Gate {
             GateType::Xnor => server_key.xnor(&input_values[0], &input_values[1]),
             GateType::Xor => server_key.xor(&input_values[0], &input_values[1]),
             GateType::Buf => input_values[0].clone(),
-            GateType::ConstOne => server_key.trivial_encrypt(true),
-            GateType::ConstZero => server_key.trivial_encrypt(false),
+            GateType::ConstUnknown => panic!("Unknown (x) values can't be encrypted!"),
+            GateType::ConstOne | GateType::ConstZero => {
+                server_key.trivial_encrypt(self.gate_type == GateType::ConstOne)
+            }
         };
         self.cycle = cycle;
         self.encrypted_gate_output = Some(encrypted_gate_output.clone());
@@ -299,7 +596,7 @@ impl Gate {
         );
         self.encrypted_lut_output = Some(ret.clone());
         let elapsed_time = Instant::now() - start_time;
-        println!("PBS time: {} us", elapsed_time.as_micros());
+        info_println!("PBS time: {} us", elapsed_time.as_micros());
         ret
     }
 
@@ -315,6 +612,89 @@ impl Gate {
         self.encrypted_multibit_output.clone()
     }
 
+    /// Select the data input at the encrypted integer `index`, modulo the
+    /// number of data inputs.
+    pub fn evaluate_encrypted_muxn_block(
+        &mut self,
+        index: &FheType,
+        data: &[FheType],
+        cycle: usize,
+    ) -> FheType {
+        if self.cycle == cycle {
+            match self.encrypted_multibit_output {
+                FheType::None => (),
+                _ => return self.encrypted_multibit_output.clone(),
+            }
+        }
+
+        self.encrypted_multibit_output = match index {
+            FheType::U8(index) => {
+                integer_select_tree!(index, data, U8, 8, |shift: u32| shift as u8)
+            }
+            FheType::U16(index) => {
+                integer_select_tree!(index, data, U16, 16, |shift: u32| shift as u16)
+            }
+            FheType::U32(index) => integer_select_tree!(index, data, U32, 32, |shift: u32| shift),
+            FheType::U64(index) => {
+                integer_select_tree!(index, data, U64, 64, |shift: u32| shift as u64)
+            }
+            FheType::U128(index) => {
+                integer_select_tree!(index, data, U128, 128, |shift: u32| shift as u128)
+            }
+            FheType::U256(index) => integer_select_tree!(index, data, U256, 256, |shift: u32| {
+                tfhe::integer::U256::from(U256::new(0, shift as u128))
+            }),
+            _ => panic!("evaluate_encrypted_muxn_block"),
+        };
+
+        self.cycle = cycle;
+        self.encrypted_multibit_output.clone()
+    }
+
+    /// Index of the last request with its low bit set, or 0 if there is
+    /// none, as an encrypted integer of the width of the requests.
+    pub fn evaluate_encrypted_priority_encoder_block(
+        &mut self,
+        requests: &[FheType],
+        cycle: usize,
+    ) -> FheType {
+        if self.cycle == cycle {
+            match self.encrypted_multibit_output {
+                FheType::None => (),
+                _ => return self.encrypted_multibit_output.clone(),
+            }
+        }
+
+        self.encrypted_multibit_output = match requests.first() {
+            Some(FheType::U8(_)) => {
+                integer_priority_encoder!(requests, U8, FheUint8, 8, |value: u32| value as u8)
+            }
+            Some(FheType::U16(_)) => {
+                integer_priority_encoder!(requests, U16, FheUint16, 16, |value: u32| value as u16)
+            }
+            Some(FheType::U32(_)) => {
+                integer_priority_encoder!(requests, U32, FheUint32, 32, |value: u32| value)
+            }
+            Some(FheType::U64(_)) => {
+                integer_priority_encoder!(requests, U64, FheUint64, 64, |value: u32| value as u64)
+            }
+            Some(FheType::U128(_)) => {
+                integer_priority_encoder!(requests, U128, FheUint128, 128, |value: u32| {
+                    value as u128
+                })
+            }
+            Some(FheType::U256(_)) => {
+                integer_priority_encoder!(requests, U256, FheUint256, 256, |value: u32| {
+                    tfhe::integer::U256::from(U256::new(0, value as u128))
+                })
+            }
+            _ => panic!("evaluate_encrypted_priority_encoder_block"),
+        };
+
+        self.cycle = cycle;
+        self.encrypted_multibit_output.clone()
+    }
+
     pub fn evaluate_encrypted_mul_block(
         &mut self,
         ct1: &FheType,
@@ -342,6 +722,9 @@ impl Gate {
             (FheType::U128(ct1_value), FheType::U128(ct2_value)) => {
                 FheType::U128(ct1_value * ct2_value)
             }
+            (FheType::U256(ct1_value), FheType::U256(ct2_value)) => {
+                FheType::U256(ct1_value * ct2_value)
+            }
             _ => panic!("evaluate_encrypted_mul_block"),
         };
 
@@ -376,6 +759,9 @@ impl Gate {
             (FheType::U128(ct1_value), PtxtType::U128(pt1_value)) => {
                 FheType::U128(ct1_value * pt1_value)
             }
+            (FheType::U256(ct1_value), PtxtType::U256(pt1_value)) => {
+                FheType::U256(ct1_value * tfhe::integer::U256::from(pt1_value))
+            }
             _ => panic!("evaluate_encrypted_mul_block_plain"),
         };
 
@@ -410,6 +796,9 @@ impl Gate {
             (FheType::U128(ct1_value), FheType::U128(ct2_value)) => {
                 FheType::U128(ct1_value / ct2_value)
             }
+            (FheType::U256(ct1_value), FheType::U256(ct2_value)) => {
+                FheType::U256(ct1_value / ct2_value)
+            }
             _ => panic!("evaluate_encrypted_div_block"),
         };
 
@@ -444,6 +833,9 @@ impl Gate {
             (FheType::U128(ct1_value), PtxtType::U128(pt1_value)) => {
                 FheType::U128(ct1_value / pt1_value)
             }
+            (FheType::U256(ct1_value), PtxtType::U256(pt1_value)) => {
+                FheType::U256(ct1_value / tfhe::integer::U256::from(pt1_value))
+            }
             _ => panic!("evaluate_encrypted_div_block_plain"),
         };
 
@@ -478,6 +870,9 @@ impl Gate {
             (FheType::U128(ct1_value), FheType::U128(ct2_value)) => {
                 FheType::U128(ct1_value + ct2_value)
             }
+            (FheType::U256(ct1_value), FheType::U256(ct2_value)) => {
+                FheType::U256(ct1_value + ct2_value)
+            }
             _ => panic!("evaluate_encrypted_add_block"),
         };
 
@@ -535,6 +930,13 @@ impl Gate {
                     FheType::U128(ct1_value >> ct2_value)
                 }
             }
+            (FheType::U256(ct1_value), FheType::U256(ct2_value)) => {
+                if dir {
+                    FheType::U256(ct1_value << ct2_value)
+                } else {
+                    FheType::U256(ct1_value >> ct2_value)
+                }
+            }
             _ => panic!("evaluate_encrypted_shift_block"),
         };
 
@@ -592,6 +994,14 @@ impl Gate {
                     FheType::U128(ct1_value >> pt1_value)
                 }
             }
+            (FheType::U256(ct1_value), PtxtType::U256(pt1_value)) => {
+                let pt1_value = tfhe::integer::U256::from(pt1_value);
+                if dir {
+                    FheType::U256(ct1_value << pt1_value)
+                } else {
+                    FheType::U256(ct1_value >> pt1_value)
+                }
+            }
             _ => panic!("evaluate_encrypted_shift_block_plain"),
         };
 
@@ -626,6 +1036,9 @@ impl Gate {
             (FheType::U128(ct1_value), PtxtType::U128(pt1_value)) => {
                 FheType::U128(ct1_value + pt1_value)
             }
+            (FheType::U256(ct1_value), PtxtType::U256(pt1_value)) => {
+                FheType::U256(ct1_value + tfhe::integer::U256::from(pt1_value))
+            }
             _ => panic!("evaluate_encrypted_add_block_plain"),
         };
 
@@ -660,6 +1073,9 @@ impl Gate {
             (FheType::U128(ct1_value), FheType::U128(ct2_value)) => {
                 FheType::U128(ct1_value - ct2_value)
             }
+            (FheType::U256(ct1_value), FheType::U256(ct2_value)) => {
+                FheType::U256(ct1_value - ct2_value)
+            }
             _ => panic!("evaluate_encrypted_sub_block"),
         };
 
@@ -694,6 +1110,9 @@ impl Gate {
             (FheType::U128(ct1_value), PtxtType::U128(pt1_value)) => {
                 FheType::U128(ct1_value - pt1_value)
             }
+            (FheType::U256(ct1_value), PtxtType::U256(pt1_value)) => {
+                FheType::U256(ct1_value - tfhe::integer::U256::from(pt1_value))
+            }
             _ => panic!("evaluate_encrypted_sub_block_plain"),
         };
 
@@ -701,6 +1120,21 @@ impl Gate {
         self.encrypted_multibit_output.clone()
     }
 
+    pub fn evaluate_encrypted_constant_lut(
+        &mut self,
+        server_key: &ServerKeyShortInt,
+    ) -> CiphertextBase {
+        // Constants are encrypted once and reused in every cycle.
+        if let Some(encrypted_lut_output) = self.encrypted_lut_output.clone() {
+            return encrypted_lut_output;
+        }
+
+        let out = server_key.create_trivial((self.gate_type == GateType::ConstOne) as u64);
+        self.encrypted_lut_output = Some(out.clone());
+
+        out
+    }
+
     pub fn evaluate_encrypted_dff(
         &mut self,
         input_values: &[CiphertextBase],
@@ -769,7 +1203,7 @@ pub fn lut(
             sks.smart_neg(&mut ctxts[0])
         }
     } else {
-        println!("gate id: {:?}", &gate_id);
+        info_println!("gate id: {:?}", &gate_id);
         let ctxts_len: u8 = (ctxts.len() - 1) as u8;
         let ct_sum = ctxts
             .iter_mut()
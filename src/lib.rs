@@ -1,22 +1,282 @@
+/// `println!` that is silenced by `--quiet`.
+#[macro_export]
+macro_rules! info_println {
+    ($($arg:tt)*) => {
+        if !$crate::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
 pub mod ascii;
 pub mod circuit;
 pub mod gates;
+pub mod manifest;
 pub mod verilog_parser;
 
 use clap::{builder::PossibleValue, value_parser, Arg, ArgAction, ArgMatches, Command};
 use std::fmt;
-use std::{collections::HashMap, fmt::Debug, str::FromStr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    str::FromStr,
+};
 use termion::color;
 use tfhe::prelude::*;
-use tfhe::{FheUint128, FheUint16, FheUint32, FheUint64, FheUint8};
+use tfhe::{FheUint128, FheUint16, FheUint256, FheUint32, FheUint64, FheUint8};
 use thiserror::Error;
 
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppress all informational output; errors still go to stderr.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
 #[derive(Debug, Error)]
 pub enum PtxtError {
     #[error("Invalid input")]
     InvalidInput,
 }
 
+#[derive(Debug, Error)]
+pub enum RunError {
+    #[error("Expected {expected} input values (one per primary input), got {actual}")]
+    InputCountMismatch { expected: usize, actual: usize },
+}
+
+/// A 256-bit plaintext word, stored as its high and low 128-bit halves (in
+/// that order, so the derived ordering is numeric).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct U256 {
+    high: u128,
+    low: u128,
+}
+
+impl U256 {
+    pub const MAX: U256 = U256 {
+        high: u128::MAX,
+        low: u128::MAX,
+    };
+
+    pub fn new(high: u128, low: u128) -> Self {
+        U256 { high, low }
+    }
+
+    pub fn high(&self) -> u128 {
+        self.high
+    }
+
+    pub fn low(&self) -> u128 {
+        self.low
+    }
+
+    /// self * factor + addend, or None on overflow. Both operands must fit
+    /// in 64 bits.
+    fn checked_mul_add_small(&self, factor: u128, addend: u128) -> Option<Self> {
+        let mask = u64::MAX as u128;
+        let p0 = (self.low & mask) * factor + addend;
+        let p1 = (self.low >> 64) * factor + (p0 >> 64);
+        let low = ((p1 & mask) << 64) | (p0 & mask);
+        let high = self.high.checked_mul(factor)?.checked_add(p1 >> 64)?;
+
+        Some(U256 { high, low })
+    }
+
+    /// Divide by a divisor of at most 64 bits, returning quotient and
+    /// remainder.
+    fn div_rem_small(&self, divisor: u128) -> (Self, u128) {
+        let mask = u64::MAX as u128;
+        let mut limbs = [
+            self.high >> 64,
+            self.high & mask,
+            self.low >> 64,
+            self.low & mask,
+        ];
+        let mut remainder = 0;
+        for limb in limbs.iter_mut() {
+            let current = (remainder << 64) | *limb;
+            *limb = current / divisor;
+            remainder = current % divisor;
+        }
+        let quotient = U256 {
+            high: (limbs[0] << 64) | limbs[1],
+            low: (limbs[2] << 64) | limbs[3],
+        };
+
+        (quotient, remainder)
+    }
+
+    pub fn wrapping_add(self, rhs: Self) -> Self {
+        let (low, carry) = self.low.overflowing_add(rhs.low);
+        let high = self.high.wrapping_add(rhs.high).wrapping_add(carry as u128);
+
+        U256 { high, low }
+    }
+
+    pub fn wrapping_sub(self, rhs: Self) -> Self {
+        let (low, borrow) = self.low.overflowing_sub(rhs.low);
+        let high = self
+            .high
+            .wrapping_sub(rhs.high)
+            .wrapping_sub(borrow as u128);
+
+        U256 { high, low }
+    }
+
+    pub fn wrapping_mul(self, rhs: Self) -> Self {
+        // Schoolbook multiplication on 64-bit limbs, least significant first,
+        // dropping everything past 256 bits.
+        let limbs = |value: Self| {
+            [
+                value.low as u64,
+                (value.low >> 64) as u64,
+                value.high as u64,
+                (value.high >> 64) as u64,
+            ]
+        };
+        let (lhs, rhs) = (limbs(self), limbs(rhs));
+        let mut product = [0u64; 4];
+        for (i, &lhs_limb) in lhs.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &rhs_limb) in rhs.iter().enumerate().take(4 - i) {
+                let current = product[i + j] as u128 + lhs_limb as u128 * rhs_limb as u128 + carry;
+                product[i + j] = current as u64;
+                carry = current >> 64;
+            }
+        }
+
+        U256 {
+            high: ((product[3] as u128) << 64) | product[2] as u128,
+            low: ((product[1] as u128) << 64) | product[0] as u128,
+        }
+    }
+
+    /// Shift left by `shift` modulo 256, like the primitive `wrapping_shl`.
+    pub fn wrapping_shl(self, shift: u32) -> Self {
+        match shift % 256 {
+            0 => self,
+            shift if shift < 128 => U256 {
+                high: (self.high << shift) | (self.low >> (128 - shift)),
+                low: self.low << shift,
+            },
+            shift => U256 {
+                high: self.low << (shift - 128),
+                low: 0,
+            },
+        }
+    }
+
+    /// Shift right by `shift` modulo 256, like the primitive `wrapping_shr`.
+    pub fn wrapping_shr(self, shift: u32) -> Self {
+        match shift % 256 {
+            0 => self,
+            shift if shift < 128 => U256 {
+                high: self.high >> shift,
+                low: (self.low >> shift) | (self.high << (128 - shift)),
+            },
+            shift => U256 {
+                high: 0,
+                low: self.high >> (shift - 128),
+            },
+        }
+    }
+
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        if rhs == U256::default() {
+            return None;
+        }
+
+        // Restoring long division, one bit at a time.
+        let mut quotient = U256::default();
+        let mut remainder = U256::default();
+        for bit in (0..256).rev() {
+            // The remainder is below rhs, so if the shift overflows the true
+            // remainder exceeds rhs and the wrapping subtraction is exact.
+            let overflow = remainder.high >> 127 == 1;
+            remainder = remainder.wrapping_shl(1);
+            remainder.low |= (self.wrapping_shr(bit).low & 1) as u128;
+            if overflow || remainder >= rhs {
+                remainder = remainder.wrapping_sub(rhs);
+                quotient = quotient.wrapping_add(U256::from(1u128).wrapping_shl(bit));
+            }
+        }
+
+        Some(quotient)
+    }
+
+    pub fn from_str_radix(s: &str, radix: u32) -> Result<Self, PtxtError> {
+        if s.is_empty() || !(2..=16).contains(&radix) {
+            return Err(PtxtError::InvalidInput);
+        }
+        let mut value = U256::default();
+        for c in s.chars() {
+            let digit = c.to_digit(radix).ok_or(PtxtError::InvalidInput)?;
+            value = value
+                .checked_mul_add_small(radix as u128, digit as u128)
+                .ok_or(PtxtError::InvalidInput)?;
+        }
+
+        Ok(value)
+    }
+}
+
+impl From<u128> for U256 {
+    fn from(low: u128) -> Self {
+        U256 { high: 0, low }
+    }
+}
+
+impl From<U256> for tfhe::integer::U256 {
+    fn from(value: U256) -> Self {
+        tfhe::integer::U256::from((value.low, value.high))
+    }
+}
+
+impl From<tfhe::integer::U256> for U256 {
+    fn from(value: tfhe::integer::U256) -> Self {
+        let (low, high) = value.to_low_high_u128();
+        U256 { high, low }
+    }
+}
+
+impl FromStr for U256 {
+    type Err = PtxtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        U256::from_str_radix(s, 10)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.high == 0 {
+            return write!(f, "{}", self.low);
+        }
+
+        // Peel off 19 decimal digits at a time (10^19 fits in 64 bits).
+        let chunk = 10_000_000_000_000_000_000u128;
+        let mut chunks = vec![];
+        let mut value = *self;
+        while value.high != 0 || value.low >= chunk {
+            let (quotient, remainder) = value.div_rem_small(chunk);
+            chunks.push(remainder);
+            value = quotient;
+        }
+        write!(f, "{}", value.low)?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:019}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub enum PtxtType {
     Bool(bool),
@@ -25,6 +285,9 @@ pub enum PtxtType {
     U32(u32),
     U64(u64),
     U128(u128),
+    U256(U256),
+    // An unknown bit (`x` or `z`), propagated by plaintext evaluation.
+    Unknown,
     None,
 }
 
@@ -35,6 +298,7 @@ pub enum FheType {
     U32(FheUint32),
     U64(FheUint64),
     U128(FheUint128),
+    U256(FheUint256),
     None,
 }
 
@@ -54,6 +318,8 @@ impl FromStr for PtxtType {
             Ok(PtxtType::U64(value))
         } else if let Ok(value) = u128::from_str(s) {
             Ok(PtxtType::U128(value))
+        } else if let Ok(value) = U256::from_str(s) {
+            Ok(PtxtType::U256(value))
         } else {
             Err(PtxtError::InvalidInput)
         }
@@ -69,11 +335,138 @@ impl fmt::Display for PtxtType {
             PtxtType::U32(value) => write!(f, "U32({})", value),
             PtxtType::U64(value) => write!(f, "U64({})", value),
             PtxtType::U128(value) => write!(f, "U128({})", value),
+            PtxtType::U256(value) => write!(f, "U256({})", value),
+            PtxtType::Unknown => write!(f, "x"),
             PtxtType::None => write!(f, "None"),
         }
     }
 }
 
+impl PtxtType {
+    /// Parse a hex string (with or without a `0x` prefix) into the plaintext
+    /// type named by `width` ("bool", "u8", ..., "u128").
+    pub fn from_hex(s: &str, width: &str) -> Result<Self, PtxtError> {
+        let digits = s.trim().trim_start_matches("0x").trim_start_matches("0X");
+        let ptxt = match width {
+            "bool" => match u8::from_str_radix(digits, 16) {
+                Ok(0) => PtxtType::Bool(false),
+                Ok(1) => PtxtType::Bool(true),
+                _ => return Err(PtxtError::InvalidInput),
+            },
+            "u8" => PtxtType::U8(u8::from_str_radix(digits, 16).or(Err(PtxtError::InvalidInput))?),
+            "u16" => {
+                PtxtType::U16(u16::from_str_radix(digits, 16).or(Err(PtxtError::InvalidInput))?)
+            }
+            "u32" => {
+                PtxtType::U32(u32::from_str_radix(digits, 16).or(Err(PtxtError::InvalidInput))?)
+            }
+            "u64" => {
+                PtxtType::U64(u64::from_str_radix(digits, 16).or(Err(PtxtError::InvalidInput))?)
+            }
+            "u128" => {
+                PtxtType::U128(u128::from_str_radix(digits, 16).or(Err(PtxtError::InvalidInput))?)
+            }
+            "u256" => PtxtType::U256(U256::from_str_radix(digits, 16)?),
+            _ => return Err(PtxtError::InvalidInput),
+        };
+
+        Ok(ptxt)
+    }
+
+    /// The low 128 bits of the value.
+    pub fn low_u128(&self) -> u128 {
+        match self {
+            PtxtType::Bool(value) => *value as u128,
+            PtxtType::U8(value) => *value as u128,
+            PtxtType::U16(value) => *value as u128,
+            PtxtType::U32(value) => *value as u128,
+            PtxtType::U64(value) => *value as u128,
+            PtxtType::U128(value) => *value,
+            PtxtType::U256(value) => value.low,
+            PtxtType::Unknown => panic!("Can't convert an unknown value"),
+            PtxtType::None => panic!("Can't convert a None value"),
+        }
+    }
+
+    /// Whether the values are equal regardless of their width, e.g. `U8(5)`
+    /// and `U32(5)`. Booleans, unknowns and None only equal their own variant.
+    pub fn value_eq(&self, other: &PtxtType) -> bool {
+        match (self.numeric_value(), other.numeric_value()) {
+            (Some(lhs), Some(rhs)) => lhs == rhs,
+            _ => self == other,
+        }
+    }
+
+    fn numeric_value(&self) -> Option<U256> {
+        match self {
+            PtxtType::Bool(_) | PtxtType::Unknown | PtxtType::None => None,
+            PtxtType::U256(value) => Some(*value),
+            _ => Some(U256::from(self.low_u128())),
+        }
+    }
+
+    /// Bit `index` of an integer value, least significant first.
+    pub fn bit(&self, index: usize) -> bool {
+        match self {
+            PtxtType::Bool(value) => index == 0 && *value,
+            _ => {
+                let value = self
+                    .numeric_value()
+                    .unwrap_or_else(|| panic!("{} has no bits", self));
+                index < 256 && value.wrapping_shr(index as u32).low & 1 == 1
+            }
+        }
+    }
+
+    /// The integer of the plaintext type named by `width` with `bits`, least
+    /// significant first. Bits past the width are dropped.
+    pub fn from_bits(bits: &[bool], width: &str) -> Self {
+        let (mut high, mut low) = (0u128, 0u128);
+        for (index, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+            match index {
+                0..=127 => low |= 1 << index,
+                128..=255 => high |= 1 << (index - 128),
+                _ => {}
+            }
+        }
+
+        match width {
+            "u256" => PtxtType::U256(U256::new(high, low)),
+            _ => PtxtType::from_u128_wrapping(low, width),
+        }
+    }
+
+    /// `value` truncated to the plaintext type named by `width`.
+    pub fn from_u128_wrapping(value: u128, width: &str) -> Self {
+        match width {
+            "bool" => PtxtType::Bool(value & 1 == 1),
+            "u8" => PtxtType::U8(value as u8),
+            "u16" => PtxtType::U16(value as u16),
+            "u32" => PtxtType::U32(value as u32),
+            "u64" => PtxtType::U64(value as u64),
+            "u128" => PtxtType::U128(value),
+            "u256" => PtxtType::U256(U256::from(value)),
+            _ => panic!("Invalid plaintext width \"{}\"", width),
+        }
+    }
+
+    /// Format the value as lowercase hex, zero-padded to the width of its
+    /// type (e.g. `U8(0xA5)` is "a5" and `U16(0xA5)` is "00a5").
+    pub fn to_hex(&self) -> String {
+        match self {
+            PtxtType::Bool(value) => format!("{:x}", *value as u8),
+            PtxtType::U8(value) => format!("{:02x}", value),
+            PtxtType::U16(value) => format!("{:04x}", value),
+            PtxtType::U32(value) => format!("{:08x}", value),
+            PtxtType::U64(value) => format!("{:016x}", value),
+            PtxtType::U128(value) => format!("{:032x}", value),
+            PtxtType::U256(value) => format!("{:032x}{:032x}", value.high, value.low),
+            // Like the CSV output, a wire without a value is written as x.
+            PtxtType::Unknown | PtxtType::None => "x".to_owned(),
+        }
+    }
+}
+
 impl FheType {
     pub fn decrypt(&self, client_key: &tfhe::ClientKey) -> PtxtType {
         match self {
@@ -82,6 +475,10 @@ impl FheType {
             FheType::U32(inner_value) => PtxtType::U32(inner_value.decrypt(client_key)),
             FheType::U64(inner_value) => PtxtType::U64(inner_value.decrypt(client_key)),
             FheType::U128(inner_value) => PtxtType::U128(inner_value.decrypt(client_key)),
+            FheType::U256(inner_value) => {
+                let value: tfhe::integer::U256 = inner_value.decrypt(client_key);
+                PtxtType::U256(U256::from(value))
+            }
             FheType::None => panic!("Decrypt found a None value"),
         }
     }
@@ -92,6 +489,7 @@ pub fn parse_input_wire(wire: &str, ptxt_type: &str) -> PtxtType {
         "bool" => {
             let init_value = match wire.trim() {
                 "1" => true,
+                "x" | "X" | "z" | "Z" => return PtxtType::Unknown,
                 s => s.parse::<bool>().unwrap_or(false),
             };
             PtxtType::Bool(init_value)
@@ -101,6 +499,7 @@ pub fn parse_input_wire(wire: &str, ptxt_type: &str) -> PtxtType {
         "u32" => PtxtType::U32(wire.parse::<u32>().unwrap()),
         "u64" => PtxtType::U64(wire.parse::<u64>().unwrap()),
         "u128" => PtxtType::U128(wire.parse::<u128>().unwrap()),
+        "u256" => PtxtType::U256(wire.parse::<U256>().unwrap()),
         _ => unreachable!(),
     }
 }
@@ -116,7 +515,7 @@ pub fn get_input_wire_map(
     arithmetic_type: &str,
 ) -> HashMap<String, PtxtType> {
     if let Some(wire_file_name) = &inputs_filename {
-        println!(
+        info_println!(
             "{}[✓]{} Input wires were provided.",
             color::Fg(color::LightGreen),
             color::Fg(color::Reset)
@@ -124,7 +523,7 @@ pub fn get_input_wire_map(
 
         verilog_parser::read_input_wires(wire_file_name, arithmetic_type)
     } else if !wire_inputs.is_empty() {
-        println!(
+        info_println!(
             "{}[✓]{} Input wires were provided.",
             color::Fg(color::LightGreen),
             color::Fg(color::Reset)
@@ -164,7 +563,7 @@ pub fn get_input_wire_map(
             })
             .collect::<HashMap<String, PtxtType>>()
     } else {
-        println!(
+        info_println!(
             "{}[!]{} No input wires specified, they will be initialized to false.",
             color::Fg(color::LightYellow),
             color::Fg(color::Reset)
@@ -178,6 +577,209 @@ pub fn get_input_wire_map(
     }
 }
 
+/// Give `value` (parsed as `arithmetic_type`) to every wire of `input_wires`
+/// missing from `input_wire_map`, instead of failing on it. The register
+/// outputs among `input_wires` are skipped: the circuit sets them to their
+/// initial values.
+pub fn fill_unspecified_inputs(
+    mut input_wire_map: HashMap<String, PtxtType>,
+    input_wires: &[String],
+    dff_outputs: &[String],
+    value: &str,
+    arithmetic_type: &str,
+) -> HashMap<String, PtxtType> {
+    let value = parse_input_wire(value, arithmetic_type);
+    input_wire_map.remove("dummy");
+    for wire in input_wires
+        .iter()
+        .filter(|wire| !dff_outputs.contains(wire))
+    {
+        input_wire_map.entry(wire.to_string()).or_insert(value);
+    }
+
+    input_wire_map
+}
+
+/// Wires that are given a value by more than one `-w` argument, in the order
+/// they are repeated. A bus given with a width sets each of its bits.
+pub fn duplicate_input_wires(wire_inputs: &[Vec<&String>]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut duplicates = vec![];
+    for parts in wire_inputs.iter() {
+        let wires = match parts
+            .get(2)
+            .and_then(|width| width.trim().parse::<usize>().ok())
+        {
+            Some(width) => (0..width)
+                .map(|idx| format!("{}[{}]", parts[0], idx))
+                .collect(),
+            None => vec![parts[0].to_string()],
+        };
+        for wire in wires {
+            if !seen.insert(wire.clone()) && !duplicates.contains(&wire) {
+                duplicates.push(wire);
+            }
+        }
+    }
+
+    duplicates
+}
+
+/// Rename `wire` according to `aliases`. A bus alias also renames its bits,
+/// e.g. `a -> b` maps `a[3]` to `b[3]`.
+pub fn rename_wire(wire: &str, aliases: &HashMap<String, String>) -> String {
+    if let Some(alias) = aliases.get(wire) {
+        return alias.clone();
+    }
+    if let Some((bus, bit)) = wire.split_once('[') {
+        if let Some(alias) = aliases.get(bus) {
+            return format!("{}[{}", alias, bit);
+        }
+    }
+
+    wire.to_owned()
+}
+
+/// Rename the wires of `wire_map` according to `aliases`.
+pub fn rename_wires<V>(
+    wire_map: HashMap<String, V>,
+    aliases: &HashMap<String, String>,
+) -> HashMap<String, V> {
+    wire_map
+        .into_iter()
+        .map(|(wire, value)| (rename_wire(&wire, aliases), value))
+        .collect()
+}
+
+/// Resolve the arithmetic type: `--arithmetic` takes precedence, otherwise the
+/// netlist's `(* helm_arith = "..." *)` module attribute is used.
+pub fn get_arithmetic_type(arithmetic_flag: Option<&String>, verilog_file: &str) -> Option<String> {
+    if let Some(arithmetic_type) = arithmetic_flag {
+        return Some(arithmetic_type.to_owned());
+    }
+
+    let attributes = verilog_parser::read_module_attributes(verilog_file);
+    let arithmetic_type = attributes.get("helm_arith")?;
+    Some(arith_attribute(arithmetic_type).unwrap_or_else(|message| {
+        panic!(
+            "{}[!]{} {}.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            message
+        )
+    }))
+}
+
+/// Check the value of a `helm_arith` attribute.
+pub(crate) fn arith_attribute(arithmetic_type: &str) -> Result<String, String> {
+    match arithmetic_type {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "u256" => Ok(arithmetic_type.to_owned()),
+        _ => Err(format!(
+            "Invalid helm_arith attribute \"{}\" (expected u8, u16, u32, u64, u128 or u256)",
+            arithmetic_type
+        )),
+    }
+}
+
+/// Resolve the number of cycles: `--cycles` takes precedence, then the
+/// netlist's `(* helm_cycles = N *)` module attribute, then a companion file
+/// next to the netlist with the `.cycles` extension, and finally one cycle.
+pub fn get_cycle_count(cycles_flag: Option<usize>, verilog_file: &str) -> usize {
+    if let Some(cycles) = cycles_flag {
+        return cycles;
+    }
+
+    let attributes = verilog_parser::read_module_attributes(verilog_file);
+    netlist_cycle_count(verilog_file, &attributes)
+        .unwrap_or_else(|message| {
+            panic!(
+                "{}[!]{} {}.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                message
+            )
+        })
+        .unwrap_or(1)
+}
+
+/// Number of cycles the netlist sets with its `helm_cycles` attribute or its
+/// `.cycles` companion file, if it sets one.
+pub(crate) fn netlist_cycle_count(
+    verilog_file: &str,
+    attributes: &HashMap<String, String>,
+) -> Result<Option<usize>, String> {
+    let companion = std::path::Path::new(verilog_file).with_extension("cycles");
+    let (source, cycles) = match attributes.get("helm_cycles") {
+        Some(cycles) => ("helm_cycles attribute".to_owned(), cycles.to_owned()),
+        None => match std::fs::read_to_string(&companion) {
+            Ok(contents) => (companion.display().to_string(), contents.trim().to_owned()),
+            Err(_) => return Ok(None),
+        },
+    };
+    match cycles.parse::<usize>() {
+        Ok(cycles) => Ok(Some(cycles)),
+        Err(_) => Err(format!(
+            "Invalid cycle count \"{}\" in {} (expected a number)",
+            cycles, source
+        )),
+    }
+}
+
+/// Evaluate `verilog` in plaintext for `cycles` cycles with positional
+/// inputs: `inputs[i]` drives the i-th primary input in declaration order
+/// (bus bits from the lowest index). Registers start from their initial
+/// values. Returns the value of every output wire.
+pub fn run_circuit_positional(
+    verilog: &str,
+    inputs: &[PtxtType],
+    cycles: usize,
+) -> Result<HashMap<String, PtxtType>, RunError> {
+    let arithmetic = get_arithmetic_type(None, verilog);
+    let (gates, wire_set, input_wires, output_wires, dff_outputs, _, _) =
+        verilog_parser::read_verilog_file(verilog, arithmetic.is_some());
+    let primary_inputs = input_wires
+        .iter()
+        .filter(|wire| !dff_outputs.contains(wire))
+        .collect::<Vec<_>>();
+    if primary_inputs.len() != inputs.len() {
+        return Err(RunError::InputCountMismatch {
+            expected: primary_inputs.len(),
+            actual: inputs.len(),
+        });
+    }
+
+    let register_init = verilog_parser::read_register_init_values(verilog);
+    let mut user_inputs = primary_inputs
+        .into_iter()
+        .cloned()
+        .zip(inputs.iter().copied())
+        .collect::<HashMap<_, _>>();
+    for wire in dff_outputs.iter() {
+        let init = register_init
+            .get(wire)
+            .copied()
+            .unwrap_or(PtxtType::Bool(false));
+        user_inputs.insert(wire.to_string(), init);
+    }
+
+    let mut circuit = circuit::Circuit::new(gates, &input_wires, &output_wires, &dff_outputs);
+    circuit.set_register_init_values(register_init);
+    circuit.optimize();
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let ptxt_type = arithmetic.as_deref().unwrap_or("bool");
+    let mut wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, ptxt_type);
+    for _ in 0..cycles.max(1) {
+        wire_map = circuit.evaluate(&wire_map);
+    }
+
+    Ok(output_wires
+        .iter()
+        .map(|wire| (wire.to_string(), wire_map[wire]))
+        .collect())
+}
+
 pub fn hex_to_bitstring(hex_string: &str) -> String {
     let mut bit_string = String::new();
     for hex_char in hex_string.chars() {
@@ -203,7 +805,21 @@ pub fn parse_args() -> ArgMatches {
                 .short('v')
                 .value_name("FILE")
                 .help("Verilog input file to evaluate")
-                .required(true),
+                .required_unless_present("list-gate-types"),
+        )
+        .arg(
+            Arg::new("list-gate-types")
+                .long("list-gate-types")
+                .help("Print the cells the netlist parser recognizes, with their pins and function, and exit")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("check-only")
+                .long("check-only")
+                .help("Only check the syntax of the Verilog file and exit")
+                .required(false)
+                .action(ArgAction::SetTrue),
         )
         .arg(
             Arg::new("input-wires")
@@ -217,15 +833,38 @@ pub fn parse_args() -> ArgMatches {
                 .conflicts_with("input-wires-file")
                 .required(false),
         )
+        .arg(
+            Arg::new("default-input")
+                .long("default-input")
+                .value_name("VALUE")
+                .help("Value of the input wires that are not given one, instead of false/0")
+                .required(false)
+                .value_parser(value_parser!(String)),
+        )
+        .arg(
+            Arg::new("dedup-inputs")
+                .long("dedup-inputs")
+                .help("Keep the last value of a wire given more than once with -w, instead of failing")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("input-wires-file")
                 .long("input-wires-file")
                 .short('i')
                 .value_name("FILE")
-                .help("CSV file that contains the input wire values (wire, value)")
+                .help("CSV file that contains the input wire values (wire, value or =other_wire)")
                 .conflicts_with("input-wires")
                 .required(false),
         )
+        .arg(
+            Arg::new("input-binary")
+                .long("input-binary")
+                .value_name("FILE")
+                .help("Binary file that contains the input wire values, faster than CSV for large input sets")
+                .conflicts_with_all(["input-wires", "input-wires-file"])
+                .required(false),
+        )
         .arg(
             Arg::new("output-wires-file")
                 .long("output-wires-file")
@@ -235,18 +874,47 @@ pub fn parse_args() -> ArgMatches {
                 .required(false)
                 .value_parser(clap::value_parser!(String)),
         )
+        .arg(
+            Arg::new("output-binary")
+                .long("output-binary")
+                .value_name("FILE")
+                .help("Binary file to write the output wires, in the format of --input-binary")
+                .required(false),
+        )
+        .arg(
+            Arg::new("wire-aliases")
+                .long("wire-aliases")
+                .value_name("FILE")
+                .help("CSV file that maps wire names of the input/output files to netlist wires (file_name, netlist_name)")
+                .required(false),
+        )
+        .arg(
+            Arg::new("sort-outputs")
+                .long("sort-outputs")
+                .help("Write the output wires sorted by name instead of in port order")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hex-output")
+                .long("hex-output")
+                .help("Write the output wire values in hex")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("arithmetic")
                 .long("arithmetic")
                 .short('a')
                 .value_name("TYPE")
-                .help("Precision for arithmetic mode")
+                .help("Precision for arithmetic mode (overrides the helm_arith attribute)")
                 .value_parser([
                     PossibleValue::new("u8"),
                     PossibleValue::new("u16"),
                     PossibleValue::new("u32"),
                     PossibleValue::new("u64"),
                     PossibleValue::new("u128"),
+                    PossibleValue::new("u256"),
                 ])
                 .required(false),
         )
@@ -255,11 +923,200 @@ pub fn parse_args() -> ArgMatches {
                 .long("cycles")
                 .short('c')
                 .value_name("NUMBER")
-                .help("Number of cycles for sequential circuits")
+                .help("Number of cycles for sequential circuits; without it, the helm_cycles attribute, then the .cycles file next to the netlist, then 1")
+                .required(false)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("constraints")
+                .long("constraints")
+                .value_name("FILE")
+                .help("SDC constraints file whose set_case_analysis constants tie primary inputs")
+                .required(false),
+        )
+        .arg(
+            Arg::new("patch")
+                .long("patch")
+                .value_name("FILE")
+                .help("Edit the netlist before evaluating it, one edit per line: type GATE TYPE, rewire GATE INDEX WIRE, or tie WIRE 0|1")
+                .required(false),
+        )
+        .arg(
+            Arg::new("only-combinational")
+                .long("only-combinational")
+                .help("Cut the circuit at its registers: evaluate the combinational logic once, with register outputs as inputs and register inputs as outputs")
+                .required(false)
+                .conflicts_with("cycles")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("sweep-widths")
+                .long("sweep-widths")
+                .help("Evaluate an arithmetic circuit in plaintext at every width, report whether the outputs agree (masked to 8 bits), and exit")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("compare-backends")
+                .long("compare-backends")
+                .help("Also evaluate an arithmetic circuit bit-blasted on the boolean backend, check that the outputs match, and report the time of each backend")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("adder")
+                .long("adder")
+                .value_name("ARCHITECTURE")
+                .help("Carry chain of the adders that --compare-backends bit-blasts")
+                .value_parser([
+                    PossibleValue::new("ripple"),
+                    PossibleValue::new("cla"),
+                    PossibleValue::new("carry-select"),
+                ])
+                .default_value("ripple")
+                .requires("compare-backends")
+                .required(false),
+        )
+        .arg(
+            Arg::new("max-depth")
+                .long("max-depth")
+                .value_name("NUMBER")
+                .help("Reject circuits whose critical path is deeper than NUMBER levels")
                 .required(false)
-                .default_value("1")
                 .value_parser(clap::value_parser!(usize)),
         )
+        .arg(
+            Arg::new("validate-widths")
+                .long("validate-widths")
+                .help("Reject arithmetic circuits whose gates mix operands or outputs of different declared widths")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("lazy-encryption")
+                .long("lazy-encryption")
+                .help("Encrypt each input wire only when a gate first reads it (gates mode)")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["time-limit-per-gate", "work-stealing"]),
+        )
+        .arg(
+            Arg::new("checkpoint")
+                .long("checkpoint")
+                .value_name("WIRE")
+                .action(ArgAction::Append)
+                .help("Decrypt and print WIRE as soon as it is computed; can be repeated (gates mode, leaks the value)")
+                .required(false),
+        )
+        .arg(
+            Arg::new("trivial-input")
+                .long("trivial-input")
+                .value_name("WIRE")
+                .action(ArgAction::Append)
+                .help("Encrypt input WIRE trivially, without noise, for public constants only; can be repeated (gates mode)")
+                .required(false),
+        )
+        .arg(
+            Arg::new("stream-outputs")
+                .long("stream-outputs")
+                .value_name("FILE")
+                .help("CSV file to append each output to (cycle, wire, value) as soon as it is computed, flushing every row (gates mode)")
+                .required(false)
+                .conflicts_with("batch-vectors"),
+        )
+        .arg(
+            Arg::new("batch-vectors")
+                .long("batch-vectors")
+                .value_name("FILE")
+                .help("CSV file of input vectors (a header row of wire names, then one row per vector) to evaluate concurrently, sharing the circuit and keys (gates mode)")
+                .required(false)
+                .conflicts_with_all(["checkpoint", "bootstrap-budget", "time-limit-per-gate"]),
+        )
+        .arg(
+            Arg::new("work-stealing")
+                .long("work-stealing")
+                .help("Evaluate each gate as soon as its inputs are ready instead of level by level (gates mode)")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("bootstrap-budget"),
+        )
+        .arg(
+            Arg::new("bootstrap-budget")
+                .long("bootstrap-budget")
+                .value_name("NUMBER")
+                .help("Abort the evaluation before it spends more than NUMBER bootstraps (gates mode)")
+                .required(false)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("time-limit-per-gate")
+                .long("time-limit-per-gate")
+                .value_name("SECONDS")
+                .help("Abort if a single encrypted gate evaluation takes longer than SECONDS (gates and LUTs modes)")
+                .required(false)
+                .value_parser(clap::value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("schedule")
+                .long("schedule")
+                .value_name("FILE")
+                .help("File of gate names in a topological order to evaluate the gates one at a time in, instead of level by level; implies --no-optimize")
+                .required(false)
+                .conflicts_with("work-stealing"),
+        )
+        .arg(
+            Arg::new("no-optimize")
+                .long("no-optimize")
+                .help("Evaluate the gates exactly as parsed, without any rewrite passes")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("state-trace")
+                .long("state-trace")
+                .value_name("FILE")
+                .help("CSV file to write the decrypted register state of every cycle, one column per register bus")
+                .required(false)
+                .value_parser(value_parser!(String)),
+        )
+        .arg(
+            Arg::new("emit-stats-csv")
+                .long("emit-stats-csv")
+                .value_name("FILE")
+                .help("Append a row with the netlist name, gate, level and bootstrap counts, and evaluation time to a CSV file")
+                .required(false),
+        )
+        .arg(
+            Arg::new("verify")
+                .long("verify")
+                .help("Check the decrypted outputs against a plaintext evaluation, and write a repro/ directory (netlist, inputs, mismatches, command) if they differ")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("batch-vectors"),
+        )
+        .arg(
+            Arg::new("evaluate-twice-check")
+                .long("evaluate-twice-check")
+                .help("Evaluate the encrypted inputs a second time and check that the decrypted outputs are identical, to catch nondeterminism")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["batch-vectors", "lazy-encryption", "stream-outputs"]),
+        )
+        .arg(
+            Arg::new("loose-width-compare")
+                .long("loose-width-compare")
+                .help("Let --verify, --evaluate-twice-check and --compare-backends match numbers of different widths, e.g. 5 as u8 and as u32")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("manifest")
+                .long("manifest")
+                .value_name("FILE")
+                .help("Write a JSON manifest of the run (netlist and input hashes, parameters, counts, time)")
+                .required(false)
+                .value_parser(value_parser!(String)),
+        )
         .arg(
             Arg::new("verbose")
                 .long("verbose")
@@ -267,6 +1124,81 @@ pub fn parse_args() -> ArgMatches {
                 .help("Turn verbose printing on")
                 .required(false)
                 .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("quiet")
+                .long("quiet")
+                .short('q')
+                .help("Suppress all non-error output")
+                .required(false)
+                .conflicts_with("verbose")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("activity")
+                .long("activity")
+                .help("Print per-wire and total toggle counts between cycles (or between the --batch-vectors vectors) and exit")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("coverage")
+                .long("coverage")
+                .help("Print the gates whose output never toggled between cycles (or across the --batch-vectors vectors), the toggle coverage, and exit")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("count-critical-bootstraps")
+                .long("count-critical-bootstraps")
+                .help("Print the total and critical-path bootstrap counts and exit")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dump-gate-truthtables")
+                .long("dump-gate-truthtables")
+                .help("Print the truth table of every boolean gate and exit")
+                .required(false)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("test-reset")
+                .long("test-reset")
+                .value_name("WIRE")
+                .help("Start the registers from random states, assert the active-high reset WIRE for one cycle in plaintext, report the registers that don't reach their initial value, and exit")
+                .required(false),
+        )
+        .arg(
+            Arg::new("fanout-report")
+                .long("fanout-report")
+                .value_name("NUMBER")
+                .help("Print the nets that drive at least NUMBER gates and exit")
+                .required(false)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("partition")
+                .long("partition")
+                .value_name("NUMBER")
+                .help("Split the circuit into NUMBER sub-circuits of about the same size with a minimal cut, write them next to the netlist, and exit")
+                .required(false)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("dump-cone")
+                .long("dump-cone")
+                .value_names(["WIRE", "FILE"])
+                .num_args(2)
+                .help("Write the logic cone of WIRE as a standalone netlist to FILE, with the wires it reads as module inputs, and exit")
+                .required(false),
+        )
+        .arg(
+            Arg::new("emit-cnf")
+                .long("emit-cnf")
+                .value_name("FILE")
+                .help("Write the combinational logic as DIMACS CNF to FILE and its variables to FILE.vars.csv, and exit")
+                .required(false),
         );
     #[cfg(feature = "gpu")]
     {
@@ -0,0 +1,167 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+};
+use termion::color;
+
+/// A record of a single run, written as JSON by `--manifest`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunManifest {
+    pub netlist: String,
+    pub netlist_hash: u64,
+    pub arithmetic: Option<String>,
+    pub parameter_set: String,
+    pub cycles: usize,
+    pub input_file: Option<String>,
+    pub input_file_hash: Option<u64>,
+    pub gates: usize,
+    pub bootstraps: usize,
+    pub wall_clock_seconds: f64,
+}
+
+/// 64-bit FNV-1a hash of `bytes`.
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// 64-bit FNV-1a hash of the contents of `file_name`.
+pub fn hash_file(file_name: &str) -> u64 {
+    let contents = fs::read(file_name).unwrap_or_else(|error| {
+        panic!(
+            "{}[!]{} Failed to read {}: {}.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            file_name,
+            error
+        )
+    });
+
+    fnv1a_hash(&contents)
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+fn json_hash(hash: u64) -> String {
+    json_string(&format!("fnv1a:{:016x}", hash))
+}
+
+fn json_option(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".to_owned())
+}
+
+impl RunManifest {
+    pub fn to_json(&self) -> String {
+        let fields = [
+            ("netlist", json_string(&self.netlist)),
+            ("netlist_hash", json_hash(self.netlist_hash)),
+            (
+                "arithmetic",
+                json_option(self.arithmetic.as_deref().map(json_string)),
+            ),
+            ("parameter_set", json_string(&self.parameter_set)),
+            ("cycles", self.cycles.to_string()),
+            (
+                "input_file",
+                json_option(self.input_file.as_deref().map(json_string)),
+            ),
+            (
+                "input_file_hash",
+                json_option(self.input_file_hash.map(json_hash)),
+            ),
+            ("gates", self.gates.to_string()),
+            ("bootstraps", self.bootstraps.to_string()),
+            (
+                "wall_clock_seconds",
+                format!("{:.6}", self.wall_clock_seconds),
+            ),
+        ];
+
+        let body = fields
+            .iter()
+            .map(|(key, value)| format!("  {}: {}", json_string(key), value))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        format!("{{\n{}\n}}\n", body)
+    }
+
+    pub fn write(&self, file_name: &str) {
+        fs::write(file_name, self.to_json()).unwrap_or_else(|error| {
+            panic!(
+                "{}[!]{} Failed to write manifest {}: {}.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                file_name,
+                error
+            )
+        });
+    }
+}
+
+/// One row of the `--emit-stats-csv` spreadsheet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunStats {
+    pub name: String,
+    pub gates: usize,
+    pub levels: usize,
+    pub bootstraps: usize,
+    pub eval_seconds: f64,
+}
+
+impl RunStats {
+    /// Append the row to the CSV file `file_name`, starting it with a header
+    /// if it is new or empty, so that several runs add up to one table.
+    pub fn append(&self, file_name: &str) {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_name)
+            .unwrap_or_else(|error| stats_error(file_name, error));
+        let is_empty = !matches!(file.metadata(), Ok(metadata) if metadata.len() > 0);
+
+        let mut writer = csv::Writer::from_writer(file);
+        if is_empty {
+            writer
+                .write_record(["name", "gates", "levels", "bootstraps", "eval_seconds"])
+                .unwrap_or_else(|error| stats_error(file_name, error));
+        }
+        writer
+            .write_record([
+                self.name.clone(),
+                self.gates.to_string(),
+                self.levels.to_string(),
+                self.bootstraps.to_string(),
+                format!("{:.6}", self.eval_seconds),
+            ])
+            .unwrap_or_else(|error| stats_error(file_name, error));
+        writer
+            .flush()
+            .unwrap_or_else(|error| stats_error(file_name, error));
+    }
+}
+
+fn stats_error(file_name: &str, error: impl std::fmt::Display) -> ! {
+    panic!(
+        "{}[!]{} Failed to write stats {}: {}.",
+        color::Fg(color::LightRed),
+        color::Fg(color::Reset),
+        file_name,
+        error
+    )
+}
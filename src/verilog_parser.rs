@@ -1,11 +1,14 @@
 use csv::Reader;
-use std::collections::{HashMap, HashSet};
+use itertools::Itertools;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::io::{BufWriter, Write};
 use termion::color;
+use thiserror::Error;
 
-use crate::gates::{Gate, GateType};
-use crate::{hex_to_bitstring, parse_input_wire, PtxtType};
+use crate::circuit::{Cnf, CrossWire, Mismatch, PatchEdit};
+use crate::gates::{muxn_layout, Gate, GateType};
+use crate::{hex_to_bitstring, parse_input_wire, PtxtType, U256};
 
 fn extract_const_val(input_str: &str) -> &str {
     let start_index = input_str.find('(').expect("Opening parenthesis not found");
@@ -28,30 +31,281 @@ fn usize_to_bitvec(value: usize, lut_size: usize) -> Vec<u64> {
     bits
 }
 
-fn parse_gate(tokens: &[&str]) -> Gate {
-    let gate_type = match tokens[0] {
-        "and" => GateType::And,
-        "lut" => GateType::Lut,
-        "dff" => GateType::Dff,
-        "mux" => GateType::Mux,
-        "nand" => GateType::Nand,
-        "nor" => GateType::Nor,
-        "not" => GateType::Not,
-        "or" => GateType::Or,
-        "xnor" => GateType::Xnor,
-        "xor" => GateType::Xor,
-        "buf" => GateType::Buf,
-        "czero" => GateType::ConstZero,
-        "cone" => GateType::ConstOne,
-        "add" => GateType::Add,
-        "mult" => GateType::Mult,
-        "div" => GateType::Div,
-        "sub" => GateType::Sub,
-        "shl" => GateType::Shl,
-        "shr" => GateType::Shr,
-        "copy" => GateType::Copy,
-        _ => panic!("Invalid gate type \"{}\"", tokens[0]),
+/// A cell of the netlist format: its keyword, the gate it is parsed into
+/// (None for cells lowered into several gates), its pins and what it does.
+pub struct GateCell {
+    pub keyword: &'static str,
+    pub gate_type: Option<GateType>,
+    pub pins: &'static str,
+    pub function: &'static str,
+}
+
+/// Every cell the parser recognizes, listed by `--list-gate-types`.
+pub const GATE_CELLS: &[GateCell] = &[
+    GateCell {
+        keyword: "and",
+        gate_type: Some(GateType::And),
+        pins: "(a, b, y)",
+        function: "y = a & b",
+    },
+    GateCell {
+        keyword: "nand",
+        gate_type: Some(GateType::Nand),
+        pins: "(a, b, y)",
+        function: "y = !(a & b)",
+    },
+    GateCell {
+        keyword: "or",
+        gate_type: Some(GateType::Or),
+        pins: "(a, b, y)",
+        function: "y = a | b",
+    },
+    GateCell {
+        keyword: "nor",
+        gate_type: Some(GateType::Nor),
+        pins: "(a, b, y)",
+        function: "y = !(a | b)",
+    },
+    GateCell {
+        keyword: "xor",
+        gate_type: Some(GateType::Xor),
+        pins: "(a, b, y)",
+        function: "y = a ^ b",
+    },
+    GateCell {
+        keyword: "xnor",
+        gate_type: Some(GateType::Xnor),
+        pins: "(a, b, y)",
+        function: "y = !(a ^ b)",
+    },
+    GateCell {
+        keyword: "not",
+        gate_type: Some(GateType::Not),
+        pins: "(a, y)",
+        function: "y = !a",
+    },
+    GateCell {
+        keyword: "buf",
+        gate_type: Some(GateType::Buf),
+        pins: "(a, y)",
+        function: "y = a",
+    },
+    GateCell {
+        keyword: "mux",
+        gate_type: Some(GateType::Mux),
+        pins: "(a, b, s, y)",
+        function: "y = s ? a : b",
+    },
+    GateCell {
+        keyword: "muxn",
+        gate_type: Some(GateType::MuxN),
+        pins: "(N, s0..sK, d0..dN-1, y) or (N, s, d0..dN-1, y)",
+        function: "y = d[s], with the select bits LSB first or an integer index",
+    },
+    GateCell {
+        keyword: "lut",
+        gate_type: Some(GateType::Lut),
+        pins: "(0xTABLE, a0..aK, y)",
+        function: "y = bit (a0..aK) of TABLE, with a0 the most significant",
+    },
+    GateCell {
+        keyword: "dff",
+        gate_type: Some(GateType::Dff),
+        pins: "(d, q)",
+        function: "q = d at the next cycle",
+    },
+    GateCell {
+        keyword: "cone",
+        gate_type: Some(GateType::ConstOne),
+        pins: "(y)",
+        function: "y = 1",
+    },
+    GateCell {
+        keyword: "czero",
+        gate_type: Some(GateType::ConstZero),
+        pins: "(y)",
+        function: "y = 0",
+    },
+    GateCell {
+        keyword: "cx",
+        gate_type: Some(GateType::ConstUnknown),
+        pins: "(y)",
+        function: "y = x, unknown in plaintext",
+    },
+    GateCell {
+        keyword: "add",
+        gate_type: Some(GateType::Add),
+        pins: "(a, b, y)",
+        function: "y = a + b (arithmetic)",
+    },
+    GateCell {
+        keyword: "sub",
+        gate_type: Some(GateType::Sub),
+        pins: "(a, b, y)",
+        function: "y = a - b (arithmetic)",
+    },
+    GateCell {
+        keyword: "mult",
+        gate_type: Some(GateType::Mult),
+        pins: "(a, b, y)",
+        function: "y = a * b (arithmetic)",
+    },
+    GateCell {
+        keyword: "div",
+        gate_type: Some(GateType::Div),
+        pins: "(a, b, y)",
+        function: "y = a / b (arithmetic)",
+    },
+    GateCell {
+        keyword: "shl",
+        gate_type: Some(GateType::Shl),
+        pins: "(a, b, y)",
+        function: "y = a << b (arithmetic)",
+    },
+    GateCell {
+        keyword: "shr",
+        gate_type: Some(GateType::Shr),
+        pins: "(a, b, y)",
+        function: "y = a >> b (arithmetic)",
+    },
+    GateCell {
+        keyword: "copy",
+        gate_type: Some(GateType::Copy),
+        pins: "(a, y)",
+        function: "y = a (arithmetic)",
+    },
+    GateCell {
+        keyword: "acc",
+        gate_type: None,
+        pins: "(d, [en,] q)",
+        function: "q += d every cycle, or only when en is 1, wrapping at the arithmetic width (arithmetic)",
+    },
+    GateCell {
+        keyword: "penc",
+        gate_type: Some(GateType::PriorityEncoder),
+        pins: "(N, r0..rN-1, g0..gN-1) or (N, r0..rN-1, y)",
+        function: "one-hot grant of the highest asserted r, or its index (arithmetic)",
+    },
+];
+
+fn parse_gate_type(keyword: &str) -> Option<GateType> {
+    GATE_CELLS
+        .iter()
+        .find(|cell| cell.keyword == keyword)
+        .and_then(|cell| cell.gate_type.clone())
+}
+
+/// Lower an `acc ID(D, Q);` or `acc ID(D, EN, Q);` cell, a register that
+/// adds D to its value every cycle, or only when the low bit of EN is 1. The
+/// sum isn't widened: it wraps at the arithmetic width, so pick one that fits
+/// the largest total.
+fn parse_accumulator(tokens: &[&str]) -> Vec<Gate> {
+    let (name, wires) = cell_wires(tokens);
+    match &wires[..] {
+        [data, output] => accumulator_gates(&name, data, None, output),
+        [data, enable, output] => accumulator_gates(&name, data, Some(enable), output),
+        _ => unreachable!("lint_cell checks the pins of {}", name),
+    }
+}
+
+/// Split a cell's tokens into its instance name and its pins.
+fn cell_wires(tokens: &[&str]) -> (String, Vec<String>) {
+    let cell = tokens[1..].join(" ");
+    let (name, wires) = cell.split_once('(').unwrap_or((cell.as_str(), ""));
+    let wires = wires
+        .trim_end_matches(';')
+        .trim_end_matches(')')
+        .split_whitespace()
+        .map(|wire| wire.to_owned())
+        .collect();
+
+    (name.trim().to_owned(), wires)
+}
+
+/// Parse a `penc ID(N, R0, .., RN-1, Y..);` cell, a priority encoder over N
+/// requests where the highest asserted index wins. In arithmetic mode, Y is
+/// the index of the granted request (the last one with its low bit set),
+/// and is 0 when no request is asserted. In boolean mode, N outputs get the
+/// one-hot grant, and a single output the grant of R0 alone. `lint_cell`
+/// has checked the pins.
+fn parse_priority_encoder(tokens: &[&str]) -> Vec<Gate> {
+    let (name, wires) = cell_wires(tokens);
+    let width = wires[0].parse::<usize>().unwrap();
+    let requests = &wires[1..=width];
+    let outputs = &wires[width + 1..];
+    let encoder = |gate_name: String, requests: &[String], output: &String| {
+        Gate::new(
+            gate_name,
+            GateType::PriorityEncoder,
+            requests.to_vec(),
+            None,
+            output.clone(),
+            0,
+        )
+    };
+    match outputs {
+        [output] => vec![encoder(name, requests, output)],
+        _ => {
+            // A request is granted when it is asserted and none above it
+            // is, and the last one only needs to be asserted.
+            let last = Gate::new(
+                format!("{}_g{}", name, width - 1),
+                GateType::Buf,
+                vec![requests[width - 1].clone()],
+                None,
+                outputs[width - 1].clone(),
+                0,
+            );
+            (0..width - 1)
+                .map(|i| encoder(format!("{}_g{}", name, i), &requests[i..], &outputs[i]))
+                .chain([last])
+                .collect()
+        }
+    }
+}
+
+fn accumulator_gates(name: &str, data: &str, enable: Option<&String>, output: &str) -> Vec<Gate> {
+    let sum = format!("{}_sum", name);
+    let mut gates = vec![Gate::new(
+        format!("{}_add", name),
+        GateType::Add,
+        vec![output.to_owned(), data.to_owned()],
+        None,
+        sum.clone(),
+        0,
+    )];
+    // The enable is the integer index of a MUXN that keeps the value or
+    // takes the sum, which is cheaper than multiplying D by it.
+    let next = match enable {
+        Some(enable) => {
+            let next = format!("{}_next", name);
+            gates.push(Gate::new(
+                format!("{}_enable", name),
+                GateType::MuxN,
+                vec![enable.clone(), output.to_owned(), sum],
+                None,
+                next.clone(),
+                0,
+            ));
+            next
+        }
+        None => sum,
     };
+    gates.push(Gate::new(
+        name.to_owned(),
+        GateType::Dff,
+        vec![next],
+        None,
+        output.to_owned(),
+        0,
+    ));
+
+    gates
+}
+
+fn parse_gate(tokens: &[&str]) -> Gate {
+    let gate_type =
+        parse_gate_type(tokens[0]).unwrap_or_else(|| panic!("Invalid gate type \"{}\"", tokens[0]));
 
     let name_and_inputs = tokens[1]
         .split(|c| c == '(' || c == ',')
@@ -64,7 +318,7 @@ fn parse_gate(tokens: &[&str]) -> Gate {
             vec![String::from(name_and_inputs[1].trim())],
             String::from(tokens[2].trim_end_matches(';').trim_end_matches(')')),
         ),
-        GateType::Mux | GateType::Lut => {
+        GateType::Mux | GateType::MuxN | GateType::Lut => {
             let mut input_wires = vec![String::from(name_and_inputs[1])];
             for token in tokens.iter().take(tokens.len() - 1).skip(2) {
                 input_wires.push(token.trim_end_matches(',').trim().to_owned());
@@ -76,7 +330,7 @@ fn parse_gate(tokens: &[&str]) -> Gate {
             );
             (input_wires, output_wire)
         }
-        GateType::ConstOne | GateType::ConstZero => {
+        GateType::ConstOne | GateType::ConstZero | GateType::ConstUnknown => {
             let output_wire = String::from(extract_const_val(tokens[1]));
             (vec![], output_wire)
         }
@@ -93,6 +347,12 @@ fn parse_gate(tokens: &[&str]) -> Gate {
         }
     };
 
+    if gate_type == GateType::MuxN {
+        // The first argument is the number of data inputs, which lint_cell
+        // checks against the select and data pins.
+        input_wires.remove(0);
+    }
+
     let lut_const = if gate_type == GateType::Lut {
         let lut_const_str = input_wires.remove(0);
         let lut_const_int = if lut_const_str.starts_with("0x") {
@@ -119,6 +379,743 @@ fn parse_gate(tokens: &[&str]) -> Gate {
     Gate::new(gate_name, gate_type, input_wires, lut_const, output_wire, 0)
 }
 
+/// Split the leading `(* key = value, ... *)` attribute instances off a line,
+/// returning the parsed attributes and the rest of the line.
+fn split_attributes(line: &str) -> (Vec<(String, String)>, &str) {
+    let mut attributes = Vec::new();
+    let mut rest = line.trim_start();
+    while let Some(body) = rest.strip_prefix("(*") {
+        let Some(end) = body.find("*)") else {
+            break;
+        };
+        for attribute in body[..end].split(',') {
+            // An attribute without a value defaults to 1.
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim().trim_matches('"')),
+                None => (attribute.trim(), "1"),
+            };
+            if !key.is_empty() {
+                attributes.push((key.to_owned(), value.to_owned()));
+            }
+        }
+        rest = body[end + 2..].trim_start();
+    }
+
+    (attributes, rest)
+}
+
+/// Read a text file, dropping a leading UTF-8 BOM and normalizing CRLF line
+/// endings to LF, as in files exported from Windows tools.
+fn read_text_file(file_name: &str, error_message: &str) -> String {
+    let contents = std::fs::read_to_string(file_name).expect(error_message);
+    let contents = contents.strip_prefix('\u{feff}').unwrap_or(&contents);
+
+    contents.replace("\r\n", "\n")
+}
+
+/// Remove `/* ... */` and `// ...` comments from Verilog source. Newlines
+/// inside block comments are kept so each statement stays on its own line.
+fn strip_comments(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find('/') {
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("/*") {
+            let end = rest.find("*/").map_or(rest.len(), |end| end + 2);
+            stripped.extend(rest[..end].chars().filter(|&c| c == '\n'));
+            rest = &rest[end..];
+        } else if rest.starts_with("//") {
+            rest = &rest[rest.find('\n').unwrap_or(rest.len())..];
+        } else {
+            stripped.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    stripped.push_str(rest);
+
+    stripped
+}
+
+/// Tokens of a combinational always block: punctuation on its own, anything
+/// else (wires with their bit selects, literals, keywords) as words.
+#[derive(Clone, Copy)]
+struct AlwaysLexer<'s> {
+    source: &'s str,
+    pos: usize,
+}
+
+impl<'s> AlwaysLexer<'s> {
+    fn next_token(&mut self) -> Option<&'s str> {
+        let rest = &self.source[self.pos..];
+        let start = self.pos + (rest.len() - rest.trim_start().len());
+        let mut chars = self.source[start..].char_indices();
+        let (_, first) = chars.next()?;
+        let mut end = start + first.len_utf8();
+        if !"()@*;=<?:".contains(first) {
+            let mut in_brackets = first == '[';
+            for (offset, c) in chars {
+                if !in_brackets && (c.is_whitespace() || "()@*;=<?:".contains(c)) {
+                    break;
+                }
+                in_brackets = (in_brackets || c == '[') && c != ']';
+                end = start + offset + c.len_utf8();
+            }
+        }
+        self.pos = end;
+
+        Some(&self.source[start..end])
+    }
+
+    fn peek_token(&self) -> Option<&'s str> {
+        let mut lexer = *self;
+        lexer.next_token()
+    }
+
+    fn expect(&mut self, expected: &str) {
+        let token = self.next_token();
+        if token != Some(expected) {
+            panic!(
+                "Expected \"{}\" in always block, found \"{}\"",
+                expected,
+                token.unwrap_or("end of file")
+            );
+        }
+    }
+}
+
+/// Values of the variables assigned so far in an always block, in the order
+/// they were first assigned.
+#[derive(Clone, Default)]
+struct AlwaysEnv {
+    order: Vec<String>,
+    values: HashMap<String, String>,
+}
+
+impl AlwaysEnv {
+    fn assign(&mut self, wire: &str, value: String) {
+        if !self.values.contains_key(wire) {
+            self.order.push(wire.to_owned());
+        }
+        self.values.insert(wire.to_owned(), value);
+    }
+
+    // Blocking assignments: later statements read the new values.
+    fn substitute(&self, tokens: &[&str]) -> String {
+        tokens
+            .iter()
+            .map(|token| match self.values.get(*token) {
+                Some(value) => format!("({})", value),
+                None => token.to_string(),
+            })
+            .join(" ")
+    }
+}
+
+/// Parse one statement of an always block: `begin ... end`,
+/// `if (cond) ... [else ...]`, or a blocking assignment `lhs = expr;`.
+fn parse_always_statement(lexer: &mut AlwaysLexer, env: &mut AlwaysEnv) {
+    match lexer.next_token() {
+        Some("begin") => {
+            while lexer.peek_token() != Some("end") {
+                if lexer.peek_token().is_none() {
+                    panic!("Missing \"end\" in always block");
+                }
+                parse_always_statement(lexer, env);
+            }
+            lexer.expect("end");
+        }
+        Some("if") => {
+            lexer.expect("(");
+            let mut condition = Vec::new();
+            let mut depth = 1;
+            loop {
+                let token = lexer
+                    .next_token()
+                    .unwrap_or_else(|| panic!("Missing \")\" in always block condition"));
+                match token {
+                    "(" => depth += 1,
+                    ")" => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => {}
+                }
+                condition.push(token);
+            }
+            let condition = env.substitute(&condition);
+
+            let mut if_true = env.clone();
+            parse_always_statement(lexer, &mut if_true);
+            let mut if_false = env.clone();
+            if lexer.peek_token() == Some("else") {
+                lexer.next_token();
+                parse_always_statement(lexer, &mut if_false);
+            }
+
+            let assigned = if_true
+                .order
+                .iter()
+                .chain(if_false.order.iter())
+                .unique()
+                .cloned()
+                .collect::<Vec<_>>();
+            for wire in assigned {
+                match (if_true.values.get(&wire), if_false.values.get(&wire)) {
+                    (Some(value_true), Some(value_false)) if value_true == value_false => {
+                        env.assign(&wire, value_true.to_owned());
+                    }
+                    (Some(value_true), Some(value_false)) => {
+                        let value =
+                            format!("({}) ? ({}) : ({})", condition, value_true, value_false);
+                        env.assign(&wire, value);
+                    }
+                    _ => panic!(
+                        "\"{}\" is not assigned on every path of the always block (a latch)",
+                        wire
+                    ),
+                }
+            }
+        }
+        Some(lhs) if !"()@*;=<?:".contains(lhs) && lhs != "else" && lhs != "end" => {
+            match lexer.next_token() {
+                Some("=") => {}
+                Some("<") => panic!("Non-blocking assignments are not supported in always @(*)"),
+                _ => panic!("Invalid statement \"{}\" in always block", lhs),
+            }
+            let mut rhs = Vec::new();
+            loop {
+                match lexer.next_token() {
+                    Some(";") => break,
+                    Some(token) => rhs.push(token),
+                    None => panic!("Missing \";\" after \"{}\" in always block", lhs),
+                }
+            }
+            let value = env.substitute(&rhs);
+            env.assign(lhs, value);
+        }
+        token => panic!(
+            "Invalid statement \"{}\" in always block",
+            token.unwrap_or("end of file")
+        ),
+    }
+}
+
+/// Rewrite the combinational always blocks (`always @(*)`, `always @*` and
+/// `always_comb`) into one assign per assigned wire, with if/else lowered to
+/// ternaries. The assigns take the place of the block, keeping the line
+/// numbers of what follows.
+fn lower_always_blocks(source: &str) -> String {
+    let mut lowered = String::with_capacity(source.len());
+    let mut pos = 0;
+    while pos < source.len() {
+        let line_end = source[pos..]
+            .find('\n')
+            .map_or(source.len(), |end| pos + end + 1);
+        let line = &source[pos..line_end];
+        let trimmed = line.trim_start();
+        let keyword = trimmed
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .next();
+        if keyword != Some("always") && keyword != Some("always_comb") {
+            lowered.push_str(line);
+            pos = line_end;
+            continue;
+        }
+
+        let mut lexer = AlwaysLexer {
+            source,
+            pos: pos + (line.len() - trimmed.len()),
+        };
+        if lexer.next_token() == Some("always") {
+            lexer.expect("@");
+            if lexer.peek_token() == Some("(") {
+                lexer.next_token();
+                if lexer.next_token() != Some("*") {
+                    panic!("Only combinational always blocks (always @(*)) are supported");
+                }
+                lexer.expect(")");
+            } else {
+                lexer.expect("*");
+            }
+        }
+        let mut env = AlwaysEnv::default();
+        parse_always_statement(&mut lexer, &mut env);
+
+        let assigns = env
+            .order
+            .iter()
+            .map(|wire| format!("assign {} = {};", wire, env.values[wire]))
+            .collect::<Vec<_>>();
+        let newlines = source[pos..lexer.pos].matches('\n').count();
+        lowered.push_str(&assigns.join("\n"));
+        for _ in assigns.len().saturating_sub(1)..newlines {
+            lowered.push('\n');
+        }
+        pos = lexer.pos;
+    }
+
+    lowered
+}
+
+/// Read the attributes attached to the top module, e.g.
+/// `(* helm_arith = "u32" *) module top(...);`.
+pub fn read_module_attributes(file_name: &str) -> HashMap<String, String> {
+    let source = strip_comments(&read_text_file(file_name, "Failed to open file"));
+    module_attributes(&source)
+}
+
+fn module_attributes(source: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    for line in source.lines() {
+        let (line_attributes, rest) = split_attributes(line.trim());
+        attributes.extend(line_attributes);
+        if rest.starts_with("module") {
+            break;
+        }
+    }
+
+    attributes
+}
+
+/// Width of every input, output and wire declared with a range, e.g. 8 for
+/// `input [7:0] a;`.
+pub fn read_wire_widths(file_name: &str) -> HashMap<String, usize> {
+    let source = strip_comments(&read_text_file(file_name, "Failed to open file"));
+
+    let mut widths = HashMap::new();
+    for line in source.lines() {
+        let (_, line) = split_attributes(line.trim());
+        let (declaration, _) = split_declaration_init(line);
+        let tokens = declaration
+            .split([',', ' '].as_ref())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        if !matches!(tokens.first(), Some(&"input" | &"output" | &"wire")) {
+            continue;
+        }
+        if let Some((start, end)) = tokens.get(1).and_then(|range| parse_range(range)) {
+            for token in &tokens[2..] {
+                let name = token.trim_end_matches(';');
+                widths.insert(name.to_owned(), end - start + 1);
+            }
+        }
+    }
+
+    widths
+}
+
+/// Name of the first module declared in `file_name`.
+pub fn read_module_name(file_name: &str) -> Option<String> {
+    let source = strip_comments(&read_text_file(file_name, "Failed to open file"));
+
+    source.lines().find_map(|line| {
+        let (_, line) = split_attributes(line.trim());
+        let header = line
+            .strip_prefix("module")
+            .filter(|header| header.starts_with(char::is_whitespace))?;
+        let name = header.split(['(', ';']).next()?.trim();
+        (!name.is_empty()).then(|| name.to_owned())
+    })
+}
+
+/// Right-hand side of an `assign`, e.g. `s ? a : (t ? b : c)`.
+enum AssignExpr {
+    Wire(String),
+    // Bits of a sized literal, least significant first; None if unknown.
+    Literal(Vec<Option<bool>>),
+    Ternary(Box<AssignExpr>, Box<AssignExpr>, Box<AssignExpr>),
+}
+
+fn tokenize_assign_expr(expr: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_brackets = false;
+    for c in expr.chars() {
+        match c {
+            '[' | ']' => {
+                in_brackets = c == '[';
+                token.push(c);
+            }
+            // Colons inside brackets are part selects, not ternaries.
+            '?' | ':' | '(' | ')' if !in_brackets => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => {
+                if !in_brackets && !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            _ => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+/// Parse a sized literal such as `8'hA5`, `4'b1010` or `3'o7` into its bits,
+/// least significant first. Returns None if `token` is not a literal.
+fn parse_sized_literal(token: &str) -> Option<Vec<bool>> {
+    let (width, value) = token.split_once('\'')?;
+    let width = width
+        .parse::<usize>()
+        .unwrap_or_else(|_| panic!("Invalid literal width in \"{}\"", token));
+    let mut chars = value.chars();
+    let radix = match chars.next().map(|c| c.to_ascii_lowercase()) {
+        Some('b') => 2,
+        Some('o') => 8,
+        Some('d') => 10,
+        Some('h') => 16,
+        _ => panic!("Invalid literal base in \"{}\"", token),
+    };
+    let digits = chars.as_str().replace('_', "");
+    let value = u128::from_str_radix(&digits, radix)
+        .unwrap_or_else(|_| panic!("Invalid literal value in \"{}\"", token));
+    if width > 128 {
+        panic!("Literal \"{}\" is wider than 128 bits", token);
+    }
+    if width == 0 || (width < 128 && value >> width != 0) {
+        panic!("Literal \"{}\" does not fit in {} bits", token, width);
+    }
+
+    Some((0..width).map(|bit| (value >> bit) & 1 == 1).collect())
+}
+
+/// Like `parse_sized_literal`, but binary, octal and hex literals may also
+/// have `x`/`z` digits (e.g. `1'bx`, `4'b10xz`, `8'hz`), whose bits are None.
+/// A literal whose top digit is unknown extends with unknown bits.
+fn parse_literal_bits(token: &str) -> Option<Vec<Option<bool>>> {
+    let (width, value) = token.split_once('\'')?;
+    let digits = value.get(1..).unwrap_or("");
+    if !digits.contains(|c: char| matches!(c.to_ascii_lowercase(), 'x' | 'z' | '?')) {
+        return parse_sized_literal(token).map(|bits| bits.into_iter().map(Some).collect());
+    }
+
+    let width = width
+        .parse::<usize>()
+        .unwrap_or_else(|_| panic!("Invalid literal width in \"{}\"", token));
+    let digit_bits = match value.chars().next().map(|c| c.to_ascii_lowercase()) {
+        Some('b') => 1,
+        Some('o') => 3,
+        Some('h') => 4,
+        _ => panic!(
+            "Unknown bits need a binary, octal or hex literal, found \"{}\"",
+            token
+        ),
+    };
+    let mut bits = Vec::new();
+    for digit in digits.chars().rev().filter(|&c| c != '_') {
+        match digit.to_ascii_lowercase() {
+            'x' | 'z' | '?' => bits.extend(std::iter::repeat(None).take(digit_bits)),
+            c => {
+                let value = c
+                    .to_digit(1 << digit_bits)
+                    .unwrap_or_else(|| panic!("Invalid literal value in \"{}\"", token));
+                bits.extend((0..digit_bits).map(|bit| Some((value >> bit) & 1 == 1)));
+            }
+        }
+    }
+    let fill = if bits.last() == Some(&None) {
+        None
+    } else {
+        Some(false)
+    };
+    bits.resize(width, fill);
+
+    Some(bits)
+}
+
+fn parse_assign_expr(tokens: &[String], pos: &mut usize) -> AssignExpr {
+    let condition = match tokens.get(*pos).map(String::as_str) {
+        Some("(") => {
+            *pos += 1;
+            let inner = parse_assign_expr(tokens, pos);
+            if tokens.get(*pos).map(String::as_str) != Some(")") {
+                panic!(
+                    "Missing closing parenthesis in assign \"{}\"",
+                    tokens.join(" ")
+                );
+            }
+            *pos += 1;
+            inner
+        }
+        Some("?") | Some(":") | Some(")") | None => {
+            panic!("Invalid assign expression \"{}\"", tokens.join(" "))
+        }
+        Some(wire) => {
+            *pos += 1;
+            match parse_literal_bits(wire) {
+                Some(bits) => AssignExpr::Literal(bits),
+                None => AssignExpr::Wire(wire.to_owned()),
+            }
+        }
+    };
+
+    // Ternaries are right associative: a ? b : c ? d : e = a ? b : (c ? d : e)
+    if tokens.get(*pos).map(String::as_str) == Some("?") {
+        *pos += 1;
+        let if_true = parse_assign_expr(tokens, pos);
+        if tokens.get(*pos).map(String::as_str) != Some(":") {
+            panic!("Missing ':' in assign \"{}\"", tokens.join(" "));
+        }
+        *pos += 1;
+        let if_false = parse_assign_expr(tokens, pos);
+        AssignExpr::Ternary(Box::new(condition), Box::new(if_true), Box::new(if_false))
+    } else {
+        condition
+    }
+}
+
+/// Name of bit `bit` of `wire`, or `wire` itself if it is not a bus.
+fn bit_wire(
+    wire: &str,
+    bit: Option<usize>,
+    bus_ranges: &HashMap<String, (usize, usize)>,
+) -> String {
+    match (bit, bus_ranges.get(wire)) {
+        (Some(bit), Some((start, _))) => format!("{}[{}]", wire, start + bit),
+        _ => wire.to_owned(),
+    }
+}
+
+/// Lower an assign expression into mux, buffer and constant gates, returning
+/// the wire that holds its value. If `output` is given, the result is driven
+/// onto it.
+fn lower_assign_expr(
+    expr: &AssignExpr,
+    bit: Option<usize>,
+    output: Option<String>,
+    bus_ranges: &HashMap<String, (usize, usize)>,
+    gates: &mut Vec<Gate>,
+    assign_count: &mut usize,
+) -> String {
+    match expr {
+        AssignExpr::Wire(wire) => {
+            let wire = bit_wire(wire, bit, bus_ranges);
+            if let Some(output) = output {
+                gates.push(Gate::new(
+                    format!("assign_buf{}", assign_count),
+                    GateType::Buf,
+                    vec![wire],
+                    None,
+                    output.clone(),
+                    0,
+                ));
+                *assign_count += 1;
+                output
+            } else {
+                wire
+            }
+        }
+        AssignExpr::Literal(bits) => {
+            // Bits past the width of the literal are zero.
+            let value = bits.get(bit.unwrap_or(0)).copied().unwrap_or(Some(false));
+            let gate_name = format!("assign_const{}", assign_count);
+            *assign_count += 1;
+            let output = output.unwrap_or_else(|| format!("{}_out", gate_name));
+            gates.push(Gate::new(
+                gate_name,
+                match value {
+                    Some(true) => GateType::ConstOne,
+                    Some(false) => GateType::ConstZero,
+                    None => GateType::ConstUnknown,
+                },
+                vec![],
+                None,
+                output.clone(),
+                0,
+            ));
+            output
+        }
+        AssignExpr::Ternary(condition, if_true, if_false) => {
+            if let AssignExpr::Wire(wire) = condition.as_ref() {
+                if bus_ranges.contains_key(wire) {
+                    panic!("Ternary condition \"{}\" must be a single bit", wire);
+                }
+            }
+            let select = lower_assign_expr(condition, None, None, bus_ranges, gates, assign_count);
+            let in_0 = lower_assign_expr(if_true, bit, None, bus_ranges, gates, assign_count);
+            let in_1 = lower_assign_expr(if_false, bit, None, bus_ranges, gates, assign_count);
+
+            let gate_name = format!("assign_mux{}", assign_count);
+            *assign_count += 1;
+            let output = output.unwrap_or_else(|| format!("{}_out", gate_name));
+            gates.push(Gate::new(
+                gate_name,
+                GateType::Mux,
+                vec![in_0, in_1, select],
+                None,
+                output.clone(),
+                0,
+            ));
+            output
+        }
+    }
+}
+
+/// Parse `assign lhs = rhs;`. Ternaries become muxes (one per bit for buses),
+/// sized literals become constant gates and plain assigns become buffers, or
+/// copies in arithmetic mode.
+fn parse_assign(
+    line: &str,
+    bus_ranges: &HashMap<String, (usize, usize)>,
+    is_arith: bool,
+    assign_count: &mut usize,
+) -> Vec<Gate> {
+    let statement = line
+        .trim_start_matches("assign")
+        .trim()
+        .trim_end_matches(';');
+    let (lhs, rhs) = statement
+        .split_once('=')
+        .unwrap_or_else(|| panic!("Invalid assign \"{}\"", line));
+    let lhs = lhs.trim();
+
+    let tokens = tokenize_assign_expr(rhs);
+    let mut pos = 0;
+    let expr = parse_assign_expr(&tokens, &mut pos);
+    if pos != tokens.len() {
+        panic!("Invalid assign expression \"{}\"", rhs.trim());
+    }
+
+    let mut gates = Vec::new();
+    if is_arith {
+        match expr {
+            AssignExpr::Wire(wire) => {
+                gates.push(Gate::new(
+                    format!("assign_copy{}", assign_count),
+                    GateType::Copy,
+                    vec![wire],
+                    None,
+                    lhs.to_owned(),
+                    0,
+                ));
+                *assign_count += 1;
+            }
+            AssignExpr::Literal(..) => {
+                panic!("Literal assigns are only supported in boolean mode")
+            }
+            AssignExpr::Ternary(..) => {
+                panic!("Ternary assigns are only supported in boolean mode")
+            }
+        }
+    } else if let Some((start, end)) = bus_ranges.get(lhs) {
+        for bit in 0..=(end - start) {
+            let output = bit_wire(lhs, Some(bit), bus_ranges);
+            lower_assign_expr(
+                &expr,
+                Some(bit),
+                Some(output),
+                bus_ranges,
+                &mut gates,
+                assign_count,
+            );
+        }
+    } else {
+        lower_assign_expr(
+            &expr,
+            None,
+            Some(lhs.to_owned()),
+            bus_ranges,
+            &mut gates,
+            assign_count,
+        );
+    }
+
+    gates
+}
+
+/// Port names of a `module name(port0, port1, ...);` header, in order. The
+/// header may span several lines joined into `line`.
+fn parse_module_ports(line: &str) -> Vec<String> {
+    match (line.find('('), line.rfind(')')) {
+        (Some(start), Some(end)) if start < end => line[start + 1..end]
+            .split(',')
+            .map(|port| port.trim().to_owned())
+            .filter(|port| !port.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Split the initial value off a register declaration and drop the `reg`
+/// keyword, e.g. `output reg [7:0] count = 8'h05;` gives
+/// (`output [7:0] count;`, Some(`8'h05`)). A plain `reg` is declared as a
+/// wire. Other lines are returned unchanged.
+fn split_declaration_init(line: &str) -> (String, Option<String>) {
+    let keyword = line.split_whitespace().next().unwrap_or("");
+    if !matches!(keyword, "output" | "reg") {
+        return (line.to_owned(), None);
+    }
+
+    let (declaration, init) = match line.trim_end_matches(';').split_once('=') {
+        Some((declaration, init)) => (declaration, Some(init.trim().to_owned())),
+        None => (line.trim_end_matches(';'), None),
+    };
+    let mut tokens = declaration
+        .split_whitespace()
+        .filter(|token| *token != "reg")
+        .collect::<Vec<_>>();
+    if keyword == "reg" {
+        tokens.insert(0, "wire");
+    }
+
+    (format!("{};", tokens.join(" ")), init)
+}
+
+/// Read the initial values of the registers declared with one, e.g.
+/// `output reg [7:0] count = 8'h05;`, one entry per bit.
+pub fn read_register_init_values(file_name: &str) -> HashMap<String, PtxtType> {
+    let source = strip_comments(&read_text_file(file_name, "Failed to open file"));
+
+    let mut init_values = HashMap::new();
+    for line in source.lines() {
+        let (_, line) = split_attributes(line.trim());
+        let (declaration, Some(init)) = split_declaration_init(line) else {
+            continue;
+        };
+        let bits = parse_sized_literal(&init).unwrap_or_else(|| match init.parse::<u128>() {
+            Ok(value) => (0..128).map(|bit| (value >> bit) & 1 == 1).collect(),
+            Err(_) => panic!("Invalid initial value \"{}\"", init),
+        });
+
+        let tokens = declaration
+            .trim_end_matches(';')
+            .split([',', ' '].as_ref())
+            .filter(|s| !s.is_empty())
+            .skip(1)
+            .collect::<Vec<_>>();
+        let (range, names) = match tokens.first().and_then(|token| parse_range(token)) {
+            Some(range) => (Some(range), &tokens[1..]),
+            None => (None, &tokens[..]),
+        };
+        for name in names {
+            match range {
+                Some((start, end)) => {
+                    for bit in 0..=(end - start) {
+                        let value = bits.get(bit).copied().unwrap_or(false);
+                        init_values
+                            .insert(format!("{}[{}]", name, start + bit), PtxtType::Bool(value));
+                    }
+                }
+                None => {
+                    init_values.insert(name.to_string(), PtxtType::Bool(bits[0]));
+                }
+            }
+        }
+    }
+
+    init_values
+}
+
 fn parse_range(range_str: &str) -> Option<(usize, usize)> {
     let trimmed = range_str.trim_matches(|c| c == '[' || c == ']');
     if let Some(tokens) = trimmed.split(':').collect::<Vec<_>>().get(..) {
@@ -135,6 +1132,337 @@ fn parse_range(range_str: &str) -> Option<(usize, usize)> {
     None
 }
 
+#[derive(Debug, Error)]
+pub enum VerilogParseError {
+    #[error("{file}: {message}")]
+    Io { file: String, message: String },
+    #[error("{file}: {message}")]
+    Netlist { file: String, message: String },
+    #[error("{file}:{line}:{column}: {message}")]
+    Syntax {
+        file: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+/// Lint an `input`, `output`, `wire` or `reg` declaration.
+fn lint_declaration(line: &str) -> Result<(), String> {
+    if !line.ends_with(';') {
+        return Err(format!("Missing ';' after the declaration \"{}\"", line));
+    }
+    let (declaration, _) = split_declaration_init(line);
+    let tokens = declaration
+        .split([',', ' '].as_ref())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    match tokens.get(1) {
+        Some(range) if range.starts_with('[') => {
+            if !range.ends_with(']') || parse_range(range).is_none() {
+                return Err(format!("Invalid range \"{}\"", range));
+            }
+            if tokens.len() < 3 {
+                return Err(format!("Missing wire name after \"{}\"", range));
+            }
+        }
+        Some(_) => (),
+        None => return Err(format!("Missing wire name after \"{}\"", tokens[0])),
+    }
+
+    Ok(())
+}
+
+/// Lint a sized literal such as `8'hA5` or `4'b10xz`.
+fn lint_literal(token: &str) -> Result<(), String> {
+    let Some((width, value)) = token.split_once('\'') else {
+        return Ok(());
+    };
+    if !matches!(width.parse::<usize>(), Ok(1..=128)) {
+        return Err(format!("Invalid literal width in \"{}\"", token));
+    }
+    let mut chars = value.chars();
+    let radix = match chars.next().map(|c| c.to_ascii_lowercase()) {
+        Some('b') => 2,
+        Some('o') => 8,
+        Some('d') => 10,
+        Some('h') => 16,
+        _ => return Err(format!("Invalid literal base in \"{}\"", token)),
+    };
+    let digits = chars.as_str();
+    let valid_digit = |c: char| {
+        c == '_' || c.is_digit(radix) || (radix != 10 && matches!(c, 'x' | 'X' | 'z' | 'Z' | '?'))
+    };
+    if digits.is_empty() || !digits.chars().all(valid_digit) {
+        return Err(format!("Invalid literal value in \"{}\"", token));
+    }
+
+    Ok(())
+}
+
+/// Lint an `assign lhs = expr;` statement.
+fn lint_assign(line: &str, is_arith: bool) -> Result<(), String> {
+    let statement = line.trim_start_matches("assign").trim();
+    let Some((lhs, rhs)) = statement
+        .strip_suffix(';')
+        .and_then(|statement| statement.split_once('='))
+        .filter(|(lhs, rhs)| !lhs.trim().is_empty() && !rhs.trim().is_empty())
+    else {
+        return Err(format!("Invalid assign \"{}\"", line));
+    };
+    if let Some((_, select)) = lhs.trim().split_once('[') {
+        if !select.ends_with(']') || parse_range(select).is_none() {
+            return Err(format!("Invalid bit select \"{}\"", lhs.trim()));
+        }
+    }
+
+    let tokens = tokenize_assign_expr(rhs);
+    let mut depth = 0;
+    for token in tokens.iter() {
+        match token.as_str() {
+            "(" => depth += 1,
+            ")" if depth == 0 => {
+                return Err(format!("Unbalanced parentheses in \"{}\"", rhs.trim()))
+            }
+            ")" => depth -= 1,
+            "?" if is_arith => {
+                return Err("Ternary assigns are only supported in boolean mode".into())
+            }
+            token if token.contains('\'') => {
+                if is_arith {
+                    return Err("Literal assigns are only supported in boolean mode".into());
+                }
+                lint_literal(token)?;
+            }
+            _ => (),
+        }
+    }
+    if depth != 0 {
+        return Err(format!("Unbalanced parentheses in \"{}\"", rhs.trim()));
+    }
+    let count = |symbol: &str| tokens.iter().filter(|token| *token == symbol).count();
+    if count("?") != count(":") {
+        return Err(format!("Invalid assign expression \"{}\"", rhs.trim()));
+    }
+
+    Ok(())
+}
+
+/// Check a cell instance against its entry in `GATE_CELLS`, returning its
+/// gate type if it has one. `read_verilog_file` runs it before parsing each
+/// cell, so the parsers below it can assume well-formed pins.
+fn lint_cell(tokens: &[&str], is_arith: bool) -> Result<Option<GateType>, String> {
+    let keyword = tokens.first().copied().unwrap_or_default();
+    let Some(cell) = GATE_CELLS.iter().find(|cell| cell.keyword == keyword) else {
+        return Err(format!("Invalid gate type \"{}\"", keyword));
+    };
+    let statement = tokens[1..].join(" ");
+    let Some(statement) = statement.strip_suffix(';') else {
+        return Err(format!("Missing ';' after the \"{}\" cell", keyword));
+    };
+    let (name, pins) = cell_wires(tokens);
+    if name.is_empty() {
+        return Err(format!(
+            "Missing instance name for the \"{}\" cell",
+            keyword
+        ));
+    }
+    if !statement.contains('(') || !statement.trim_end().ends_with(')') {
+        return Err(format!("Expected the pins of \"{}\" in parentheses", name));
+    }
+
+    let pin_error = || format!("Cell \"{}\" should have the pins {}", name, cell.pins);
+    let leading_number = || pins.first().and_then(|pin| pin.parse::<usize>().ok());
+    match &cell.gate_type {
+        None if keyword == "acc" => {
+            if !is_arith {
+                return Err("Accumulators are only supported in arithmetic mode".into());
+            }
+            if !matches!(pins.len(), 2 | 3) {
+                return Err(pin_error());
+            }
+        }
+        None => unreachable!("Accumulators are the only cells without a gate type"),
+        Some(GateType::PriorityEncoder) => {
+            let width = leading_number()
+                .filter(|width| *width >= 2 && pins.len() > *width + 1)
+                .ok_or_else(pin_error)?;
+            let num_outputs = pins.len() - width - 1;
+            if num_outputs != 1 && (is_arith || num_outputs != width) {
+                return Err(pin_error());
+            }
+        }
+        Some(GateType::Not | GateType::Buf | GateType::Dff | GateType::Copy) => {
+            if pins.len() != 2 {
+                return Err(pin_error());
+            }
+        }
+        Some(GateType::ConstOne | GateType::ConstZero | GateType::ConstUnknown) => {
+            if pins.len() != 1 {
+                return Err(pin_error());
+            }
+        }
+        Some(GateType::Mux) => {
+            if pins.len() != 4 {
+                return Err(pin_error());
+            }
+        }
+        Some(GateType::MuxN) => {
+            let arity = leading_number()
+                .filter(|arity| *arity >= 2 && arity.is_power_of_two())
+                .ok_or_else(|| {
+                    format!("MUXN arity should be a power of two, found \"{}\"", pins[0])
+                })?;
+            let num_inputs = pins.len().saturating_sub(2);
+            if num_inputs != arity.trailing_zeros() as usize + arity && num_inputs != 1 + arity {
+                return Err(pin_error());
+            }
+        }
+        Some(GateType::Lut) => {
+            if pins.len() < 3 {
+                return Err(pin_error());
+            }
+            let table = &pins[0];
+            let valid = match table.strip_prefix("0x") {
+                Some(hex) => usize::from_str_radix(hex, 16).is_ok(),
+                None => table.parse::<usize>().is_ok(),
+            };
+            if !valid {
+                return Err(format!("Invalid LUT table \"{}\"", table));
+            }
+        }
+        Some(_) => {
+            if pins.len() != 3 {
+                return Err(pin_error());
+            }
+        }
+    }
+
+    Ok(cell.gate_type.clone())
+}
+
+/// Check the syntax of `file_name` statement by statement, without building
+/// the circuit, and report the first error with its position. The module
+/// attributes are checked too, and `helm_arith` selects arithmetic mode like
+/// `is_arith`.
+pub fn check_verilog_file(file_name: &str, is_arith: bool) -> Result<(), VerilogParseError> {
+    let text = std::fs::read_to_string(file_name).map_err(|error| VerilogParseError::Io {
+        file: file_name.to_owned(),
+        message: error.to_string(),
+    })?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+    let source = strip_comments(&text.replace("\r\n", "\n"));
+
+    let attributes = module_attributes(&source);
+    let netlist_error = |message| VerilogParseError::Netlist {
+        file: file_name.to_owned(),
+        message,
+    };
+    if let Some(arithmetic_type) = attributes.get("helm_arith") {
+        crate::arith_attribute(arithmetic_type).map_err(netlist_error)?;
+    }
+    crate::netlist_cycle_count(file_name, &attributes).map_err(netlist_error)?;
+    let is_arith = is_arith || attributes.contains_key("helm_arith");
+
+    // The line where the module header being read starts, and the nesting
+    // depth of the always block being skipped.
+    let mut header = None;
+    let mut always_depth = None;
+    let (mut has_gates, mut has_luts, mut has_arith) = (false, false, false);
+    for (line_number, contents) in source.lines().enumerate() {
+        let (_, line) = split_attributes(contents.trim());
+        let words = line
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .collect::<Vec<_>>();
+        // Always blocks are lowered to assigns when the netlist is read, so
+        // only their extent matters here.
+        let nesting = words
+            .iter()
+            .map(|word| match *word {
+                "begin" | "case" => 1,
+                "end" | "endcase" => -1,
+                _ => 0,
+            })
+            .sum::<i32>();
+        if words
+            .first()
+            .map_or(false, |word| *word == "always" || *word == "always_comb")
+        {
+            always_depth = Some(0);
+        }
+        if let Some(depth) = always_depth.as_mut() {
+            *depth += nesting;
+            if *depth == 0 && (line.contains(';') || nesting < 0) {
+                always_depth = None;
+            }
+            has_gates = true;
+            continue;
+        }
+
+        let result = if line.starts_with("module") || header.is_some() {
+            header.get_or_insert(line_number);
+            if line.contains(';') {
+                header = None;
+            }
+            Ok(())
+        } else if line.is_empty() || line.starts_with("endmodule") {
+            Ok(())
+        } else {
+            let tokens = line
+                .split([',', ' '].as_ref())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>();
+            match tokens.first().copied().unwrap_or(line) {
+                "input" | "output" | "wire" | "reg" => lint_declaration(line),
+                "assign" => {
+                    has_gates = true;
+                    lint_assign(line, is_arith)
+                }
+                _ => lint_cell(&tokens, is_arith).map(|gate_type| {
+                    has_gates = true;
+                    has_luts |= gate_type == Some(GateType::Lut);
+                    has_arith |= tokens[0] == "acc"
+                        || gate_type.map_or(false, |gate_type| gate_type.is_arithmetic());
+                }),
+            }
+        };
+
+        if let Err(message) = result {
+            // Point at the quoted token of the message if there is one, and
+            // at the start of the statement otherwise.
+            let token_column = message
+                .split('"')
+                .nth(1)
+                .filter(|token| !token.is_empty())
+                .and_then(|token| contents.find(token));
+            let column =
+                token_column.unwrap_or_else(|| contents.len() - contents.trim_start().len()) + 1;
+            return Err(VerilogParseError::Syntax {
+                file: file_name.to_owned(),
+                line: line_number + 1,
+                column,
+                message,
+            });
+        }
+    }
+
+    let message = if let Some(line_number) = header {
+        format!(
+            "The module header on line {} is never closed",
+            line_number + 1
+        )
+    } else if always_depth.is_some() {
+        "An always block is never closed".to_owned()
+    } else if !has_gates {
+        "No gates detected. Make sure to use the 'no-expr' flag in Yosys.".to_owned()
+    } else if has_arith && has_luts {
+        "Can't mix LUTs with arithmetic operators!".to_owned()
+    } else {
+        return Ok(());
+    };
+    Err(netlist_error(message))
+}
+
 pub fn read_verilog_file(
     file_name: &str,
     is_arith: bool,
@@ -147,8 +1475,8 @@ pub fn read_verilog_file(
     bool,
     bool,
 ) {
-    let file = File::open(file_name).expect("Failed to open file");
-    let reader = BufReader::new(file);
+    let source = strip_comments(&read_text_file(file_name, "Failed to open file"));
+    let source = lower_always_blocks(&source);
 
     let mut has_luts = false;
     let mut has_arith = false;
@@ -158,14 +1486,30 @@ pub fn read_verilog_file(
     let mut outputs = Vec::new();
     let mut _wires = Vec::new();
     let mut dff_outputs = Vec::new();
-    for line in reader.lines() {
-        let line = line.expect("Failed to read line").trim().to_owned();
+    let mut bus_ranges = HashMap::new();
+    let mut assign_count = 0;
+    let mut ports = Vec::new();
+    // The module header read so far, while it spans several lines.
+    let mut header: Option<String> = None;
+    for line in source.lines() {
+        let (_, line) = split_attributes(line.trim());
 
-        if line.is_empty()
-            || line.starts_with("module")
-            || line.starts_with("endmodule")
-            || line.starts_with("//")
-        {
+        if line.starts_with("module") || header.is_some() {
+            let text = match header.take() {
+                Some(text) => format!("{} {}", text, line),
+                None => line.to_owned(),
+            };
+            if text.contains(';') {
+                ports = parse_module_ports(&text);
+            } else {
+                header = Some(text);
+            }
+            continue;
+        }
+        let (declaration, _) = split_declaration_init(line);
+        let line = declaration.as_str();
+
+        if line.is_empty() || line.starts_with("endmodule") || line.starts_with("//") {
             continue;
         }
 
@@ -176,7 +1520,10 @@ pub fn read_verilog_file(
         match tokens[0] {
             "input" => {
                 if let Some((start, end)) = parse_range(tokens[1]) {
-                    let input_name = tokens[2].trim_matches(',').trim_end_matches(';');
+                    for token in tokens.iter().skip(2) {
+                        let name = token.trim_matches(',').trim_end_matches(';');
+                        bus_ranges.insert(name.to_owned(), (start, end));
+                    }
                     if is_arith {
                         inputs.extend(
                             tokens[2..]
@@ -184,7 +1531,11 @@ pub fn read_verilog_file(
                                 .map(|t| t.trim_matches(',').trim_end_matches(';').to_owned()),
                         );
                     } else {
-                        inputs.extend((start..end + 1).map(|i| format!("{}[{}]", input_name, i)));
+                        for token in tokens.iter().skip(2) {
+                            let input_name = token.trim_matches(',').trim_end_matches(';');
+                            inputs
+                                .extend((start..end + 1).map(|i| format!("{}[{}]", input_name, i)));
+                        }
                     }
                 } else {
                     inputs.extend(
@@ -196,7 +1547,10 @@ pub fn read_verilog_file(
             }
             "output" => {
                 if let Some((start, end)) = parse_range(tokens[1]) {
-                    let output_name = tokens[2].trim_matches(',').trim_end_matches(';');
+                    for token in tokens.iter().skip(2) {
+                        let name = token.trim_matches(',').trim_end_matches(';');
+                        bus_ranges.insert(name.to_owned(), (start, end));
+                    }
                     if is_arith {
                         outputs.extend(
                             tokens[2..]
@@ -204,7 +1558,12 @@ pub fn read_verilog_file(
                                 .map(|t| t.trim_matches(',').trim_end_matches(';').to_owned()),
                         );
                     } else {
-                        outputs.extend((start..end + 1).map(|i| format!("{}[{}]", output_name, i)));
+                        for token in tokens.iter().skip(2) {
+                            let output_name = token.trim_matches(',').trim_end_matches(';');
+                            outputs.extend(
+                                (start..end + 1).map(|i| format!("{}[{}]", output_name, i)),
+                            );
+                        }
                     }
                 } else {
                     outputs.extend(
@@ -215,32 +1574,50 @@ pub fn read_verilog_file(
                 }
             }
             "wire" => {
+                if let Some((start, end)) = parse_range(tokens[1]) {
+                    for token in tokens.iter().skip(2) {
+                        let name = token.trim_matches(',').trim_end_matches(';');
+                        bus_ranges.insert(name.to_owned(), (start, end));
+                    }
+                }
                 for token in tokens.iter().skip(1) {
                     _wires.push(String::from(token.trim_matches(',').trim_end_matches(';')));
                 }
             }
+            "assign" => {
+                for gate in parse_assign(line, &bus_ranges, is_arith, &mut assign_count) {
+                    if gate.get_gate_type().is_arithmetic() {
+                        has_arith = true;
+                    }
+                    wire_set.insert(gate.get_output_wire());
+                    gates.insert(gate);
+                }
+            }
             _ => {
-                // Gate
-                let gate = parse_gate(&tokens);
-                if gate.get_gate_type() == GateType::Dff {
-                    inputs.push(gate.get_output_wire());
-                    dff_outputs.push(gate.get_output_wire());
-                } else if gate.get_gate_type() == GateType::Lut {
-                    has_luts = true;
-                } else if gate.get_gate_type() == GateType::Add
-                    || gate.get_gate_type() == GateType::Sub
-                    || gate.get_gate_type() == GateType::Mult
-                    || gate.get_gate_type() == GateType::Div
-                    || gate.get_gate_type() == GateType::Shl
-                    || gate.get_gate_type() == GateType::Shr
-                    || gate.get_gate_type() == GateType::Copy
-                {
-                    has_arith = true;
+                // Cells follow the same rules as check_verilog_file.
+                if let Err(message) = lint_cell(&tokens, is_arith) {
+                    panic!("{}", message);
                 }
+                // Gate, or the gates of an accumulator or priority encoder
+                let cell_gates = match tokens[0] {
+                    "acc" => parse_accumulator(&tokens),
+                    "penc" => parse_priority_encoder(&tokens),
+                    _ => vec![parse_gate(&tokens)],
+                };
+                for gate in cell_gates {
+                    if gate.get_gate_type() == GateType::Dff {
+                        inputs.push(gate.get_output_wire());
+                        dff_outputs.push(gate.get_output_wire());
+                    } else if gate.get_gate_type() == GateType::Lut {
+                        has_luts = true;
+                    } else if gate.get_gate_type().is_arithmetic() {
+                        has_arith = true;
+                    }
 
-                wire_set.insert(gate.get_output_wire());
+                    wire_set.insert(gate.get_output_wire());
 
-                gates.insert(gate);
+                    gates.insert(gate);
+                }
             }
         }
     }
@@ -264,6 +1641,13 @@ pub fn read_verilog_file(
         panic!("Can't mix LUTs with arithmetic operators!");
     }
 
+    // Outputs follow the order of the module ports.
+    let port_position = |wire: &String| {
+        let port = wire.split_once('[').map_or(wire.as_str(), |(bus, _)| bus);
+        ports.iter().position(|p| p == port).unwrap_or(ports.len())
+    };
+    outputs.sort_by_key(port_position);
+
     (
         gates,
         wire_set,
@@ -276,15 +1660,35 @@ pub fn read_verilog_file(
 }
 
 pub fn read_input_wires(file_name: &str, ptxt_type: &str) -> HashMap<String, PtxtType> {
-    let inputs_file = File::open(file_name).expect("Failed to open CSV file");
-    let reader = BufReader::new(inputs_file);
+    let contents = read_text_file(file_name, "Failed to open CSV file");
 
     let mut input_map = HashMap::new();
-    for rec in Reader::from_reader(reader).records() {
+    // Values of the form `=wire` mirror another wire, resolved at the end.
+    let mut references = HashMap::new();
+    for rec in Reader::from_reader(contents.as_bytes()).records() {
         let record = rec.unwrap();
         let wire_name = record[0].trim().to_string();
 
-        if record.len() == 2 {
+        if let Some(target) = record
+            .get(1)
+            .and_then(|value| value.trim().strip_prefix('='))
+        {
+            let target = target.trim().to_string();
+            let wire_width = match record.get(2) {
+                Some(width) if ptxt_type == "bool" => width.trim().parse::<usize>().unwrap(),
+                _ => 1,
+            };
+            if wire_width > 1 {
+                for idx in 0..wire_width {
+                    references.insert(
+                        format!("{}[{}]", wire_name, idx),
+                        format!("{}[{}]", target, idx),
+                    );
+                }
+            } else {
+                references.insert(wire_name, target);
+            }
+        } else if record.len() == 2 {
             let wire_value = parse_input_wire(record[1].trim(), ptxt_type);
             input_map.insert(wire_name, wire_value);
         } else if record.len() == 3 && ptxt_type == "bool" {
@@ -313,15 +1717,461 @@ pub fn read_input_wires(file_name: &str, ptxt_type: &str) -> HashMap<String, Ptx
         }
     }
 
+    let resolved = references
+        .keys()
+        .map(|wire| {
+            let value = resolve_input_reference(wire, &references, &input_map, &mut vec![]);
+            (wire.clone(), value)
+        })
+        .collect::<Vec<_>>();
+    input_map.extend(resolved);
+
     input_map
 }
 
-pub fn write_output_wires(file_name: Option<String>, input_map: &HashMap<String, PtxtType>) {
+/// Value of the input `wire`, following `=wire` references.
+fn resolve_input_reference(
+    wire: &str,
+    references: &HashMap<String, String>,
+    input_map: &HashMap<String, PtxtType>,
+    chain: &mut Vec<String>,
+) -> PtxtType {
+    if chain.iter().any(|previous| previous == wire) {
+        chain.push(wire.to_owned());
+        panic!(
+            "{}[!]{} Input wire reference cycle: {}.",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            chain.join(" -> ")
+        );
+    }
+    if let Some(target) = references.get(wire) {
+        chain.push(wire.to_owned());
+        resolve_input_reference(target, references, input_map, chain)
+    } else if let Some(value) = input_map.get(wire) {
+        *value
+    } else {
+        panic!(
+            "{}[!]{} Input wire \"{}\" references unknown wire \"{}\".",
+            color::Fg(color::LightRed),
+            color::Fg(color::Reset),
+            chain.first().map_or(wire, String::as_str),
+            wire
+        );
+    }
+}
+
+/// Read a CSV of `file_name, netlist_name` wire aliases.
+pub fn read_wire_aliases(file_name: &str) -> HashMap<String, String> {
+    let contents = read_text_file(file_name, "Failed to open CSV file");
+
+    let mut aliases = HashMap::new();
+    for rec in Reader::from_reader(contents.as_bytes()).records() {
+        let record = rec.unwrap();
+        if record.len() != 2 {
+            panic!(
+                "{}[!]{} Wire alias records should be \"file_name, netlist_name\".",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset)
+            );
+        }
+        aliases.insert(record[0].trim().to_string(), record[1].trim().to_string());
+    }
+
+    aliases
+}
+
+/// Read the `set_case_analysis VALUE [get_ports PORTS]` commands of an SDC
+/// constraints file, giving the constant of every port they tie. Other
+/// commands are ignored.
+pub fn read_constraints(file_name: &str) -> HashMap<String, bool> {
+    let contents = read_text_file(file_name, "Failed to open constraints file");
+
+    let mut constants = HashMap::new();
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some(arguments) = line.strip_prefix("set_case_analysis") else {
+            continue;
+        };
+        let (value, ports) = arguments.trim().split_once(' ').unwrap_or((arguments, ""));
+        let value = match value {
+            "0" | "zero" => false,
+            "1" | "one" => true,
+            _ => panic!(
+                "{}[!]{} Unsupported case analysis value \"{}\", expected 0 or 1.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                value
+            ),
+        };
+        let Some(ports) = ports
+            .trim()
+            .strip_prefix('[')
+            .and_then(|ports| ports.strip_suffix(']'))
+            .and_then(|ports| ports.trim().strip_prefix("get_ports"))
+        else {
+            panic!(
+                "{}[!]{} Case analysis should apply to [get_ports ...], found \"{}\".",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                line
+            );
+        };
+        for port in ports
+            .split(|c: char| c.is_whitespace() || c == '{' || c == '}')
+            .filter(|port| !port.is_empty())
+        {
+            constants.insert(port.to_owned(), value);
+        }
+    }
+
+    constants
+}
+
+/// Read a patch file of netlist edits, one per line: `type GATE TYPE`,
+/// `rewire GATE INDEX WIRE` or `tie WIRE 0|1`, where `#` starts a comment.
+pub fn read_patch(file_name: &str) -> Vec<PatchEdit> {
+    let contents = read_text_file(file_name, "Failed to open patch file");
+
+    let mut edits = vec![];
+    for (line_number, line) in contents.lines().enumerate() {
+        let tokens = line
+            .split('#')
+            .next()
+            .unwrap_or("")
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        let edit = match tokens[..] {
+            [] => continue,
+            ["type", gate, gate_type] => {
+                parse_gate_type(gate_type).map(|gate_type| PatchEdit::SetType {
+                    gate: gate.to_owned(),
+                    gate_type,
+                })
+            }
+            ["rewire", gate, input, wire] => {
+                input.parse::<usize>().ok().map(|input| PatchEdit::Rewire {
+                    gate: gate.to_owned(),
+                    input,
+                    wire: wire.to_owned(),
+                })
+            }
+            ["tie", wire, value @ ("0" | "1")] => Some(PatchEdit::Tie {
+                wire: wire.to_owned(),
+                value: value == "1",
+            }),
+            _ => None,
+        };
+        let Some(edit) = edit else {
+            panic!(
+                "{}[!]{} {}:{}: Invalid patch edit \"{}\".",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                file_name,
+                line_number + 1,
+                line.trim()
+            );
+        };
+        edits.push(edit);
+    }
+
+    edits
+}
+
+/// Read a gate schedule, the names of the gates in evaluation order,
+/// separated by whitespace, where `#` starts a comment.
+pub fn read_schedule(file_name: &str) -> Vec<String> {
+    read_text_file(file_name, "Failed to open schedule file")
+        .lines()
+        .flat_map(|line| line.split('#').next().unwrap_or("").split_whitespace())
+        .map(|gate| gate.to_owned())
+        .collect()
+}
+
+/// Read a CSV of input vectors: a header row with the wire names, then one
+/// row of values per vector.
+pub fn read_input_vectors(file_name: &str, ptxt_type: &str) -> Vec<HashMap<String, PtxtType>> {
+    let contents = read_text_file(file_name, "Failed to open CSV file");
+
+    let mut reader = Reader::from_reader(contents.as_bytes());
+    let wires = reader
+        .headers()
+        .expect("Failed to read CSV header")
+        .iter()
+        .map(|wire| wire.trim().to_owned())
+        .collect::<Vec<_>>();
+    reader
+        .records()
+        .map(|rec| {
+            let record = rec.unwrap();
+            if record.len() != wires.len() {
+                panic!(
+                    "{}[!]{} Input vector {:?} should have a value for each of the {} wires.",
+                    color::Fg(color::LightRed),
+                    color::Fg(color::Reset),
+                    record,
+                    wires.len()
+                );
+            }
+            wires
+                .iter()
+                .zip(record.iter())
+                .map(|(wire, value)| (wire.to_string(), parse_input_wire(value.trim(), ptxt_type)))
+                .collect()
+        })
+        .collect()
+}
+
+/// A value as `read_input_wires` parses it.
+fn input_value(value: &PtxtType) -> String {
+    match value {
+        PtxtType::Bool(value) => value.to_string(),
+        PtxtType::U256(value) => value.to_string(),
+        PtxtType::Unknown => "x".to_owned(),
+        PtxtType::None => String::new(),
+        value => value.low_u128().to_string(),
+    }
+}
+
+/// Write what it takes to reproduce a failed `--verify` to the directory
+/// `dir`: a copy of the netlist, the inputs as an `--input-wires-file`, the
+/// outputs that differ, and the command to run from there. `extra_args` are
+/// passed as they are, and every `(flag, file)` of `extra_files` is copied
+/// next to the netlist and passed by its name.
+pub fn write_repro(
+    dir: &str,
+    netlist_file: &str,
+    inputs: &HashMap<String, PtxtType>,
+    mismatches: &[Mismatch],
+    extra_args: &[String],
+    extra_files: &[(String, String)],
+) {
+    let dir = std::path::Path::new(dir);
+    std::fs::create_dir_all(dir).expect("Failed to create repro directory");
+    let copy_file = |file: &str| {
+        let name = std::path::Path::new(file)
+            .file_name()
+            .unwrap_or_else(|| panic!("Path {} has no file name", file));
+        std::fs::copy(file, dir.join(name))
+            .unwrap_or_else(|_| panic!("Failed to copy {} to the repro directory", file));
+        name.to_string_lossy().into_owned()
+    };
+    let netlist_name = copy_file(netlist_file);
+    let file_args = extra_files
+        .iter()
+        .flat_map(|(flag, file)| [flag.clone(), copy_file(file)])
+        .collect::<Vec<_>>();
+
+    let file = File::create(dir.join("inputs.csv")).expect("Failed to create CSV file");
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "wire, value").expect("Failed to write record");
+    for (wire, value) in inputs.iter().filter(|(wire, _)| *wire != "dummy").sorted() {
+        writeln!(writer, "{}, {}", wire, input_value(value)).expect("Failed to write record");
+    }
+
+    let file = File::create(dir.join("mismatches.csv")).expect("Failed to create CSV file");
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "wire,expected,actual").expect("Failed to write record");
+    for mismatch in mismatches {
+        writeln!(
+            writer,
+            "{},{},{}",
+            mismatch.wire,
+            input_value(&mismatch.expected),
+            input_value(&mismatch.actual)
+        )
+        .expect("Failed to write record");
+    }
+
+    let command = format!(
+        "helm --verilog {} --input-wires-file inputs.csv",
+        netlist_name
+    );
+    let command = std::iter::once(command)
+        .chain(extra_args.iter().cloned())
+        .chain(file_args)
+        .join(" ");
+    std::fs::write(dir.join("command.txt"), command + "\n").expect("Failed to write command");
+}
+
+// Binary wire files start with this magic and version byte, followed by
+// records of a little-endian u32 length, then that many bytes: the u16
+// length of the wire name, the name, the type tag (its index in
+// BINARY_TYPES) and the little-endian value (a byte for booleans).
+const BINARY_MAGIC: &[u8] = b"HELM\x01";
+const BINARY_TYPES: [&str; 7] = ["bool", "u8", "u16", "u32", "u64", "u128", "u256"];
+
+/// Write wire values in the binary format of `read_binary_wires`, sorted by
+/// wire name.
+pub fn write_binary_wires(file_name: &str, wire_map: &HashMap<String, PtxtType>) {
+    let mut bytes = BINARY_MAGIC.to_vec();
+    for (wire, value) in wire_map.iter().sorted() {
+        let (tag, value_bytes) = match value {
+            PtxtType::Bool(value) => (0, vec![*value as u8]),
+            PtxtType::U8(value) => (1, value.to_le_bytes().to_vec()),
+            PtxtType::U16(value) => (2, value.to_le_bytes().to_vec()),
+            PtxtType::U32(value) => (3, value.to_le_bytes().to_vec()),
+            PtxtType::U64(value) => (4, value.to_le_bytes().to_vec()),
+            PtxtType::U128(value) => (5, value.to_le_bytes().to_vec()),
+            PtxtType::U256(value) => (
+                6,
+                [value.low().to_le_bytes(), value.high().to_le_bytes()].concat(),
+            ),
+            PtxtType::Unknown | PtxtType::None => {
+                panic!("Wire \"{}\" has no value to write", wire)
+            }
+        };
+        let record_length = 2 + wire.len() + 1 + value_bytes.len();
+        bytes.extend((record_length as u32).to_le_bytes());
+        bytes.extend((wire.len() as u16).to_le_bytes());
+        bytes.extend(wire.as_bytes());
+        bytes.push(tag);
+        bytes.extend(value_bytes);
+    }
+
+    std::fs::write(file_name, bytes).expect("Failed to write binary file");
+}
+
+fn binary_error(file_name: &str, reason: &str) -> ! {
+    panic!(
+        "{}[!]{} Invalid binary wire file {}: {}.",
+        color::Fg(color::LightRed),
+        color::Fg(color::Reset),
+        file_name,
+        reason
+    )
+}
+
+/// Read the wire values of a binary file written by `write_binary_wires`,
+/// which must all be of type `ptxt_type`. Much faster than CSV for large
+/// input sets.
+pub fn read_binary_wires(file_name: &str, ptxt_type: &str) -> HashMap<String, PtxtType> {
+    let bytes = std::fs::read(file_name).expect("Failed to open binary file");
+
+    let Some(mut rest) = bytes.strip_prefix(BINARY_MAGIC) else {
+        binary_error(file_name, "not a HELM binary file, or of another version");
+    };
+    let mut wire_map = HashMap::new();
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            binary_error(file_name, "truncated record length");
+        }
+        let (length, tail) = rest.split_at(4);
+        let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+        if tail.len() < length || length < 3 {
+            binary_error(file_name, "truncated record");
+        }
+        let (record, tail) = tail.split_at(length);
+        rest = tail;
+
+        let name_length = u16::from_le_bytes([record[0], record[1]]) as usize;
+        if record.len() < 3 + name_length {
+            binary_error(file_name, "wire name longer than its record");
+        }
+        let wire = std::str::from_utf8(&record[2..2 + name_length])
+            .unwrap_or_else(|_| binary_error(file_name, "wire name is not UTF-8"));
+        let tag = record[2 + name_length] as usize;
+        let value = &record[3 + name_length..];
+        if BINARY_TYPES.get(tag) != Some(&ptxt_type) {
+            binary_error(
+                file_name,
+                &format!(
+                    "wire \"{}\" is a {}, expected {}",
+                    wire,
+                    BINARY_TYPES.get(tag).unwrap_or(&"unknown type"),
+                    ptxt_type
+                ),
+            );
+        }
+        let expected_length = [1, 1, 2, 4, 8, 16, 32][tag];
+        if value.len() != expected_length {
+            binary_error(
+                file_name,
+                &format!("wire \"{}\" has a value of {} bytes", wire, value.len()),
+            );
+        }
+        let value = match tag {
+            0 => PtxtType::Bool(value[0] != 0),
+            1 => PtxtType::U8(value[0]),
+            2 => PtxtType::U16(u16::from_le_bytes(value.try_into().unwrap())),
+            3 => PtxtType::U32(u32::from_le_bytes(value.try_into().unwrap())),
+            4 => PtxtType::U64(u64::from_le_bytes(value.try_into().unwrap())),
+            5 => PtxtType::U128(u128::from_le_bytes(value.try_into().unwrap())),
+            _ => {
+                let (low, high) = value.split_at(16);
+                PtxtType::U256(U256::new(
+                    u128::from_le_bytes(high.try_into().unwrap()),
+                    u128::from_le_bytes(low.try_into().unwrap()),
+                ))
+            }
+        };
+        wire_map.insert(wire.to_owned(), value);
+    }
+
+    wire_map
+}
+
+/// Write the outputs of several input vectors as CSV: a header row with the
+/// wire names, in the order of `wire_order`, then one row per vector.
+pub fn write_output_vectors(
+    file_name: &str,
+    outputs: &[HashMap<String, PtxtType>],
+    wire_order: &[String],
+) {
+    let file = File::create(file_name).expect("Failed to create CSV file");
+    let mut writer = BufWriter::new(file);
+
+    let wires = wire_order
+        .iter()
+        .filter(|wire| outputs.iter().any(|output| output.contains_key(*wire)))
+        .chain(
+            outputs
+                .iter()
+                .flat_map(|output| output.keys())
+                .filter(|wire| !wire_order.contains(wire))
+                .sorted()
+                .dedup(),
+        )
+        .collect::<Vec<_>>();
+    writeln!(writer, "{}", wires.iter().join(",")).expect("Failed to write record");
+    for output in outputs {
+        let values = wires.iter().map(|wire| match output.get(*wire) {
+            Some(PtxtType::Bool(value)) => value.to_string(),
+            Some(value) => value.to_hex(),
+            None => String::new(),
+        });
+        writeln!(writer, "{}", values.format(",")).expect("Failed to write record");
+    }
+}
+
+/// Write the output wires to a CSV file, in the order of `wire_order`. Wires
+/// missing from `wire_order` come last, sorted by name.
+pub fn write_output_wires(
+    file_name: Option<String>,
+    input_map: &HashMap<String, PtxtType>,
+    wire_order: &[String],
+    hex_output: bool,
+) {
     if let Some(file_name) = file_name {
         let file = File::create(&file_name).expect("Failed to create CSV file");
         let mut writer = BufWriter::new(file);
 
-        for (input_wire, ptxt_type) in input_map.iter() {
+        let ordered_wires = wire_order
+            .iter()
+            .filter(|wire| input_map.contains_key(*wire))
+            .chain(
+                input_map
+                    .keys()
+                    .filter(|wire| !wire_order.contains(wire))
+                    .sorted(),
+            );
+        for input_wire in ordered_wires {
+            let ptxt_type = &input_map[input_wire];
+            if hex_output {
+                writeln!(writer, "{}, {}", input_wire, ptxt_type.to_hex())
+                    .expect("Failed to write record");
+                continue;
+            }
             match ptxt_type {
                 PtxtType::Bool(value) => {
                     writeln!(writer, "{}, {}", input_wire, value).expect("Failed to write record");
@@ -341,9 +2191,199 @@ pub fn write_output_wires(file_name: Option<String>, input_map: &HashMap<String,
                 PtxtType::U128(value) => {
                     writeln!(writer, "{}, {}", input_wire, value).expect("Failed to write record");
                 }
+                PtxtType::U256(value) => {
+                    writeln!(writer, "{}, {}", input_wire, value).expect("Failed to write record");
+                }
+                PtxtType::Unknown => {
+                    writeln!(writer, "{}, x", input_wire).expect("Failed to write record");
+                }
                 PtxtType::None => unreachable!(),
             }
         }
-        println!("Decrypted outputs written to {}", file_name);
+        info_println!("Decrypted outputs written to {}", file_name);
+    }
+}
+
+/// Pack the values of the register outputs into one number per register
+/// bus, with `name[i]` as bit i of `name`.
+pub fn pack_register_state(register_values: &HashMap<String, PtxtType>) -> BTreeMap<String, u128> {
+    let mut state = BTreeMap::new();
+    for (wire, value) in register_values.iter() {
+        let bit_value = match value {
+            PtxtType::Bool(value) => *value,
+            PtxtType::U64(value) => value & 1 == 1,
+            _ => panic!("Register \"{}\" does not hold a bit", wire),
+        };
+        let (bus, bit) = match wire.split_once('[') {
+            Some((bus, bit)) => match bit.trim_end_matches(']').parse::<u32>() {
+                Ok(bit) => (bus, bit),
+                Err(_) => (wire.as_str(), 0),
+            },
+            None => (wire.as_str(), 0),
+        };
+        if bit >= u128::BITS {
+            panic!(
+                "{}[!]{} Register bus \"{}\" is wider than 128 bits.",
+                color::Fg(color::LightRed),
+                color::Fg(color::Reset),
+                bus
+            );
+        }
+        *state.entry(bus.to_owned()).or_insert(0) |= (bit_value as u128) << bit;
+    }
+
+    state
+}
+
+/// Write the register state of every cycle as CSV: a `cycle` column followed
+/// by one column per register bus, sorted by name.
+pub fn write_state_trace(file_name: &str, trace: &[BTreeMap<String, u128>]) {
+    let file = File::create(file_name).expect("Failed to create CSV file");
+    let mut writer = BufWriter::new(file);
+
+    let buses = trace
+        .iter()
+        .flat_map(|state| state.keys())
+        .sorted()
+        .dedup()
+        .collect::<Vec<_>>();
+    writeln!(writer, "cycle,{}", buses.iter().join(",")).expect("Failed to write record");
+    for (cycle, state) in trace.iter().enumerate() {
+        let values = buses
+            .iter()
+            .map(|bus| state.get(*bus).copied().unwrap_or(0));
+        writeln!(writer, "{},{}", cycle, values.format(",")).expect("Failed to write record");
+    }
+    info_println!("Register state trace written to {}", file_name);
+}
+
+/// Keyword of a gate in a netlist, the inverse of `parse_gate_type`. A new
+/// gate type needs a keyword here and a cell in `GATE_CELLS`.
+fn gate_keyword(gate_type: &GateType) -> &'static str {
+    match gate_type {
+        GateType::And => "and",
+        GateType::Dff => "dff",
+        GateType::Lut => "lut",
+        GateType::Mux => "mux",
+        GateType::MuxN => "muxn",
+        GateType::Nand => "nand",
+        GateType::Nor => "nor",
+        GateType::Not => "not",
+        GateType::Or => "or",
+        GateType::Xnor => "xnor",
+        GateType::Xor => "xor",
+        GateType::Buf => "buf",
+        GateType::ConstOne => "cone",
+        GateType::ConstZero => "czero",
+        GateType::ConstUnknown => "cx",
+        GateType::Mult => "mult",
+        GateType::Add => "add",
+        GateType::Sub => "sub",
+        GateType::Div => "div",
+        GateType::Shl => "shl",
+        GateType::Shr => "shr",
+        GateType::Copy => "copy",
+        GateType::PriorityEncoder => "penc",
+    }
+}
+
+/// Write gates as a netlist that `read_verilog_file` reads back, with every
+/// input and output wire declared on its own, e.g. one part of a partitioned
+/// circuit.
+pub fn write_verilog_file(
+    file_name: &str,
+    module_name: &str,
+    gates: &[Gate],
+    input_wires: &[String],
+    output_wires: &[String],
+) {
+    let file = File::create(file_name).expect("Failed to create Verilog file");
+    let mut writer = BufWriter::new(file);
+
+    let ports = input_wires
+        .iter()
+        .chain(output_wires)
+        .map(|wire| wire.split_once('[').map_or(wire.as_str(), |(bus, _)| bus))
+        .unique();
+    writeln!(writer, "module {}({});", module_name, ports.format(", "))
+        .expect("Failed to write netlist");
+    for wire in input_wires {
+        writeln!(writer, "  input {};", wire).expect("Failed to write netlist");
+    }
+    for wire in output_wires {
+        writeln!(writer, "  output {};", wire).expect("Failed to write netlist");
+    }
+
+    for gate in gates {
+        let mut arguments = gate.get_input_wires().clone();
+        match gate.get_gate_type() {
+            GateType::Lut => {
+                let lut_const = gate.get_lut_const().unwrap_or_default();
+                let value = lut_const
+                    .iter()
+                    .enumerate()
+                    .fold(0, |value, (i, bit)| value | ((*bit as usize) << i));
+                arguments.insert(0, format!("0x{:x}", value));
+            }
+            GateType::MuxN => {
+                let (_, arity) = muxn_layout(arguments.len());
+                arguments.insert(0, arity.to_string());
+            }
+            GateType::PriorityEncoder => arguments.insert(0, arguments.len().to_string()),
+            _ => {}
+        }
+        arguments.push(gate.get_output_wire());
+        writeln!(
+            writer,
+            "  {} {}({});",
+            gate_keyword(&gate.get_gate_type()),
+            gate.get_gate_name(),
+            arguments.join(", ")
+        )
+        .expect("Failed to write netlist");
+    }
+    writeln!(writer, "endmodule").expect("Failed to write netlist");
+}
+
+/// Write `cnf` in the DIMACS format to `cnf_file`, and the wire of each
+/// variable as CSV to `variables_file`.
+pub fn write_cnf(cnf_file: &str, variables_file: &str, cnf: &Cnf) {
+    let file = File::create(cnf_file).expect("Failed to create CNF file");
+    let mut writer = BufWriter::new(file);
+
+    writeln!(
+        writer,
+        "p cnf {} {}",
+        cnf.variables.len(),
+        cnf.clauses.len()
+    )
+    .expect("Failed to write clause");
+    for clause in &cnf.clauses {
+        writeln!(writer, "{} 0", clause.iter().join(" ")).expect("Failed to write clause");
+    }
+
+    let file = File::create(variables_file).expect("Failed to create CSV file");
+    let mut writer = BufWriter::new(file);
+
+    writeln!(writer, "variable,wire").expect("Failed to write record");
+    for (i, wire) in cnf.variables.iter().enumerate() {
+        writeln!(writer, "{},{}", i + 1, wire).expect("Failed to write record");
+    }
+}
+
+/// Write the wires between the parts of a partitioned circuit as CSV, one
+/// row per wire and reading part.
+pub fn write_cross_wires(file_name: &str, cross_wires: &[CrossWire]) {
+    let file = File::create(file_name).expect("Failed to create CSV file");
+    let mut writer = BufWriter::new(file);
+
+    writeln!(writer, "wire,from,to").expect("Failed to write record");
+    for cross_wire in cross_wires {
+        writeln!(
+            writer,
+            "{},{},{}",
+            cross_wire.wire, cross_wire.from, cross_wire.to
+        )
+        .expect("Failed to write record");
     }
 }
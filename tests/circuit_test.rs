@@ -1,3 +1,6 @@
+mod common;
+
+use common::Netlist;
 #[cfg(feature = "gpu")]
 use concrete_core::prelude::*;
 use debug_print::debug_println;
@@ -5,12 +8,19 @@ use debug_print::debug_println;
 use helm::circuit::CircuitCuda;
 use helm::{
     circuit::{
-        ArithCircuit, Circuit, EvalCircuit, GateCircuit, HighPrecisionLutCircuit, LutCircuit,
+        self, AdderArchitecture, ArithCircuit, Circuit, CircuitError, EvalCircuit, GateCircuit,
+        HighPrecisionLutCircuit, KeyPair, KeySet, LutCircuit, Mismatch,
     },
+    gates::{Gate, GateType},
     verilog_parser, PtxtType,
 };
 use itertools::Itertools;
-use std::{collections::HashMap, vec};
+use std::{
+    collections::{HashMap, HashSet},
+    thread,
+    time::Duration,
+    vec,
+};
 use tfhe::{
     boolean::gen_keys,
     generate_keys,
@@ -547,3 +557,2023 @@ fn encrypted_chi_squared_arithmetic() {
         };
     }
 }
+
+#[test]
+fn critical_bootstraps_of_wide_circuit() {
+    // Sixteen independent ANDs followed by a NOT each: plenty of bootstraps,
+    // but only one of them on any path.
+    let mut gates = HashSet::new();
+    let mut input_wires = vec![];
+    let mut output_wires = vec![];
+    for i in 0..16 {
+        input_wires.push(format!("a[{}]", i));
+        input_wires.push(format!("b[{}]", i));
+        output_wires.push(format!("out[{}]", i));
+        gates.insert(Gate::new(
+            format!("and_{}", i),
+            GateType::And,
+            vec![format!("a[{}]", i), format!("b[{}]", i)],
+            None,
+            format!("t[{}]", i),
+            0,
+        ));
+        gates.insert(Gate::new(
+            format!("not_{}", i),
+            GateType::Not,
+            vec![format!("t[{}]", i)],
+            None,
+            format!("out[{}]", i),
+            0,
+        ));
+    }
+
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    assert_eq!(circuit.count_bootstraps(), 16);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    assert_eq!(circuit.count_bootstraps(), 16);
+    assert_eq!(circuit.count_critical_bootstraps(), 1);
+}
+
+#[test]
+fn toggled_wires_after_single_input_change() {
+    let gates = HashSet::from([
+        Gate::new(
+            "g0".to_string(),
+            GateType::And,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+            "t".to_string(),
+            0,
+        ),
+        Gate::new(
+            "g1".to_string(),
+            GateType::Not,
+            vec!["t".to_string()],
+            None,
+            "out0".to_string(),
+            0,
+        ),
+        Gate::new(
+            "g2".to_string(),
+            GateType::Buf,
+            vec!["c".to_string()],
+            None,
+            "out1".to_string(),
+            0,
+        ),
+    ]);
+    let wire_set = HashSet::from(["t".to_string(), "out0".to_string(), "out1".to_string()]);
+    let input_wires = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    let output_wires = vec!["out0".to_string(), "out1".to_string()];
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let mut inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(false)),
+        ("b".to_string(), PtxtType::Bool(true)),
+        ("c".to_string(), PtxtType::Bool(true)),
+    ]);
+    let wire_map = circuit.initialize_wire_map(&wire_set, &inputs, "bool");
+    let previous = circuit.evaluate(&wire_map);
+
+    inputs.insert("a".to_string(), PtxtType::Bool(true));
+    let wire_map = circuit.initialize_wire_map(&wire_set, &inputs, "bool");
+    let current = circuit.evaluate(&wire_map);
+
+    assert_eq!(
+        circuit::toggled_wires(&previous, &current),
+        vec!["a", "out0", "t"]
+    );
+}
+
+#[test]
+fn max_depth_rejects_deep_circuit() {
+    // A chain of ten inverters: a -> w[0] -> ... -> w[9]
+    let mut gates = HashSet::new();
+    for i in 0..10 {
+        let input = if i == 0 {
+            "a".to_string()
+        } else {
+            format!("w[{}]", i - 1)
+        };
+        gates.insert(Gate::new(
+            format!("not_{}", i),
+            GateType::Not,
+            vec![input],
+            None,
+            format!("w[{}]", i),
+            0,
+        ));
+    }
+
+    let input_wires = vec!["a".to_string()];
+    let output_wires = vec!["w[9]".to_string()];
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    assert_eq!(circuit.depth(), 10);
+    assert!(matches!(
+        circuit.check_max_depth(5),
+        Err(CircuitError::MaxDepthExceeded {
+            depth: 10,
+            max_depth: 5
+        })
+    ));
+    assert!(circuit.check_max_depth(10).is_ok());
+}
+
+#[test]
+fn width_check_reports_uncast_operands() {
+    let check = |body: &str| {
+        let netlist = Netlist::write(
+            "helm_mixed_widths.v",
+            &format!(
+                "module mixed(a, b, y);\n\
+                 input [7:0] a;\n\
+                 input [15:0] b;\n\
+                 output [15:0] y;\n\
+                 {}\n\
+                 endmodule\n",
+                body
+            ),
+            true,
+        );
+        let circuit = netlist.circuit();
+        circuit.check_widths(&verilog_parser::read_wire_widths(&netlist.file_name))
+    };
+
+    let error = check("add g0(a, a, t);\nadd g1(t, b, y);").unwrap_err();
+    assert!(matches!(
+        &error,
+        CircuitError::WidthMismatch {
+            gate,
+            wire,
+            width: 8,
+            other_wire,
+            other_width: 16,
+        } if gate == "g1" && wire == "t" && other_wire == "b"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Gate g1 mixes t (8 bits) with b (16 bits)"
+    );
+
+    assert!(check("wire [15:0] a16;\ncopy c0(a, a16);\nadd g0(a16, b, y);").is_ok());
+}
+
+#[test]
+fn key_set_follows_the_gate_types() {
+    let gate = |name: &str, gate_type, lut_const| {
+        Gate::new(
+            name.to_string(),
+            gate_type,
+            vec!["a".to_string(), "b".to_string()],
+            lut_const,
+            format!("{}_out", name),
+            0,
+        )
+    };
+    let xor = gate("xor_0", GateType::Xor, None);
+    let lut = gate("lut_0", GateType::Lut, Some(vec![0, 1, 1, 0]));
+    let add = gate("add_0", GateType::Add, None);
+
+    // Only the gates that need them select the shortint or integer keys.
+    assert_eq!(KeySet::from_gates([&xor]), KeySet::Boolean);
+    assert_eq!(KeySet::from_gates([&xor, &lut]), KeySet::ShortInt);
+    assert_eq!(KeySet::from_gates([&xor, &add]), KeySet::Integer);
+    assert_eq!(KeySet::from_gates([&lut, &add]), KeySet::Integer);
+}
+
+#[test]
+fn full_adder_matches_reference_model() {
+    let gate = |name: &str, gate_type, inputs: &[&str], output: &str| {
+        Gate::new(
+            name.to_string(),
+            gate_type,
+            inputs.iter().map(|input| input.to_string()).collect(),
+            None,
+            output.to_string(),
+            0,
+        )
+    };
+    let gates = HashSet::from([
+        gate("g0", GateType::Xor, &["a", "b"], "p"),
+        gate("g1", GateType::Xor, &["p", "cin"], "sum"),
+        gate("g2", GateType::And, &["a", "b"], "g"),
+        gate("g3", GateType::And, &["p", "cin"], "t"),
+        gate("g4", GateType::Or, &["g", "t"], "cout"),
+    ]);
+    let input_wires = vec!["a".to_string(), "b".to_string(), "cin".to_string()];
+    let output_wires = vec!["sum".to_string(), "cout".to_string()];
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let vectors = (0..8)
+        .map(|i| {
+            HashMap::from([
+                ("a".to_string(), PtxtType::Bool(i & 1 == 1)),
+                ("b".to_string(), PtxtType::Bool(i & 2 == 2)),
+                ("cin".to_string(), PtxtType::Bool(i & 4 == 4)),
+            ])
+        })
+        .collect::<Vec<_>>();
+    let bit = |inputs: &HashMap<String, PtxtType>, wire: &str| match inputs[wire] {
+        PtxtType::Bool(value) => value as u8,
+        _ => unreachable!(),
+    };
+    let adder = |inputs: &HashMap<String, PtxtType>| {
+        let total = bit(inputs, "a") + bit(inputs, "b") + bit(inputs, "cin");
+        HashMap::from([
+            ("sum".to_string(), PtxtType::Bool(total & 1 == 1)),
+            ("cout".to_string(), PtxtType::Bool(total >= 2)),
+        ])
+    };
+    assert!(circuit.verify_against(adder, &vectors).is_empty());
+
+    // A broken model that ignores the carry in.
+    let half_adder = |inputs: &HashMap<String, PtxtType>| {
+        let total = bit(inputs, "a") + bit(inputs, "b");
+        HashMap::from([("cout".to_string(), PtxtType::Bool(total >= 2))])
+    };
+    let mismatches = circuit.verify_against(half_adder, &vectors);
+    assert_eq!(
+        mismatches,
+        vec![
+            Mismatch {
+                vector: 5,
+                wire: "cout".to_string(),
+                expected: PtxtType::Bool(false),
+                actual: PtxtType::Bool(true),
+            },
+            Mismatch {
+                vector: 6,
+                wire: "cout".to_string(),
+                expected: PtxtType::Bool(false),
+                actual: PtxtType::Bool(true),
+            },
+        ]
+    );
+}
+
+#[test]
+fn fanout_of_net_driving_three_gates() {
+    // "a" drives an AND, an OR and a NOT; "b" drives the AND and the OR.
+    let gates = HashSet::from([
+        Gate::new(
+            "g0".to_string(),
+            GateType::And,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+            "x".to_string(),
+            0,
+        ),
+        Gate::new(
+            "g1".to_string(),
+            GateType::Or,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+            "y".to_string(),
+            0,
+        ),
+        Gate::new(
+            "g2".to_string(),
+            GateType::Not,
+            vec!["a".to_string()],
+            None,
+            "z".to_string(),
+            0,
+        ),
+    ]);
+    let input_wires = vec!["a".to_string(), "b".to_string()];
+    let output_wires = vec!["x".to_string(), "y".to_string(), "z".to_string()];
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    assert_eq!(circuit.fanout("a"), 3);
+    assert_eq!(circuit.fanout("b"), 2);
+    assert_eq!(circuit.fanout("x"), 0);
+    let fanouts = circuit.fanout_map();
+    assert_eq!(fanouts["a"], 3);
+    assert_eq!(fanouts["b"], 2);
+    assert!(!fanouts.contains_key("z"));
+}
+
+#[test]
+fn circuit_cut_at_registers_evaluates_combinational_cloud() {
+    // A toggle flip-flop: q_next = q ^ en, with y = q & en.
+    let gates = HashSet::from([
+        Gate::new(
+            "g0".to_string(),
+            GateType::Xor,
+            vec!["q".to_string(), "en".to_string()],
+            None,
+            "q_next".to_string(),
+            0,
+        ),
+        Gate::new(
+            "g1".to_string(),
+            GateType::And,
+            vec!["q".to_string(), "en".to_string()],
+            None,
+            "y".to_string(),
+            0,
+        ),
+        Gate::new(
+            "dff0".to_string(),
+            GateType::Dff,
+            vec!["q_next".to_string()],
+            None,
+            "q".to_string(),
+            0,
+        ),
+    ]);
+    let mut input_wires = vec!["en".to_string(), "q".to_string()];
+    let mut output_wires = vec!["y".to_string()];
+    let mut dff_outputs = vec!["q".to_string()];
+    let wire_set = HashSet::from(["q_next".to_string(), "y".to_string()]);
+
+    let gates =
+        circuit::cut_at_registers(gates, &mut input_wires, &mut output_wires, &mut dff_outputs);
+    assert_eq!(gates.len(), 2);
+    assert_eq!(input_wires, vec!["en".to_string(), "q".to_string()]);
+    assert_eq!(output_wires, vec!["y".to_string(), "q_next".to_string()]);
+    assert!(dff_outputs.is_empty());
+
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    for (q, en) in [(false, false), (false, true), (true, false), (true, true)] {
+        let user_inputs = HashMap::from([
+            ("q".to_string(), PtxtType::Bool(q)),
+            ("en".to_string(), PtxtType::Bool(en)),
+        ]);
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(wire_map["q_next"], PtxtType::Bool(q ^ en));
+        assert_eq!(wire_map["y"], PtxtType::Bool(q & en));
+    }
+}
+
+#[test]
+fn shared_constant_is_encrypted_once() {
+    // Ten ANDs, each reading its own copy of the constant 1.
+    let mut gates = HashSet::new();
+    let mut input_wires = vec![];
+    let mut output_wires = vec![];
+    for i in 0..10 {
+        input_wires.push(format!("a[{}]", i));
+        output_wires.push(format!("y[{}]", i));
+        gates.insert(Gate::new(
+            format!("one_{}", i),
+            GateType::ConstOne,
+            vec![],
+            None,
+            format!("one[{}]", i),
+            0,
+        ));
+        gates.insert(Gate::new(
+            format!("and_{}", i),
+            GateType::And,
+            vec![format!("a[{}]", i), format!("one[{}]", i)],
+            None,
+            format!("y[{}]", i),
+            0,
+        ));
+    }
+    let wire_set = (0..10)
+        .flat_map(|i| [format!("one[{}]", i), format!("y[{}]", i)])
+        .collect::<HashSet<_>>();
+
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.optimize();
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    assert_eq!(circuit.gate_count(), 11);
+
+    let (client_key, server_key) = gen_keys();
+    let input_wire_map = (0..10)
+        .map(|i| (format!("a[{}]", i), PtxtType::Bool(i % 2 == 0)))
+        .collect::<HashMap<_, _>>();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
+    assert_eq!(circuit.constant_encryption_count(), 0);
+
+    let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+    assert_eq!(circuit.constant_encryption_count(), 1);
+    // The constant keeps its ciphertext in the following cycles.
+    let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 2, "bool");
+    assert_eq!(circuit.constant_encryption_count(), 1);
+
+    let outputs = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false);
+    for i in 0..10 {
+        assert_eq!(outputs[&format!("y[{}]", i)], PtxtType::Bool(i % 2 == 0));
+    }
+}
+
+#[test]
+fn coverage_of_constant_and_toggling_vectors() {
+    let gates = HashSet::from([
+        Gate::new(
+            "g0".to_string(),
+            GateType::And,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+            "x".to_string(),
+            0,
+        ),
+        Gate::new(
+            "g1".to_string(),
+            GateType::Xnor,
+            vec!["x".to_string(), "b".to_string()],
+            None,
+            "y".to_string(),
+            0,
+        ),
+    ]);
+    let input_wires = vec!["a".to_string(), "b".to_string()];
+    let output_wires = vec!["y".to_string()];
+    let wire_set = HashSet::from(["x".to_string(), "y".to_string()]);
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let mut snapshots = vec![];
+    for value in [false, true] {
+        let user_inputs = HashMap::from([
+            ("a".to_string(), PtxtType::Bool(value)),
+            ("b".to_string(), PtxtType::Bool(value)),
+        ]);
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        snapshots.push(circuit.evaluate(&wire_map));
+    }
+
+    // A single vector can't toggle anything.
+    let coverage = circuit.coverage(&snapshots[..1]);
+    assert!(coverage.toggled.is_empty());
+    assert_eq!(coverage.stuck, vec!["g0".to_string(), "g1".to_string()]);
+    assert_eq!(coverage.percentage(), 0.0);
+
+    // x goes 0 -> 1 but y = xnor(x, b) stays at 1.
+    let coverage = circuit.coverage(&snapshots);
+    assert_eq!(coverage.toggled, vec!["g0".to_string()]);
+    assert_eq!(coverage.stuck, vec!["g1".to_string()]);
+    assert_eq!(coverage.percentage(), 50.0);
+
+    // Adding a vector where only b is set toggles y too.
+    let user_inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(false)),
+        ("b".to_string(), PtxtType::Bool(true)),
+    ]);
+    let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+    snapshots.push(circuit.evaluate(&wire_map));
+    let coverage = circuit.coverage(&snapshots);
+    assert!(coverage.stuck.is_empty());
+    assert_eq!(coverage.percentage(), 100.0);
+}
+
+#[test]
+fn watchdog_trips_on_slow_gate() {
+    // A mock backend whose gate evaluation hangs far past the time limit.
+    let slow_gate = || {
+        thread::sleep(Duration::from_secs(5));
+        true
+    };
+    let result = circuit::evaluate_with_time_limit("and_3", Duration::from_millis(50), slow_gate);
+    match result {
+        Err(CircuitError::GateTimeout { gate, time_limit }) => {
+            assert_eq!(gate, "and_3");
+            assert_eq!(time_limit, Duration::from_millis(50));
+        }
+        _ => panic!("The watchdog did not trip"),
+    }
+
+    let fast_gate = || true;
+    assert!(matches!(
+        circuit::evaluate_with_time_limit("and_4", Duration::from_secs(5), fast_gate),
+        Ok(true)
+    ));
+}
+
+/// An AND of the inputs a and b, levelled and with `time_limit` per gate.
+fn and_circuit_with_time_limit<'a>(
+    wires: &'a (Vec<String>, Vec<String>, Vec<String>),
+    time_limit: Duration,
+) -> Circuit<'a> {
+    let gates = HashSet::from([Gate::new(
+        "and_0".to_string(),
+        GateType::And,
+        vec!["a".to_string(), "b".to_string()],
+        None,
+        "y".to_string(),
+        0,
+    )]);
+    let (inputs, outputs, dff_outputs) = wires;
+    let mut circuit = Circuit::new(gates, inputs, outputs, dff_outputs);
+    circuit.set_time_limit_per_gate(time_limit);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    circuit
+}
+
+fn and_circuit_wires() -> (Vec<String>, Vec<String>, Vec<String>) {
+    (
+        vec!["a".to_string(), "b".to_string()],
+        vec!["y".to_string()],
+        vec![],
+    )
+}
+
+#[test]
+fn gate_circuit_evaluates_within_time_limit() {
+    let wires = and_circuit_wires();
+    let circuit = and_circuit_with_time_limit(&wires, Duration::from_secs(60));
+    let wire_set = HashSet::from(["a".to_string(), "b".to_string(), "y".to_string()]);
+    let (client_key, server_key) = gen_keys();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit);
+    let user_inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(true)),
+    ]);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &user_inputs);
+    let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+    let outputs = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false);
+    assert_eq!(outputs["y"], PtxtType::Bool(true));
+}
+
+#[test]
+#[should_panic(expected = "did not finish within")]
+fn gate_circuit_aborts_past_time_limit() {
+    // No bootstrap finishes within a nanosecond.
+    let wires = and_circuit_wires();
+    let circuit = and_circuit_with_time_limit(&wires, Duration::from_nanos(1));
+    let wire_set = HashSet::from(["a".to_string(), "b".to_string(), "y".to_string()]);
+    let (client_key, server_key) = gen_keys();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit);
+    let user_inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(false)),
+    ]);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &user_inputs);
+    EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+}
+
+#[test]
+#[should_panic(expected = "not supported in arithmetic mode")]
+fn arithmetic_circuit_rejects_time_limit() {
+    let wires = and_circuit_wires();
+    let circuit = and_circuit_with_time_limit(&wires, Duration::from_secs(60));
+    let (client_key, server_key) = match KeySet::Integer.generate() {
+        KeyPair::Integer(client_key, server_key) => (client_key, server_key),
+        _ => unreachable!(),
+    };
+    ArithCircuit::new(client_key, server_key, circuit);
+}
+
+#[test]
+fn lazy_encryption_skips_unused_inputs() {
+    let gates = HashSet::from([Gate::new(
+        "and_0".to_string(),
+        GateType::And,
+        vec!["a".to_string(), "b".to_string()],
+        None,
+        "y".to_string(),
+        0,
+    )]);
+    let input_wires = vec!["a".to_string(), "b".to_string(), "unused".to_string()];
+    let output_wires = vec!["y".to_string()];
+    let empty = vec![];
+    let wire_set = HashSet::from(["y".to_string()]);
+    let input_wire_map = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(true)),
+        ("unused".to_string(), PtxtType::Bool(false)),
+    ]);
+    let (client_key, server_key) = gen_keys();
+
+    let mut circuit = Circuit::new(gates.clone(), &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let mut eager = GateCircuit::new(client_key.clone(), server_key.clone(), circuit);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut eager, &wire_set, &input_wire_map);
+    let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut eager, &enc_wire_map, 1, "bool");
+    let eager_outputs = EvalCircuit::decrypt_outputs(&mut eager, &enc_wire_map, false);
+    assert_eq!(eager.input_encryption_count(), 3);
+
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let mut lazy = GateCircuit::new(client_key, server_key, circuit);
+    let enc_wire_map = lazy.evaluate_encrypted_lazily(&wire_set, &input_wire_map, 1);
+    let lazy_outputs = EvalCircuit::decrypt_outputs(&mut lazy, &enc_wire_map, false);
+    assert_eq!(lazy.input_encryption_count(), 2);
+    assert!(!enc_wire_map.contains_key("unused"));
+
+    assert_eq!(lazy_outputs, eager_outputs);
+    assert_eq!(lazy_outputs["y"], PtxtType::Bool(true));
+}
+
+#[test]
+#[should_panic(expected = "Bootstrap budget of 2 exceeded after 2 bootstraps, at level 3 of 3")]
+fn bootstrap_budget_aborts_partway() {
+    // A chain of three ANDs, one bootstrap per level.
+    let gates = (0..3)
+        .map(|i| {
+            Gate::new(
+                format!("and_{}", i),
+                GateType::And,
+                vec![format!("t[{}]", i), "a".to_string()],
+                None,
+                format!("t[{}]", i + 1),
+                0,
+            )
+        })
+        .collect::<HashSet<_>>();
+    let input_wires = vec!["a".to_string(), "t[0]".to_string()];
+    let output_wires = vec!["t[3]".to_string()];
+    let empty = vec![];
+    let wire_set = (1..4).map(|i| format!("t[{}]", i)).collect::<HashSet<_>>();
+
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.set_bootstrap_budget(2);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let (client_key, server_key) = gen_keys();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &HashMap::new());
+    EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+}
+
+#[test]
+fn display_summarizes_circuit() {
+    let gates = HashSet::from([
+        Gate::new(
+            "and_0".to_string(),
+            GateType::And,
+            vec!["a".to_string(), "q".to_string()],
+            None,
+            "t".to_string(),
+            0,
+        ),
+        Gate::new(
+            "and_1".to_string(),
+            GateType::And,
+            vec!["t".to_string(), "b".to_string()],
+            None,
+            "y".to_string(),
+            0,
+        ),
+        Gate::new(
+            "xor_0".to_string(),
+            GateType::Xor,
+            vec!["t".to_string(), "y".to_string()],
+            None,
+            "d".to_string(),
+            0,
+        ),
+        Gate::new(
+            "dff_0".to_string(),
+            GateType::Dff,
+            vec!["d".to_string()],
+            None,
+            "q".to_string(),
+            0,
+        ),
+    ]);
+    let input_wires = vec!["a".to_string(), "b".to_string(), "q".to_string()];
+    let output_wires = vec!["y".to_string()];
+    let dff_outputs = vec!["q".to_string()];
+
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &dff_outputs);
+    circuit.set_module_name("toggler");
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    assert_eq!(
+        circuit.to_string(),
+        "Circuit toggler\n  \
+         Inputs: 2\n  \
+         Outputs: 1\n  \
+         Registers: 1\n  \
+         Gates: 4\n    \
+         And: 2\n    \
+         Dff: 1\n    \
+         Xor: 1"
+    );
+}
+
+#[test]
+fn identical_registers_collapse_to_one() {
+    let gate = |name: &str, gate_type, inputs: &[&str], output: &str| {
+        Gate::new(
+            name.to_string(),
+            gate_type,
+            inputs.iter().map(|input| input.to_string()).collect(),
+            None,
+            output.to_string(),
+            0,
+        )
+    };
+    // Two copies of the same toggle register, one of them read by an AND.
+    let gates = HashSet::from([
+        gate("xor_0", GateType::Xor, &["a", "q0"], "d"),
+        gate("dff_0", GateType::Dff, &["d"], "q0"),
+        gate("dff_1", GateType::Dff, &["d"], "q1"),
+        gate("and_0", GateType::And, &["q1", "a"], "z"),
+    ]);
+    let input_wires = vec!["a".to_string(), "q0".to_string(), "q1".to_string()];
+    let output_wires = vec!["q0".to_string(), "q1".to_string(), "z".to_string()];
+    let dff_outputs = vec!["q0".to_string(), "q1".to_string()];
+    let wire_set = ["d", "q0", "q1", "z"]
+        .iter()
+        .map(|wire| wire.to_string())
+        .collect::<HashSet<_>>();
+    let user_inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("q0".to_string(), PtxtType::Bool(false)),
+        ("q1".to_string(), PtxtType::Bool(false)),
+    ]);
+
+    let mut reference = Circuit::new(gates.clone(), &input_wires, &output_wires, &dff_outputs);
+    reference.sort_circuit();
+    reference.compute_levels();
+    let mut merged = Circuit::new(gates, &input_wires, &output_wires, &dff_outputs);
+    merged.optimize();
+    merged.sort_circuit();
+    merged.compute_levels();
+    assert_eq!(reference.gate_count(), 4);
+    assert_eq!(merged.gate_count(), 3);
+
+    let mut reference_map = reference.initialize_wire_map(&wire_set, &user_inputs, "bool");
+    let mut merged_map = merged.initialize_wire_map(&wire_set, &user_inputs, "bool");
+    for cycle in 0..4 {
+        reference_map = reference.evaluate(&reference_map);
+        merged_map = merged.evaluate(&merged_map);
+        for wire in output_wires.iter() {
+            assert_eq!(
+                merged_map[wire], reference_map[wire],
+                "{} at cycle {}",
+                wire, cycle
+            );
+        }
+        assert_eq!(merged_map["q1"], PtxtType::Bool(cycle % 2 == 0));
+    }
+}
+
+#[test]
+fn adder_agrees_across_widths() {
+    let gates = HashSet::from([
+        Gate::new(
+            "add_0".to_string(),
+            GateType::Add,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+            "s".to_string(),
+            0,
+        ),
+        Gate::new(
+            "sub_0".to_string(),
+            GateType::Sub,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+            "d".to_string(),
+            0,
+        ),
+        Gate::new(
+            "shr_0".to_string(),
+            GateType::Shr,
+            vec!["d".to_string(), "c".to_string()],
+            None,
+            "h".to_string(),
+            0,
+        ),
+    ]);
+    let input_wires = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    let output_wires = vec!["s".to_string(), "h".to_string()];
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let widths = ["u8", "u16", "u32", "u64", "u128", "u256"];
+
+    // 3 + 4 and 4 - 3 fit in every width.
+    let inputs = HashMap::from([
+        ("a".to_string(), PtxtType::U8(4)),
+        ("b".to_string(), PtxtType::U8(3)),
+        ("c".to_string(), PtxtType::U8(4)),
+    ]);
+    assert!(circuit.sweep_widths(&inputs, &widths).is_empty());
+
+    // 3 - 4 wraps, and shifting the wrapped value right depends on the width.
+    let inputs = HashMap::from([
+        ("a".to_string(), PtxtType::U8(3)),
+        ("b".to_string(), PtxtType::U8(4)),
+        ("c".to_string(), PtxtType::U8(4)),
+    ]);
+    let mismatches = circuit.sweep_widths(&inputs, &widths);
+    assert_eq!(mismatches.len(), widths.len() - 1);
+    assert!(mismatches.iter().all(|mismatch| mismatch.wire == "h"));
+    assert_eq!(mismatches[0].width, "u16");
+    assert_eq!(mismatches[0].expected, 0x0F);
+    assert_eq!(mismatches[0].actual, 0xFF);
+}
+
+#[test]
+fn partitions_reassemble_to_same_outputs() {
+    let netlist = Netlist::write(
+        "helm_partition.v",
+        "module adder(a, b, cin, sum, cout);\n\
+         input [1:0] a, b;\n\
+         input cin;\n\
+         output [1:0] sum;\n\
+         output cout;\n\
+         xor g0(a[0], b[0], p0);\n\
+         xor g1(p0, cin, sum[0]);\n\
+         and g2(a[0], b[0], g_0);\n\
+         and g3(p0, cin, t0);\n\
+         or g4(g_0, t0, c1);\n\
+         xor g5(a[1], b[1], p1);\n\
+         xor g6(p1, c1, sum[1]);\n\
+         and g7(a[1], b[1], g_1);\n\
+         and g8(p1, c1, t1);\n\
+         or g9(g_1, t1, cout);\n\
+         endmodule\n",
+        false,
+    );
+    let mut circuit = netlist.circuit();
+
+    let (partitions, cross_wires) = circuit.partition(3);
+    assert_eq!(partitions.len(), 3);
+    assert!(partitions
+        .iter()
+        .all(|partition| !partition.gates.is_empty()));
+    assert_eq!(
+        partitions
+            .iter()
+            .map(|partition| partition.gates.len())
+            .sum::<usize>(),
+        10
+    );
+    assert!(cross_wires
+        .iter()
+        .all(|cross_wire| cross_wire.from < cross_wire.to));
+
+    for value in 0..32u32 {
+        let inputs = netlist
+            .input_wires
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| (wire.to_string(), PtxtType::Bool((value >> i) & 1 == 1)))
+            .collect::<HashMap<_, _>>();
+        let mut wire_map = netlist
+            .wire_set
+            .iter()
+            .map(|wire| (wire.to_string(), PtxtType::None))
+            .collect::<HashMap<_, _>>();
+        wire_map.extend(inputs.clone());
+        let expected = circuit.evaluate(&wire_map);
+
+        // Write every part, read it back and evaluate it on the values of
+        // the primary inputs and of the parts before it.
+        let mut reassembled = inputs;
+        for (part, partition) in partitions.iter().enumerate() {
+            let part_file = common::temp_path(&format!("helm_partition.part{}.v", part));
+            let part_file = part_file.to_str().unwrap();
+            verilog_parser::write_verilog_file(
+                part_file,
+                &format!("adder_part{}", part),
+                &partition.gates,
+                &partition.input_wires,
+                &partition.output_wires,
+            );
+            let part_netlist = Netlist::read(part_file, false);
+            assert_eq!(part_netlist.input_wires, partition.input_wires);
+            let mut part_circuit = part_netlist.circuit();
+
+            let mut part_wire_map = part_netlist
+                .wire_set
+                .iter()
+                .map(|wire| (wire.to_string(), PtxtType::None))
+                .collect::<HashMap<_, _>>();
+            for wire in part_netlist.input_wires.iter() {
+                part_wire_map.insert(wire.to_string(), reassembled[wire]);
+            }
+            let part_outputs = part_circuit.evaluate(&part_wire_map);
+            for wire in part_netlist.output_wires.iter() {
+                reassembled.insert(wire.to_string(), part_outputs[wire]);
+            }
+        }
+
+        for wire in netlist.output_wires.iter() {
+            assert_eq!(reassembled[wire], expected[wire], "{} for {}", wire, value);
+        }
+    }
+}
+
+#[test]
+fn partition_keeps_independent_chains_apart() {
+    let netlist = Netlist::write(
+        "helm_partition_chains.v",
+        "module chains(a, b, y, z);\n\
+         input a, b;\n\
+         output y, z;\n\
+         and g0(a, b, s0);\n\
+         and g1(s0, b, s1);\n\
+         and g2(s1, b, y);\n\
+         or h0(a, b, t0);\n\
+         or h1(t0, a, t1);\n\
+         or h2(t1, a, z);\n\
+         endmodule\n",
+        false,
+    );
+    let circuit = netlist.circuit();
+
+    // Cutting the levels in two would cut both chains, the minimal cut
+    // gives each chain its own part.
+    let (partitions, cross_wires) = circuit.partition(2);
+    assert!(cross_wires.is_empty(), "{:?}", cross_wires);
+    for partition in partitions.iter() {
+        assert_eq!(partition.gates.len(), 3);
+        assert_eq!(
+            partition
+                .gates
+                .iter()
+                .map(|gate| gate.get_gate_type())
+                .unique()
+                .count(),
+            1
+        );
+    }
+}
+
+#[test]
+fn extracted_cone_evaluates_like_the_circuit() {
+    let netlist = Netlist::write(
+        "helm_cone.v",
+        "module m(a, b, c, d, y, w);\n\
+         input a, b, c, d;\n\
+         output y, w;\n\
+         and g0(a, b, t0);\n\
+         xor g1(t0, c, t1);\n\
+         or g2(d, a, t2);\n\
+         nand g3(t1, t2, y);\n\
+         nor g4(c, d, z);\n\
+         dff r0(z, q);\n\
+         and g5(q, t1, w);\n\
+         endmodule\n",
+        false,
+    );
+    let mut circuit = netlist.circuit();
+
+    // The register cuts the cone, so q is an input and g4 is left out.
+    let cone = circuit.cone("w");
+    assert_eq!(cone.input_wires, vec!["a", "b", "c", "q"]);
+    assert_eq!(
+        cone.gates
+            .iter()
+            .map(|gate| gate.get_gate_name())
+            .sorted()
+            .collect::<Vec<_>>(),
+        vec!["g0", "g1", "g5"]
+    );
+
+    let cone_file = common::temp_path("helm_cone_w.v");
+    let cone_file = cone_file.to_str().unwrap();
+    verilog_parser::write_verilog_file(
+        cone_file,
+        "m_cone",
+        &cone.gates,
+        &cone.input_wires,
+        &cone.output_wires,
+    );
+    let cone_netlist = Netlist::read(cone_file, false);
+    assert_eq!(cone_netlist.input_wires, cone.input_wires);
+    assert_eq!(cone_netlist.output_wires, vec!["w"]);
+    let mut cone_circuit = cone_netlist.circuit();
+
+    for value in 0..32u32 {
+        let mut inputs = ["a", "b", "c", "d", "q"]
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| (wire.to_string(), PtxtType::Bool((value >> i) & 1 == 1)))
+            .collect::<HashMap<_, _>>();
+        let mut wire_map = circuit.initialize_wire_map(&netlist.wire_set, &inputs, "bool");
+        wire_map.insert("q".to_string(), inputs["q"]);
+        let expected = circuit.evaluate(&wire_map);
+
+        inputs.remove("d");
+        let cone_wire_map =
+            cone_circuit.initialize_wire_map(&cone_netlist.wire_set, &inputs, "bool");
+        let actual = cone_circuit.evaluate(&cone_wire_map);
+        assert_eq!(actual["w"], expected["w"], "for {:05b}", value);
+    }
+}
+
+#[test]
+fn schedule_must_respect_dependencies() {
+    let netlist = Netlist::write(
+        "helm_schedule.v",
+        "module m(a, b, c, y, z);\n\
+         input a, b, c;\n\
+         output y, z;\n\
+         and g0(a, b, t0);\n\
+         or g1(b, c, t1);\n\
+         xor g2(t0, t1, y);\n\
+         nand g3(t1, c, z);\n\
+         endmodule\n",
+        false,
+    );
+    let new_circuit = || netlist.circuit();
+    let schedule = |gates: &str| {
+        verilog_parser::read_schedule(&common::write_temp_file("helm_schedule.txt", gates))
+    };
+
+    let mut scheduled = new_circuit();
+    scheduled
+        .set_schedule(&schedule("# g1 first\ng1 g3\ng0\ng2\n"))
+        .unwrap();
+    assert_eq!(scheduled.depth(), 4);
+    let mut levelled = new_circuit();
+    for value in 0..8u32 {
+        let inputs = ["a", "b", "c"]
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| (wire.to_string(), PtxtType::Bool((value >> i) & 1 == 1)))
+            .collect::<HashMap<_, _>>();
+        let wire_map = levelled.initialize_wire_map(&netlist.wire_set, &inputs, "bool");
+        let expected = levelled.evaluate(&wire_map);
+        let actual = scheduled.evaluate(&wire_map);
+        for wire in ["y", "z"] {
+            assert_eq!(actual[wire], expected[wire], "{} for {:03b}", wire, value);
+        }
+    }
+
+    let rejection = |gates: &str| match new_circuit().set_schedule(&schedule(gates)) {
+        Err(CircuitError::InvalidSchedule { gate, reason }) => (gate, reason),
+        result => panic!("{:?} is not an invalid schedule", result),
+    };
+    assert_eq!(
+        rejection("g0 g2 g1 g3"),
+        (
+            "g2".to_string(),
+            "it reads t1 before it is computed".to_string()
+        )
+    );
+    assert_eq!(
+        rejection("g0 g1 g2"),
+        (
+            "g3".to_string(),
+            "it is missing from the schedule".to_string()
+        )
+    );
+    assert_eq!(
+        rejection("g0 g1 g1 g2 g3"),
+        ("g1".to_string(), "it is listed twice".to_string())
+    );
+    assert_eq!(
+        rejection("g0 g1 g4 g2 g3"),
+        (
+            "g4".to_string(),
+            "it is not a gate of the circuit".to_string()
+        )
+    );
+}
+
+#[test]
+fn bit_blasted_adder_matches_integer_backend() {
+    let netlist = Netlist::write(
+        "helm_bit_blast.v",
+        "module alu(a, b, s, d, p, l, r);\n\
+         input a, b;\n\
+         output s, d, p, l, r;\n\
+         add g0(a, b, s);\n\
+         sub g1(a, b, d);\n\
+         mult g2(a, b, p);\n\
+         shl g3(a, b, l);\n\
+         shr g4(a, b, r);\n\
+         endmodule\n",
+        true,
+    );
+    let mut circuit = netlist.circuit();
+
+    let bit_blasted = circuit.bit_blast(8, AdderArchitecture::Ripple);
+    let mut circuit_bits = Circuit::new(
+        bit_blasted.gates.clone(),
+        &bit_blasted.input_wires,
+        &bit_blasted.output_wires,
+        &bit_blasted.dff_outputs,
+    );
+    circuit_bits.sort_circuit();
+    circuit_bits.compute_levels();
+
+    let inputs = |a: u8, b: u8| {
+        HashMap::from([
+            ("a".to_string(), PtxtType::U8(a)),
+            ("b".to_string(), PtxtType::U8(b)),
+        ])
+    };
+    for (a, b) in [(0, 0), (1, 255), (200, 77), (255, 255), (13, 3), (128, 9)] {
+        let wire_map = circuit.initialize_wire_map(&netlist.wire_set, &inputs(a, b), "u8");
+        let expected = circuit.evaluate(&wire_map);
+        let bit_map = circuit_bits.initialize_wire_map(
+            &bit_blasted.wire_set,
+            &bit_blasted.split_inputs(&inputs(a, b)),
+            "bool",
+        );
+        let actual = bit_blasted.join_outputs(&circuit_bits.evaluate(&bit_map), "u8");
+        for wire in netlist.output_wires.iter() {
+            assert_eq!(actual[wire], expected[wire], "{} for {} and {}", wire, a, b);
+        }
+    }
+
+    let (client_key, server_key) = match KeySet::Integer.generate() {
+        KeyPair::Integer(client_key, server_key) => (client_key, server_key),
+        _ => unreachable!(),
+    };
+    let mut circuit = ArithCircuit::new(client_key, server_key, circuit);
+    let enc_wire_map =
+        EvalCircuit::encrypt_inputs(&mut circuit, &netlist.wire_set, &inputs(200, 77));
+    let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "u8");
+    let integer_outputs = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false);
+
+    let (client_key, server_key) = gen_keys();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit_bits);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(
+        &mut circuit,
+        &bit_blasted.wire_set,
+        &bit_blasted.split_inputs(&inputs(200, 77)),
+    );
+    let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+    let output_bits = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false);
+    let boolean_outputs = bit_blasted.join_outputs(&output_bits, "u8");
+
+    assert!(circuit::compare_outputs(&integer_outputs, &boolean_outputs, false).is_empty());
+    assert_eq!(boolean_outputs["s"], PtxtType::U8(200u8.wrapping_add(77)));
+}
+
+#[test]
+fn adder_architectures_agree_and_trade_depth() {
+    let netlist = Netlist::write(
+        "helm_adder_architectures.v",
+        "module adder(a, b, s, d);\n\
+         input a, b;\n\
+         output s, d;\n\
+         add g0(a, b, s);\n\
+         sub g1(a, b, d);\n\
+         endmodule\n",
+        true,
+    );
+    let circuit = netlist.circuit();
+
+    let mut depths = HashMap::new();
+    for adder in [
+        AdderArchitecture::Ripple,
+        AdderArchitecture::CarryLookahead,
+        AdderArchitecture::CarrySelect,
+    ] {
+        let bit_blasted = circuit.bit_blast(8, adder);
+        let mut circuit_bits = Circuit::new(
+            bit_blasted.gates.clone(),
+            &bit_blasted.input_wires,
+            &bit_blasted.output_wires,
+            &bit_blasted.dff_outputs,
+        );
+        circuit_bits.sort_circuit();
+        circuit_bits.compute_levels();
+        depths.insert(adder, circuit_bits.depth());
+
+        for (a, b) in (0..=255u8)
+            .step_by(7)
+            .cartesian_product((0..=255u8).step_by(11))
+        {
+            let inputs = HashMap::from([
+                ("a".to_string(), PtxtType::U8(a)),
+                ("b".to_string(), PtxtType::U8(b)),
+            ]);
+            let bit_map = circuit_bits.initialize_wire_map(
+                &bit_blasted.wire_set,
+                &bit_blasted.split_inputs(&inputs),
+                "bool",
+            );
+            let outputs = bit_blasted.join_outputs(&circuit_bits.evaluate(&bit_map), "u8");
+            assert_eq!(outputs["s"], PtxtType::U8(a.wrapping_add(b)), "{:?}", adder);
+            assert_eq!(outputs["d"], PtxtType::U8(a.wrapping_sub(b)), "{:?}", adder);
+        }
+    }
+
+    assert!(depths[&AdderArchitecture::CarryLookahead] < depths[&AdderArchitecture::Ripple]);
+    assert!(depths[&AdderArchitecture::CarrySelect] < depths[&AdderArchitecture::Ripple]);
+}
+
+#[test]
+fn case_analysis_constant_folds_dependent_logic() {
+    let Netlist {
+        gates,
+        mut wire_set,
+        mut input_wires,
+        output_wires,
+        dff_outputs,
+        ..
+    } = Netlist::write(
+        "helm_case_analysis.v",
+        "module m(a, b, c, mode, y, z);\n\
+         input a, b, c, mode;\n\
+         output y, z;\n\
+         mux g0(a, b, mode, y);\n\
+         not g1(mode, n);\n\
+         xor g2(n, c, z);\n\
+         endmodule\n",
+        false,
+    );
+    let constraints = common::write_temp_file(
+        "helm_case_analysis.sdc",
+        "create_clock -period 10 [get_ports clk]\n\
+         set_case_analysis 0 [get_ports {mode}] # test mode off\n",
+    );
+
+    let constants = verilog_parser::read_constraints(&constraints);
+    assert_eq!(constants, HashMap::from([("mode".to_string(), false)]));
+    let gates = circuit::tie_inputs(gates, &mut input_wires, &mut wire_set, &constants);
+    assert_eq!(input_wires, vec!["a", "b", "c"]);
+
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &dff_outputs);
+    circuit.optimize();
+    circuit.sort_circuit();
+    assert!(circuit
+        .get_ordered_gates()
+        .iter()
+        .all(|gate| !matches!(gate.get_gate_type(), GateType::Mux | GateType::Not)));
+    circuit.compute_levels();
+
+    for value in 0..8u32 {
+        let inputs = input_wires
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| (wire.to_string(), PtxtType::Bool((value >> i) & 1 == 1)))
+            .collect::<HashMap<_, _>>();
+        let wire_map = circuit.initialize_wire_map(&wire_set, &inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(wire_map["y"], inputs["b"]);
+        assert_eq!(
+            wire_map["z"],
+            PtxtType::Bool(inputs["c"] == PtxtType::Bool(false))
+        );
+    }
+}
+
+#[test]
+fn simulate_plaintext_returns_internal_wires() {
+    // Full adder: s = a ^ b ^ cin, cout = (a & b) | (cin & (a ^ b)).
+    let gate = |name: &str, gate_type, inputs: [&str; 2], output: &str| {
+        Gate::new(
+            name.to_string(),
+            gate_type,
+            inputs.iter().map(|input| input.to_string()).collect(),
+            None,
+            output.to_string(),
+            0,
+        )
+    };
+    let gates = HashSet::from([
+        gate("g0", GateType::Xor, ["a", "b"], "p"),
+        gate("g1", GateType::Xor, ["p", "cin"], "s"),
+        gate("g2", GateType::And, ["a", "b"], "g"),
+        gate("g3", GateType::And, ["p", "cin"], "t"),
+        gate("g4", GateType::Or, ["g", "t"], "cout"),
+    ]);
+    let input_wires = vec!["a".to_string(), "b".to_string(), "cin".to_string()];
+    let output_wires = vec!["s".to_string(), "cout".to_string()];
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(false)),
+        ("cin".to_string(), PtxtType::Bool(true)),
+    ]);
+    let wire_map = circuit.simulate_plaintext(&inputs);
+    assert_eq!(wire_map.len(), 8);
+    assert_eq!(wire_map["p"], PtxtType::Bool(true));
+    assert_eq!(wire_map["g"], PtxtType::Bool(false));
+    assert_eq!(wire_map["t"], PtxtType::Bool(true));
+    assert_eq!(wire_map["s"], PtxtType::Bool(false));
+    assert_eq!(wire_map["cout"], PtxtType::Bool(true));
+}
+
+#[test]
+fn batched_vectors_match_separate_runs() {
+    let netlist = Netlist::write(
+        "helm_batch.v",
+        "module full_adder(a, b, cin, s, cout);\n\
+         input a, b, cin;\n\
+         output s, cout;\n\
+         xor g0(a, b, p);\n\
+         xor g1(p, cin, s);\n\
+         and g2(a, b, g);\n\
+         and g3(p, cin, t);\n\
+         or g4(g, t, cout);\n\
+         endmodule\n",
+        false,
+    );
+    let (client_key, server_key) = gen_keys();
+    let new_circuit =
+        || GateCircuit::new(client_key.clone(), server_key.clone(), netlist.circuit());
+
+    let vectors = [
+        [true, false, true],
+        [true, true, true],
+        [false, true, false],
+    ]
+    .iter()
+    .map(|bits| {
+        ["a", "b", "cin"]
+            .iter()
+            .zip(bits)
+            .map(|(wire, bit)| (wire.to_string(), PtxtType::Bool(*bit)))
+            .collect::<HashMap<_, _>>()
+    })
+    .collect::<Vec<_>>();
+    let batched = new_circuit().evaluate_batch(&netlist.wire_set, &vectors, 1);
+    assert_eq!(batched.len(), 3);
+
+    for (inputs, batched_outputs) in vectors.iter().zip(&batched) {
+        let mut circuit = new_circuit();
+        let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &netlist.wire_set, inputs);
+        let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+        let outputs = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false);
+        assert_eq!(*batched_outputs, outputs);
+    }
+    assert_eq!(batched[1]["s"], PtxtType::Bool(true));
+    assert_eq!(batched[1]["cout"], PtxtType::Bool(true));
+    assert_eq!(batched[2]["cout"], PtxtType::Bool(false));
+}
+
+#[test]
+fn register_ignoring_reset_is_flagged() {
+    let netlist = Netlist::write(
+        "helm_test_reset.v",
+        "module r(rst, x, q0, q1);\n\
+         input rst, x;\n\
+         output q0, q1;\n\
+         not g0(rst, nrst);\n\
+         and g1(nrst, x, d0);\n\
+         buf g2(q1, d1);\n\
+         dff r0(d0, q0);\n\
+         dff r1(d1, q1);\n\
+         endmodule\n",
+        false,
+    );
+    let circuit = netlist.circuit();
+
+    let inputs = HashMap::from([("x".to_string(), PtxtType::Bool(true))]);
+    let violations = circuit.test_reset("rst", &inputs, 4);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].register, "q1");
+    assert!(!violations[0].expected);
+    assert!(violations[0].actual);
+}
+
+/// A circuit with very uneven levels: a chain of `depth` inverters next to a
+/// block of `width` independent gates that all read the first input.
+fn irregular_circuit_gates(depth: usize, width: usize) -> HashSet<Gate> {
+    let mut gates = HashSet::new();
+    for i in 0..depth {
+        let input = if i == 0 {
+            "a".to_string()
+        } else {
+            format!("chain_{}", i - 1)
+        };
+        gates.insert(Gate::new(
+            format!("not_{}", i),
+            GateType::Not,
+            vec![input],
+            None,
+            format!("chain_{}", i),
+            0,
+        ));
+    }
+    for i in 0..width {
+        let gate_type = if i % 2 == 0 {
+            GateType::Xor
+        } else {
+            GateType::And
+        };
+        gates.insert(Gate::new(
+            format!("wide_{}", i),
+            gate_type,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+            format!("wide_{}", i),
+            0,
+        ));
+    }
+    gates.insert(Gate::new(
+        "join".to_string(),
+        GateType::Or,
+        vec![
+            format!("chain_{}", depth - 1),
+            format!("wide_{}", width - 1),
+        ],
+        None,
+        "out".to_string(),
+        0,
+    ));
+
+    gates
+}
+
+#[test]
+fn work_stealing_matches_level_by_level() {
+    let input_wires = vec!["a".to_string(), "b".to_string()];
+    let output_wires = vec!["out".to_string()];
+    let empty = vec![];
+    let mut circuits = [false, true].map(|work_stealing| {
+        let mut circuit = Circuit::new(
+            irregular_circuit_gates(9, 6),
+            &input_wires,
+            &output_wires,
+            &empty,
+        );
+        circuit.set_work_stealing(work_stealing);
+        circuit.sort_circuit();
+        circuit.compute_levels();
+        circuit
+    });
+    let wire_set = circuits[0].wire_set();
+    for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+        let inputs = HashMap::from([
+            ("a".to_string(), PtxtType::Bool(a)),
+            ("b".to_string(), PtxtType::Bool(b)),
+        ]);
+        let wire_map = circuits[0].initialize_wire_map(&wire_set, &inputs, "bool");
+        let level_by_level = circuits[0].evaluate(&wire_map);
+        let work_stealing = circuits[1].evaluate(&wire_map);
+        assert_eq!(level_by_level, work_stealing);
+    }
+
+    // Encrypted, the two schedules agree as well.
+    let (client_key, server_key) = gen_keys();
+    let [level_by_level, work_stealing] = circuits.map(|circuit| {
+        let mut circuit = GateCircuit::new(client_key.clone(), server_key.clone(), circuit);
+        let inputs = HashMap::from([
+            ("a".to_string(), PtxtType::Bool(true)),
+            ("b".to_string(), PtxtType::Bool(true)),
+        ]);
+        let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &inputs);
+        let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+        EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false)
+    });
+    assert_eq!(level_by_level, work_stealing);
+    // 9 inverters of 1 give 0, and 1 & 1 = 1.
+    assert_eq!(work_stealing["out"], PtxtType::Bool(true));
+}
+
+/// Compare the two schedules on an irregular circuit, with
+/// `cargo test --release -- --ignored work_stealing_benchmark --nocapture`.
+#[test]
+#[ignore]
+fn work_stealing_benchmark() {
+    let input_wires = vec!["a".to_string(), "b".to_string()];
+    let output_wires = vec!["out".to_string()];
+    let empty = vec![];
+    let (client_key, server_key) = gen_keys();
+    let inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(false)),
+    ]);
+
+    for work_stealing in [false, true] {
+        let mut circuit = Circuit::new(
+            irregular_circuit_gates(64, 256),
+            &input_wires,
+            &output_wires,
+            &empty,
+        );
+        circuit.set_work_stealing(work_stealing);
+        circuit.sort_circuit();
+        circuit.compute_levels();
+        let wire_set = circuit.wire_set();
+        let mut circuit = GateCircuit::new(client_key.clone(), server_key.clone(), circuit);
+        let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &inputs);
+
+        let start = std::time::Instant::now();
+        EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+        println!(
+            "work stealing {}: {:.3} seconds",
+            work_stealing,
+            start.elapsed().as_secs_f64()
+        );
+    }
+}
+
+#[test]
+fn cnf_of_xor_holds_for_valid_assignments_only() {
+    let gates = HashSet::from([Gate::new(
+        String::from("g0"),
+        GateType::Xor,
+        vec!["a".to_string(), "b".to_string()],
+        None,
+        "y".to_string(),
+        0,
+    )]);
+    let input_wires = vec!["a".to_string(), "b".to_string()];
+    let output_wires = vec!["y".to_string()];
+    let empty = vec![];
+    let circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+
+    let cnf = circuit.to_cnf();
+    assert_eq!(cnf.variables, vec!["a", "b", "y"]);
+    // One clause per row of the truth table, each over all three variables.
+    assert_eq!(cnf.clauses.len(), 4);
+    for clause in &cnf.clauses {
+        assert_eq!(
+            clause
+                .iter()
+                .map(|literal| literal.abs())
+                .sorted()
+                .collect_vec(),
+            vec![1, 2, 3]
+        );
+    }
+
+    for (a, b, y) in itertools::iproduct!([false, true], [false, true], [false, true]) {
+        let assignment = HashMap::from([
+            ("a".to_string(), a),
+            ("b".to_string(), b),
+            ("y".to_string(), y),
+        ]);
+        assert_eq!(cnf.is_satisfied_by(&assignment), y == (a ^ b));
+    }
+}
+
+#[test]
+fn patch_turning_and_into_or_changes_output() {
+    let Netlist {
+        gates,
+        wire_set,
+        mut input_wires,
+        output_wires,
+        dff_outputs,
+        ..
+    } = Netlist::write(
+        "helm_patch.v",
+        "module m(a, b, c, y, z);\n\
+         input a, b, c;\n\
+         output y, z;\n\
+         and g0(a, b, y);\n\
+         xor g1(a, b, z);\n\
+         endmodule\n",
+        false,
+    );
+    let patch = common::write_temp_file(
+        "helm_patch.txt",
+        "# what if g0 were an OR?\n\
+         type g0 or\n\
+         rewire g1 1 c\n\
+         tie a 1\n\
+         rewire g1 0 a\n",
+    );
+
+    let edits = verilog_parser::read_patch(&patch);
+    assert_eq!(edits.len(), 4);
+    let gates = circuit::apply_patch(gates, &mut input_wires, &wire_set, &edits);
+    assert_eq!(input_wires, vec!["b", "c"]);
+
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    for (b, c) in itertools::iproduct!([false, true], [false, true]) {
+        let inputs = HashMap::from([
+            ("b".to_string(), PtxtType::Bool(b)),
+            ("c".to_string(), PtxtType::Bool(c)),
+        ]);
+        let wire_map = circuit.initialize_wire_map(&wire_set, &inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        // a is tied to 1, so the OR is always 1 where the AND would follow b,
+        // and g1 reads the primary input c and the tied a.
+        assert_eq!(wire_map["y"], PtxtType::Bool(true));
+        assert_eq!(wire_map["z"], PtxtType::Bool(!c));
+    }
+}
+
+#[test]
+fn accumulator_sums_input_stream() {
+    let netlist = Netlist::write(
+        "helm_accumulator.v",
+        "module running_sum(x, en, total, all);\n\
+         input x, en;\n\
+         output total, all;\n\
+         acc acc0(x, en, total);\n\
+         acc acc1(x, all);\n\
+         endmodule\n",
+        true,
+    );
+    assert_eq!(netlist.dff_outputs, vec!["total", "all"]);
+    // The enable selects the next value instead of multiplying the data.
+    assert!(netlist
+        .gates
+        .iter()
+        .all(|gate| gate.get_gate_type() != GateType::Mult));
+    assert_eq!(
+        netlist
+            .gates
+            .iter()
+            .filter(|gate| gate.get_gate_type() == GateType::MuxN)
+            .count(),
+        1
+    );
+    let mut circuit = netlist.circuit();
+
+    let user_inputs = netlist
+        .input_wires
+        .iter()
+        .map(|wire| (wire.to_string(), PtxtType::U16(0)))
+        .collect::<HashMap<_, _>>();
+    let mut wire_map = circuit.initialize_wire_map(&netlist.wire_set, &user_inputs, "u16");
+    let stream = [(7, 1), (300, 0), (20, 1), (1000, 1)];
+    for (x, en) in stream {
+        wire_map.insert("x".to_string(), PtxtType::U16(x));
+        wire_map.insert("en".to_string(), PtxtType::U16(en));
+        wire_map = circuit.evaluate(&wire_map);
+    }
+
+    assert_eq!(wire_map["total"], PtxtType::U16(7 + 20 + 1000));
+    assert_eq!(wire_map["all"], PtxtType::U16(7 + 300 + 20 + 1000));
+}
+
+#[test]
+fn accumulator_sums_encrypted_input_stream() {
+    let netlist = Netlist::write(
+        "helm_accumulator_encrypted.v",
+        "module running_sum(x, en, total, all);\n\
+         input x, en;\n\
+         output total, all;\n\
+         acc acc0(x, en, total);\n\
+         acc acc1(x, all);\n\
+         endmodule\n",
+        true,
+    );
+    let circuit = netlist.circuit();
+
+    let (client_key, server_key) = match KeySet::Integer.generate() {
+        KeyPair::Integer(client_key, server_key) => (client_key, server_key),
+        _ => unreachable!(),
+    };
+    let mut circuit = ArithCircuit::new(client_key, server_key, circuit);
+    let inputs = |x: u16, en: u16| {
+        HashMap::from([
+            ("x".to_string(), PtxtType::U16(x)),
+            ("en".to_string(), PtxtType::U16(en)),
+        ])
+    };
+    let mut enc_wire_map =
+        EvalCircuit::encrypt_inputs(&mut circuit, &netlist.wire_set, &inputs(0, 0));
+    for (cycle, (x, en)) in [(7, 1), (300, 0), (20, 1)].into_iter().enumerate() {
+        // Feed a fresh encrypted input each cycle, keeping the registers.
+        let fresh = EvalCircuit::encrypt_inputs(&mut circuit, &netlist.wire_set, &inputs(x, en));
+        for wire in ["x", "en"] {
+            enc_wire_map.insert(wire.to_string(), fresh[wire].clone());
+        }
+        enc_wire_map =
+            EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, cycle + 1, "u16");
+    }
+    let outputs = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false);
+
+    assert_eq!(outputs["total"], PtxtType::U16(7 + 20));
+    assert_eq!(outputs["all"], PtxtType::U16(7 + 300 + 20));
+}
+
+#[test]
+fn trivial_constants_decrypt_and_evaluate_the_same() {
+    let gates = HashSet::from([
+        Gate::new(
+            "xor_0".to_string(),
+            GateType::Xor,
+            vec!["a".to_string(), "key".to_string()],
+            None,
+            "x".to_string(),
+            0,
+        ),
+        Gate::new(
+            "and_0".to_string(),
+            GateType::And,
+            vec!["x".to_string(), "mask".to_string()],
+            None,
+            "y".to_string(),
+            0,
+        ),
+    ]);
+    let input_wires = vec!["a".to_string(), "key".to_string(), "mask".to_string()];
+    let output_wires = vec!["y".to_string()];
+    let empty = vec![];
+    let wire_set = HashSet::from(["x".to_string(), "y".to_string()]);
+    let constants = vec!["key".to_string(), "mask".to_string()];
+    let (client_key, server_key) = gen_keys();
+
+    for a in [false, true] {
+        let input_wire_map = HashMap::from([
+            ("a".to_string(), PtxtType::Bool(a)),
+            ("key".to_string(), PtxtType::Bool(true)),
+            ("mask".to_string(), PtxtType::Bool(true)),
+        ]);
+        let mut outputs = vec![];
+        for trivial in [false, true] {
+            let mut circuit = Circuit::new(gates.clone(), &input_wires, &output_wires, &empty);
+            circuit.sort_circuit();
+            circuit.compute_levels();
+            let mut circuit = GateCircuit::new(client_key.clone(), server_key.clone(), circuit);
+            if trivial {
+                circuit.set_trivial_inputs(&constants);
+            }
+            let enc_wire_map =
+                EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
+            for wire in &constants {
+                assert!(client_key.decrypt(&enc_wire_map[wire]));
+            }
+            assert_eq!(
+                circuit.input_encryption_count(),
+                if trivial { 1 } else { 3 }
+            );
+            let enc_wire_map =
+                EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+            outputs.push(EvalCircuit::decrypt_outputs(
+                &mut circuit,
+                &enc_wire_map,
+                false,
+            ));
+        }
+
+        assert_eq!(outputs[0], outputs[1]);
+        assert_eq!(outputs[1]["y"], PtxtType::Bool(!a));
+    }
+}
+
+#[test]
+fn outputs_stream_in_the_order_they_are_computed() {
+    // y0 is ready after one level, y1 after three; a is an input.
+    let gate = |name: &str, gate_type, inputs: &[&str], output: &str| {
+        Gate::new(
+            name.to_string(),
+            gate_type,
+            inputs.iter().map(|input| input.to_string()).collect(),
+            None,
+            output.to_string(),
+            0,
+        )
+    };
+    let gates = HashSet::from([
+        gate("and_0", GateType::And, &["a", "b"], "y0"),
+        gate("xor_0", GateType::Xor, &["a", "b"], "t0"),
+        gate("or_0", GateType::Or, &["t0", "b"], "t1"),
+        gate("nand_0", GateType::Nand, &["t1", "a"], "y1"),
+    ]);
+    let input_wires = vec!["a".to_string(), "b".to_string()];
+    let output_wires = vec!["y1".to_string(), "a".to_string(), "y0".to_string()];
+    let empty = vec![];
+    let wire_set = ["y0", "t0", "t1", "y1"]
+        .iter()
+        .map(|wire| wire.to_string())
+        .collect::<HashSet<_>>();
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let stream_file = common::temp_path("helm_output_stream.csv");
+    let (client_key, server_key) = gen_keys();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit);
+    circuit.set_output_stream(stream_file.to_str().unwrap());
+    let input_wire_map = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(true)),
+    ]);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
+    EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+
+    assert_eq!(
+        std::fs::read_to_string(&stream_file).unwrap(),
+        "cycle,wire,value\n1,y0,1\n1,y1,0\n1,a,1\n"
+    );
+}
+
+#[test]
+fn priority_encoder_grants_highest_request() {
+    let netlist = Netlist::write(
+        "helm_priority_encoder.v",
+        "module arbiter(r0, r1, r2, r3, g0, g1, g2, g3, l);\n\
+         input r0, r1, r2, r3;\n\
+         output g0, g1, g2, g3, l;\n\
+         penc grant(4, r0, r1, r2, r3, g0, g1, g2, g3);\n\
+         penc lowest(4, r0, r1, r2, r3, l);\n\
+         endmodule\n",
+        false,
+    );
+    let mut circuit = netlist.circuit();
+
+    let requests = |pattern: usize| {
+        (0..4)
+            .map(|i| (format!("r{}", i), PtxtType::Bool((pattern >> i) & 1 == 1)))
+            .collect::<HashMap<_, _>>()
+    };
+    let highest = |pattern: usize| (0..4).rev().find(|i| (pattern >> i) & 1 == 1);
+    for pattern in 0..16 {
+        let wire_map = circuit.initialize_wire_map(&netlist.wire_set, &requests(pattern), "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        for i in 0..4 {
+            assert_eq!(
+                wire_map[&format!("g{}", i)],
+                PtxtType::Bool(highest(pattern) == Some(i)),
+                "pattern {:04b}",
+                pattern
+            );
+        }
+        assert_eq!(wire_map["l"], PtxtType::Bool(highest(pattern) == Some(0)));
+    }
+
+    let (client_key, server_key) = gen_keys();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit);
+    let enc_wire_map =
+        EvalCircuit::encrypt_inputs(&mut circuit, &netlist.wire_set, &requests(0b0110));
+    let enc_wire_map = EvalCircuit::evaluate_encrypted(&mut circuit, &enc_wire_map, 1, "bool");
+    let outputs = EvalCircuit::decrypt_outputs(&mut circuit, &enc_wire_map, false);
+    for (wire, value) in [("g1", false), ("g2", true), ("l", false)] {
+        assert_eq!(outputs[wire], PtxtType::Bool(value));
+    }
+}
+
+#[test]
+fn arithmetic_priority_encoder_gives_index() {
+    let netlist = Netlist::write(
+        "helm_priority_encoder_arith.v",
+        "module arbiter(r0, r1, r2, y);\n\
+         input r0, r1, r2;\n\
+         output y;\n\
+         penc index(3, r0, r1, r2, y);\n\
+         endmodule\n",
+        true,
+    );
+    assert_eq!(netlist.gates.len(), 1);
+    let mut circuit = netlist.circuit();
+
+    // A request is asserted when its low bit is set.
+    for (requests, index) in [
+        ([0, 0, 0], 0),
+        ([1, 0, 0], 0),
+        ([1, 1, 0], 1),
+        ([0, 0, 3], 2),
+        ([1, 2, 0], 0),
+        ([5, 1, 1], 2),
+    ] {
+        let inputs = requests
+            .iter()
+            .enumerate()
+            .map(|(i, &request)| (format!("r{}", i), PtxtType::U8(request)))
+            .collect::<HashMap<_, _>>();
+        let wire_map = circuit.initialize_wire_map(&netlist.wire_set, &inputs, "u8");
+        let wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(wire_map["y"], PtxtType::U8(index), "{:?}", requests);
+    }
+}
+
+#[test]
+fn forced_mismatch_writes_repro() {
+    let netlist = Netlist::write(
+        "helm_repro_and.v",
+        "module and2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         and g0(a, b, y);\n\
+         endmodule\n",
+        false,
+    );
+    let circuit = netlist.circuit();
+    let inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(true)),
+    ]);
+    let expected = circuit.reference_outputs(&inputs, 1, "bool");
+    assert_eq!(
+        expected,
+        HashMap::from([("y".to_string(), PtxtType::Bool(true))])
+    );
+
+    // Pretend the encrypted evaluation got the AND wrong.
+    let actual = HashMap::from([("y".to_string(), PtxtType::Bool(false))]);
+    let mismatches = circuit::compare_outputs(&expected, &actual, false);
+    assert_eq!(mismatches.len(), 1);
+    assert!(circuit::compare_outputs(&expected, &expected, false).is_empty());
+
+    // A number only matches the same number of another width when asked to.
+    let narrow = HashMap::from([("s".to_string(), PtxtType::U8(5))]);
+    let wide = HashMap::from([("s".to_string(), PtxtType::U32(5))]);
+    assert_eq!(circuit::compare_outputs(&narrow, &wide, false).len(), 1);
+    assert!(circuit::compare_outputs(&narrow, &wide, true).is_empty());
+
+    let repro = common::temp_path("helm_repro");
+    let _ = std::fs::remove_dir_all(&repro);
+    let extra_args = vec!["--cycles".to_string(), "1".to_string()];
+    let patch = common::write_temp_file("helm_repro.patch", "type g0 and\n");
+    let extra_files = vec![("--patch".to_string(), patch)];
+    verilog_parser::write_repro(
+        repro.to_str().unwrap(),
+        &netlist.file_name,
+        &inputs,
+        &mismatches,
+        &extra_args,
+        &extra_files,
+    );
+
+    assert!(repro.join("helm_repro_and.v").exists());
+    assert_eq!(
+        std::fs::read_to_string(repro.join("helm_repro.patch")).unwrap(),
+        "type g0 and\n"
+    );
+    let repro_inputs =
+        verilog_parser::read_input_wires(repro.join("inputs.csv").to_str().unwrap(), "bool");
+    assert_eq!(repro_inputs, inputs);
+    assert_eq!(
+        std::fs::read_to_string(repro.join("mismatches.csv")).unwrap(),
+        "wire,expected,actual\ny,true,false\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(repro.join("command.txt")).unwrap(),
+        "helm --verilog helm_repro_and.v --input-wires-file inputs.csv --cycles 1 --patch \
+         helm_repro.patch\n"
+    );
+}
+
+#[test]
+fn evaluating_twice_gives_the_same_outputs() {
+    let (gates, wire_set, input_wires, output_wires, dff_outputs, _, _) =
+        verilog_parser::read_verilog_file("hdl-benchmarks/processed-netlists/2-bit-adder.v", false);
+    let input_wire_map = verilog_parser::read_input_wires(
+        "hdl-benchmarks/test-cases/2-bit-adder.inputs.csv",
+        "bool",
+    );
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let wire_map = circuit.initialize_wire_map(&wire_set, &input_wire_map, "bool");
+    let expected = circuit.evaluate(&wire_map);
+
+    let (client_key, server_key) = gen_keys();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &input_wire_map);
+    let first = circuit::evaluate_and_decrypt(&mut circuit, &enc_wire_map, 1, "bool");
+    let second = circuit::evaluate_and_decrypt(&mut circuit, &enc_wire_map, 1, "bool");
+
+    assert!(circuit::compare_outputs(&first, &second, false).is_empty());
+    for wire in output_wires.iter() {
+        assert_eq!(first[wire], expected[wire]);
+    }
+}
+
+#[test]
+fn evaluating_again_recomputes_every_gate() {
+    let gates = HashSet::from([Gate::new(
+        "g1".to_string(),
+        GateType::And,
+        vec!["a".to_string(), "b".to_string()],
+        None,
+        "out".to_string(),
+        0,
+    )]);
+    let wire_set = HashSet::from(["out".to_string()]);
+    let input_wires = vec!["a".to_string(), "b".to_string()];
+    let output_wires = vec!["out".to_string()];
+    let empty = vec![];
+    let mut circuit = Circuit::new(gates, &input_wires, &output_wires, &empty);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let (client_key, server_key) = gen_keys();
+    let mut circuit = GateCircuit::new(client_key, server_key, circuit);
+    let mut inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(true)),
+    ]);
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &inputs);
+    let first = circuit::evaluate_and_decrypt(&mut circuit, &enc_wire_map, 1, "bool");
+
+    // A cached ciphertext would still decrypt to the first output.
+    inputs.insert("b".to_string(), PtxtType::Bool(false));
+    let enc_wire_map = EvalCircuit::encrypt_inputs(&mut circuit, &wire_set, &inputs);
+    let second = circuit::evaluate_and_decrypt(&mut circuit, &enc_wire_map, 1, "bool");
+
+    assert_eq!(first["out"], PtxtType::Bool(true));
+    assert_eq!(second["out"], PtxtType::Bool(false));
+}
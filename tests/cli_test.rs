@@ -0,0 +1,519 @@
+mod common;
+
+use common::write_temp_file;
+use std::process::Command;
+
+#[test]
+fn quiet_run_has_empty_stdout() {
+    let netlist = write_temp_file(
+        "helm_cli_quiet.v",
+        "module and2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         and g0(a, b, y);\n\
+         endmodule\n",
+    );
+    let outputs = common::temp_path("helm_cli_quiet.outputs.csv");
+    let _ = std::fs::remove_file(&outputs);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--verilog", &netlist])
+        .args(["-w", "a", "1", "-w", "b", "1"])
+        .arg("--output-wires-file")
+        .arg(&outputs)
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let written = std::fs::read_to_string(&outputs).expect("Outputs were not written");
+    assert_eq!(written.trim(), "y, true");
+}
+
+#[test]
+fn quiet_conflicts_with_verbose() {
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--verbose", "--verilog", "unused.v"])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn check_only_reports_syntax_errors() {
+    let valid = write_temp_file(
+        "helm_cli_check_valid.v",
+        "module and2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         and g0(a, b, y);\n\
+         endmodule\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--check-only", "--verilog", &valid])
+        .output()
+        .expect("Failed to run helm");
+    assert!(output.status.success());
+
+    let broken = write_temp_file(
+        "helm_cli_check_broken.v",
+        "module and2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         \x20 andd g0(a, b, y);\n\
+         endmodule\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--check-only", "--verilog", &broken])
+        .output()
+        .expect("Failed to run helm");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("{}:4:3: Invalid gate type \"andd\"", broken)));
+}
+
+#[test]
+fn manifest_records_netlist_hash_and_gate_count() {
+    let netlist = write_temp_file(
+        "helm_cli_manifest.v",
+        "module and_or(a, b, c, y);\n\
+         input a, b, c;\n\
+         output y;\n\
+         wire t;\n\
+         and g0(a, b, t);\n\
+         or g1(t, c, y);\n\
+         endmodule\n",
+    );
+    let manifest = common::temp_path("helm_cli_manifest.json");
+    let _ = std::fs::remove_file(&manifest);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--verilog", &netlist])
+        .args(["-w", "a", "1", "-w", "b", "1", "-w", "c", "0"])
+        .arg("--manifest")
+        .arg(&manifest)
+        .output()
+        .expect("Failed to run helm");
+    assert!(output.status.success());
+
+    let written = std::fs::read_to_string(&manifest).expect("Manifest was not written");
+    let netlist_hash = helm::manifest::hash_file(&netlist);
+    assert!(written.contains(&format!(
+        "\"netlist_hash\": \"fnv1a:{:016x}\"",
+        netlist_hash
+    )));
+    assert!(written.contains("\"gates\": 2,"));
+    assert!(written.contains("\"cycles\": 1,"));
+    assert!(written.contains("\"input_file\": null,"));
+}
+
+#[test]
+fn duplicate_input_wires_are_rejected() {
+    let netlist = write_temp_file(
+        "helm_cli_duplicate.v",
+        "module and2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         and g0(a, b, y);\n\
+         endmodule\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--verilog", &netlist])
+        .args(["-w", "a", "1", "-w", "a", "0", "-w", "b", "1"])
+        .output()
+        .expect("Failed to run helm");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Input wire \"a\" is given more than once"));
+
+    // With --dedup-inputs the last value wins.
+    let outputs = common::temp_path("helm_cli_duplicate.outputs.csv");
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--dedup-inputs", "--verilog", &netlist])
+        .args(["-w", "a", "1", "-w", "a", "0", "-w", "b", "1"])
+        .arg("--output-wires-file")
+        .arg(&outputs)
+        .output()
+        .expect("Failed to run helm");
+    assert!(output.status.success());
+    let written = std::fs::read_to_string(&outputs).expect("Outputs were not written");
+    assert_eq!(written.trim(), "y, false");
+}
+
+#[test]
+fn gates_mode_flags_are_rejected_in_luts_mode() {
+    let netlist = write_temp_file(
+        "helm_cli_lut.v",
+        "module and2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         lut g0(0x8, a, b, y);\n\
+         endmodule\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--lazy-encryption", "--verilog", &netlist])
+        .output()
+        .expect("Failed to run helm");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Lazy encryption is only supported in gates mode"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--bootstrap-budget", "10", "--verilog", &netlist])
+        .output()
+        .expect("Failed to run helm");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("The bootstrap budget is only supported in gates mode"));
+}
+
+#[test]
+fn batch_vectors_conflict_with_per_gate_flags() {
+    let netlist = write_temp_file(
+        "helm_cli_batch_and2.v",
+        "module and2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         and g0(a, b, y);\n\
+         endmodule\n",
+    );
+    let vectors = write_temp_file("helm_cli_batch_vectors.csv", "a, b\n1, 1\n0, 1\n");
+    for flag in [
+        ["--checkpoint", "y"],
+        ["--bootstrap-budget", "10"],
+        ["--time-limit-per-gate", "1"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+            .args([
+                "--quiet",
+                "--batch-vectors",
+                &vectors,
+                "--verilog",
+                &netlist,
+            ])
+            .args(flag)
+            .output()
+            .expect("Failed to run helm");
+        assert!(!output.status.success(), "{:?}", flag);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("cannot be used with"), "{}", stderr);
+    }
+}
+
+#[test]
+fn quiet_run_still_warns_about_trivial_inputs() {
+    let netlist = write_temp_file(
+        "helm_cli_trivial_and2.v",
+        "module and2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         and g0(a, b, y);\n\
+         endmodule\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--trivial-input", "b", "--verilog", &netlist])
+        .args(["-w", "a", "1", "-w", "b", "1"])
+        .output()
+        .expect("Failed to run helm");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Trivial inputs are not encrypted at all"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn work_stealing_conflicts_with_bootstrap_budget() {
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args([
+            "--work-stealing",
+            "--bootstrap-budget",
+            "10",
+            "--verilog",
+            "unused.v",
+        ])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with"), "{}", stderr);
+}
+
+#[test]
+fn lazy_encryption_conflicts_with_per_gate_scheduling() {
+    for flag in [&["--time-limit-per-gate", "1"][..], &["--work-stealing"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+            .args(["--lazy-encryption", "--verilog", "unused.v"])
+            .args(flag)
+            .output()
+            .expect("Failed to run helm");
+        assert!(!output.status.success(), "{:?}", flag);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("cannot be used with"), "{}", stderr);
+    }
+}
+
+#[test]
+fn stats_csv_gets_one_row_per_netlist() {
+    let and_or = write_temp_file(
+        "helm_cli_stats_and_or.v",
+        "module and_or(a, b, c, y);\n\
+         input a, b, c;\n\
+         output y;\n\
+         and g0(a, b, t);\n\
+         or g1(t, c, y);\n\
+         endmodule\n",
+    );
+    let xor3 = write_temp_file(
+        "helm_cli_stats_xor3.v",
+        "module xor3(a, b, c, y);\n\
+         input a, b, c;\n\
+         output y;\n\
+         xor g0(a, b, t);\n\
+         xor g1(t, c, u);\n\
+         not g2(u, y);\n\
+         endmodule\n",
+    );
+    let stats = common::temp_path("helm_cli_stats.csv");
+    let _ = std::fs::remove_file(&stats);
+
+    for netlist in [&and_or, &xor3] {
+        let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+            .args(["--quiet", "--verilog", netlist])
+            .args(["-w", "a", "1", "-w", "b", "0", "-w", "c", "1"])
+            .arg("--emit-stats-csv")
+            .arg(&stats)
+            .output()
+            .expect("Failed to run helm");
+        assert!(output.status.success());
+    }
+
+    let written = std::fs::read_to_string(&stats).expect("Stats were not written");
+    let rows = written.lines().collect::<Vec<_>>();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], "name,gates,levels,bootstraps,eval_seconds");
+    assert!(rows[1].starts_with(&format!("{},2,2,2,", and_or)));
+    assert!(rows[2].starts_with(&format!("{},3,3,", xor3)));
+}
+
+#[test]
+fn checkpoint_prints_intermediate_value() {
+    let netlist = write_temp_file(
+        "helm_cli_checkpoint.v",
+        "module and_or(a, b, c, y);\n\
+         input a, b, c;\n\
+         output y;\n\
+         wire n;\n\
+         and g0(a, b, n);\n\
+         or g1(n, c, y);\n\
+         endmodule\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--verilog", &netlist, "--checkpoint", "n"])
+        .args(["-w", "a", "1", "-w", "b", "1", "-w", "c", "1"])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("Checkpoint n: true").count(), 1);
+    assert!(!stdout.contains("Checkpoint y"));
+
+    // The leak warning and the checkpoints are still printed with --quiet.
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--verilog", &netlist, "--checkpoint", "n"])
+        .args(["-w", "a", "1", "-w", "b", "1", "-w", "c", "1"])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("leaks their values"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), "Checkpoint n: true");
+}
+
+#[test]
+fn gate_type_listing_names_known_cells() {
+    // The listing is the output asked for, so --quiet doesn't hide it.
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--list-gate-types"])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let keywords = stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect::<Vec<_>>();
+    for cell in [
+        "and", "xnor", "mux", "muxn", "lut", "dff", "cone", "add", "acc",
+    ] {
+        assert!(keywords.contains(&cell), "{} is not listed", cell);
+    }
+    assert!(stdout.contains("y = s ? a : b"));
+}
+
+#[test]
+fn activity_compares_the_supplied_vectors() {
+    let netlist = write_temp_file(
+        "helm_cli_activity.v",
+        "module gates(a, b, y, z);\n\
+         input a, b;\n\
+         output y, z;\n\
+         and g0(a, b, y);\n\
+         or g1(a, b, z);\n\
+         endmodule\n",
+    );
+    // Only a changes, once, and of the outputs only y follows it.
+    let vectors = write_temp_file("helm_cli_activity.csv", "a,b\n1,1\n0,1\n0,1\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args([
+            "--activity",
+            "--verilog",
+            &netlist,
+            "--batch-vectors",
+            &vectors,
+        ])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(" a: 1"));
+    assert!(stdout.contains(" y: 1"));
+    assert!(!stdout.contains(" z: "));
+    assert!(stdout.contains("2 toggles in total."));
+}
+
+#[test]
+fn coverage_is_measured_over_the_supplied_vectors() {
+    let netlist = write_temp_file(
+        "helm_cli_coverage.v",
+        "module gates(a, b, y, z);\n\
+         input a, b;\n\
+         output y, z;\n\
+         and g0(a, b, y);\n\
+         or g1(a, b, z);\n\
+         endmodule\n",
+    );
+    let coverage = |vectors: &str| {
+        let vectors = write_temp_file("helm_cli_coverage.csv", vectors);
+        let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+            .args([
+                "--coverage",
+                "--verilog",
+                &netlist,
+                "--batch-vectors",
+                &vectors,
+            ])
+            .output()
+            .expect("Failed to run helm");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert!(coverage("a,b\n1,1\n1,1\n").contains("Toggle coverage: 0/2 gates"));
+    assert!(coverage("a,b\n0,0\n1,1\n").contains("Toggle coverage: 2/2 gates"));
+}
+
+#[test]
+fn streamed_outputs_record_each_cycle() {
+    // q0 toggles every cycle and y follows it.
+    let netlist = write_temp_file(
+        "helm_cli_stream_cycles.v",
+        "module toggle(a, y);\n\
+         input a;\n\
+         output y;\n\
+         xor g0(q0, a, d0);\n\
+         buf g1(q0, d1);\n\
+         buf g2(q0, y);\n\
+         dff r0(d0, q0);\n\
+         dff r1(d1, q1);\n\
+         endmodule\n",
+    );
+    let stream = common::temp_path("helm_cli_stream_cycles.csv");
+    let _ = std::fs::remove_file(&stream);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--verilog", &netlist, "-w", "a", "1"])
+        .args(["--cycles", "3", "--stream-outputs"])
+        .arg(&stream)
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(&stream).expect("Outputs were not streamed"),
+        "cycle,wire,value\n1,y,0\n2,y,1\n3,y,0\n"
+    );
+}
+
+#[test]
+fn only_combinational_ignores_netlist_cycles() {
+    let netlist = write_temp_file(
+        "helm_cli_only_combinational_cycles.v",
+        "(* helm_cycles = 3 *)\n\
+         module toggle(a, y);\n\
+         input a;\n\
+         output y;\n\
+         xor g0(q0, a, d0);\n\
+         buf g1(q0, d1);\n\
+         buf g2(q0, y);\n\
+         dff r0(d0, q0);\n\
+         dff r1(d1, q1);\n\
+         endmodule\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--only-combinational", "--verilog", &netlist])
+        .args(["--default-input", "0", "-w", "a", "1"])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Ignoring the 3 cycles set by the netlist"));
+}
+
+#[test]
+fn unknown_constants_are_rejected_before_encryption() {
+    let netlist = write_temp_file(
+        "helm_cli_unknown_constant.v",
+        "module and_x(a, y);\n\
+         input a;\n\
+         output y;\n\
+         cx g0(t);\n\
+         and g1(a, t, y);\n\
+         endmodule\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--quiet", "--verilog", &netlist, "-w", "a", "1"])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unknown (x) constants"), "{}", stderr);
+}
+
+#[test]
+fn adder_requires_compare_backends() {
+    let output = Command::new(env!("CARGO_BIN_EXE_helm"))
+        .args(["--adder", "cla", "--verilog", "unused.v"])
+        .output()
+        .expect("Failed to run helm");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--compare-backends"), "{}", stderr);
+}
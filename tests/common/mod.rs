@@ -0,0 +1,67 @@
+// Each test crate uses some of these helpers.
+#![allow(dead_code)]
+
+use helm::{circuit::Circuit, gates::Gate, verilog_parser};
+use std::{collections::HashSet, path::PathBuf};
+
+/// Path of `file_name` in a temporary directory of this test process, so
+/// concurrent test runs don't overwrite each other's files.
+pub fn temp_path(file_name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("helm_tests_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("Failed to create the temporary directory");
+    dir.join(file_name)
+}
+
+/// Write `contents` to a file in the temporary directory and return its path.
+pub fn write_temp_file(file_name: &str, contents: &str) -> String {
+    let path = temp_path(file_name);
+    std::fs::write(&path, contents).expect("Failed to write temporary file");
+    path.to_str().unwrap().to_owned()
+}
+
+/// The parts of a netlist read by `read_verilog_file`, which own the wires
+/// its circuit borrows.
+pub struct Netlist {
+    pub file_name: String,
+    pub gates: HashSet<Gate>,
+    pub wire_set: HashSet<String>,
+    pub input_wires: Vec<String>,
+    pub output_wires: Vec<String>,
+    pub dff_outputs: Vec<String>,
+}
+
+impl Netlist {
+    /// Write `source` to `file_name` in the temporary directory and read it.
+    pub fn write(file_name: &str, source: &str, is_arith: bool) -> Netlist {
+        Netlist::read(&write_temp_file(file_name, source), is_arith)
+    }
+
+    /// Read the netlist at `file_name`.
+    pub fn read(file_name: &str, is_arith: bool) -> Netlist {
+        let (gates, wire_set, input_wires, output_wires, dff_outputs, _, _) =
+            verilog_parser::read_verilog_file(file_name, is_arith);
+
+        Netlist {
+            file_name: file_name.to_owned(),
+            gates,
+            wire_set,
+            input_wires,
+            output_wires,
+            dff_outputs,
+        }
+    }
+
+    /// A sorted and levelled circuit of the netlist's gates.
+    pub fn circuit(&self) -> Circuit<'_> {
+        let mut circuit = Circuit::new(
+            self.gates.clone(),
+            &self.input_wires,
+            &self.output_wires,
+            &self.dff_outputs,
+        );
+        circuit.sort_circuit();
+        circuit.compute_levels();
+
+        circuit
+    }
+}
@@ -1,6 +1,6 @@
 use helm::{
     gates::{Gate, GateType},
-    FheType, PtxtType,
+    FheType, PtxtType, U256,
 };
 use rand::Rng;
 use tfhe::boolean::gen_keys;
@@ -309,3 +309,386 @@ fn caching_of_gate_evaluation() {
         assert_eq!(decrypted, ptxt_result);
     }
 }
+
+#[test]
+fn encrypted_u256_addition() {
+    use tfhe::{generate_keys, prelude::*, set_server_key, ConfigBuilder, FheUint256};
+
+    let config = ConfigBuilder::all_disabled()
+        .enable_custom_integers(
+            tfhe::shortint::parameters::PARAM_MULTI_BIT_MESSAGE_2_CARRY_2_GROUP_3_KS_PBS,
+            None,
+        )
+        .build();
+    let (client_key, server_key) = generate_keys(config); // integer ctxt
+    set_server_key(server_key);
+
+    // (2^128 + 5) + (2^128 - 1) = 2^129 + 4
+    let ptxt = vec![U256::new(1, 5), U256::new(0, u128::MAX)];
+    let inputs_ctxt = ptxt
+        .iter()
+        .map(|&value| {
+            FheType::U256(
+                FheUint256::try_encrypt(tfhe::integer::U256::from(value), &client_key).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut gate = Gate::new(
+        String::from(""),
+        GateType::Add,
+        vec![],
+        None,
+        "".to_string(),
+        0,
+    );
+    let result = gate.evaluate_encrypted_add_block(&inputs_ctxt[0], &inputs_ctxt[1], 1);
+    assert_eq!(result.decrypt(&client_key), PtxtType::U256(U256::new(2, 4)));
+}
+
+#[test]
+fn truth_table_of_aoi21_lut() {
+    // AOI21: y = !((a & b) | c), with a as the most significant LUT input.
+    let aoi21 = |a: bool, b: bool, c: bool| !((a && b) || c);
+    let lut_const = (0..8)
+        .map(|row| aoi21(row & 4 != 0, row & 2 != 0, row & 1 != 0) as u64)
+        .collect::<Vec<_>>();
+    let gate = Gate::new(
+        String::from("aoi21"),
+        GateType::Lut,
+        vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        Some(lut_const),
+        "y".to_string(),
+        0,
+    );
+
+    let table = gate.truth_table().unwrap();
+    assert_eq!(table.len(), 8);
+    for (inputs, output) in table {
+        assert_eq!(output, aoi21(inputs[0], inputs[1], inputs[2]));
+    }
+
+    let dff = Gate::new(
+        String::from("dff"),
+        GateType::Dff,
+        vec!["d".to_string()],
+        None,
+        "q".to_string(),
+        0,
+    );
+    assert!(dff.truth_table().is_none());
+}
+
+#[test]
+fn muxn_selects_each_of_four_inputs() {
+    let (client_key, server_key) = gen_keys();
+    let mut input_wires = vec!["s[0]".to_string(), "s[1]".to_string()];
+    input_wires.extend((0..4).map(|i| format!("d[{}]", i)));
+    let mut gate = Gate::new(
+        String::from("muxn"),
+        GateType::MuxN,
+        input_wires,
+        None,
+        "y".to_string(),
+        0,
+    );
+    assert_eq!(gate.bootstrap_count(), 6);
+
+    let mut cycle = 1;
+    for index in 0..4 {
+        for hot in 0..4 {
+            // Only data input `hot` is set, so the output is set iff it's selected.
+            let bits = [
+                index & 1 == 1,
+                index & 2 == 2,
+                hot == 0,
+                hot == 1,
+                hot == 2,
+                hot == 3,
+            ];
+            let ptxts = bits.iter().map(|&b| PtxtType::Bool(b)).collect::<Vec<_>>();
+            let ctxts = bits
+                .iter()
+                .map(|&b| client_key.encrypt(b))
+                .collect::<Vec<_>>();
+
+            assert_eq!(gate.evaluate(&ptxts), PtxtType::Bool(index == hot));
+            let output = gate.evaluate_encrypted(&server_key, &ctxts, cycle);
+            assert_eq!(client_key.decrypt(&output), index == hot);
+            cycle += 1;
+        }
+    }
+}
+
+macro_rules! check_wrapping_boundaries {
+    ($ty:ty, $variant:path) => {
+        let boundaries = [
+            0,
+            1,
+            2,
+            <$ty>::MAX / 2,
+            <$ty>::MAX / 2 + 1,
+            <$ty>::MAX - 1,
+            <$ty>::MAX,
+        ];
+        for &a in boundaries.iter() {
+            for &b in boundaries.iter() {
+                let (lhs, rhs) = ($variant(a), $variant(b));
+                assert_eq!(
+                    GateType::Add.wrapping_eval(&lhs, &rhs),
+                    $variant(a.wrapping_add(b))
+                );
+                assert_eq!(
+                    GateType::Sub.wrapping_eval(&lhs, &rhs),
+                    $variant(a.wrapping_sub(b))
+                );
+                assert_eq!(
+                    GateType::Mult.wrapping_eval(&lhs, &rhs),
+                    $variant(a.wrapping_mul(b))
+                );
+                assert_eq!(
+                    GateType::Shl.wrapping_eval(&lhs, &rhs),
+                    $variant(a.wrapping_shl(b as u32))
+                );
+                assert_eq!(
+                    GateType::Shr.wrapping_eval(&lhs, &rhs),
+                    $variant(a.wrapping_shr(b as u32))
+                );
+                assert_eq!(
+                    GateType::Div.wrapping_eval(&lhs, &rhs),
+                    $variant(a.checked_div(b).unwrap_or(<$ty>::MAX))
+                );
+            }
+        }
+    };
+}
+
+#[test]
+fn wrapping_eval_matches_rust_on_boundaries() {
+    check_wrapping_boundaries!(u8, PtxtType::U8);
+    check_wrapping_boundaries!(u16, PtxtType::U16);
+    check_wrapping_boundaries!(u32, PtxtType::U32);
+    check_wrapping_boundaries!(u64, PtxtType::U64);
+    check_wrapping_boundaries!(u128, PtxtType::U128);
+}
+
+#[test]
+fn wrapping_eval_u256_boundaries() {
+    let eval = |gate_type: GateType, a: U256, b: U256| {
+        gate_type.wrapping_eval(&PtxtType::U256(a), &PtxtType::U256(b))
+    };
+    let zero = U256::default();
+    let one = U256::from(1u128);
+    let two_128 = U256::new(1, 0);
+
+    assert_eq!(eval(GateType::Add, U256::MAX, one), PtxtType::U256(zero));
+    assert_eq!(
+        eval(GateType::Add, U256::from(u128::MAX), one),
+        PtxtType::U256(two_128)
+    );
+    assert_eq!(eval(GateType::Sub, zero, one), PtxtType::U256(U256::MAX));
+    // (2^256 - 1)^2 = 1 mod 2^256
+    assert_eq!(
+        eval(GateType::Mult, U256::MAX, U256::MAX),
+        PtxtType::U256(one)
+    );
+    assert_eq!(eval(GateType::Mult, two_128, two_128), PtxtType::U256(zero));
+    assert_eq!(
+        eval(GateType::Mult, U256::from(u128::MAX), U256::from(2u128)),
+        PtxtType::U256(U256::new(1, u128::MAX - 1))
+    );
+    assert_eq!(
+        eval(GateType::Div, U256::MAX, two_128),
+        PtxtType::U256(U256::from(u128::MAX))
+    );
+    assert_eq!(eval(GateType::Div, one, zero), PtxtType::U256(U256::MAX));
+    assert_eq!(
+        eval(GateType::Shl, one, U256::from(255u128)),
+        PtxtType::U256(U256::new(1 << 127, 0))
+    );
+    assert_eq!(
+        eval(GateType::Shl, one, U256::from(256u128)),
+        PtxtType::U256(one)
+    );
+    assert_eq!(
+        eval(GateType::Shr, two_128, U256::from(128u128)),
+        PtxtType::U256(one)
+    );
+}
+
+#[test]
+fn encrypted_u8_overflow_matches_wrapping_eval() {
+    use tfhe::{generate_keys, prelude::*, set_server_key, ConfigBuilder, FheUint8};
+
+    let config = ConfigBuilder::all_disabled()
+        .enable_custom_integers(
+            tfhe::shortint::parameters::PARAM_MULTI_BIT_MESSAGE_2_CARRY_2_GROUP_3_KS_PBS,
+            None,
+        )
+        .build();
+    let (client_key, server_key) = generate_keys(config); // integer ctxt
+    set_server_key(server_key);
+
+    let mut cycle = 1;
+    for (a, b) in [(u8::MAX, 1), (u8::MAX, u8::MAX), (128, 2), (0, 1)] {
+        let lhs = FheType::U8(FheUint8::try_encrypt(a, &client_key).unwrap());
+        let rhs = FheType::U8(FheUint8::try_encrypt(b, &client_key).unwrap());
+        for gate_type in [GateType::Add, GateType::Sub, GateType::Mult] {
+            let mut gate = Gate::new(
+                String::from(""),
+                gate_type.clone(),
+                vec![],
+                None,
+                "".to_string(),
+                0,
+            );
+            let result = match gate_type {
+                GateType::Add => gate.evaluate_encrypted_add_block(&lhs, &rhs, cycle),
+                GateType::Sub => gate.evaluate_encrypted_sub_block(&lhs, &rhs, cycle),
+                _ => gate.evaluate_encrypted_mul_block(&lhs, &rhs, cycle),
+            };
+            assert_eq!(
+                result.decrypt(&client_key),
+                gate_type.wrapping_eval(&PtxtType::U8(a), &PtxtType::U8(b))
+            );
+            cycle += 1;
+        }
+    }
+}
+
+#[test]
+fn unknown_inputs_propagate_unless_masked() {
+    let x = PtxtType::Unknown;
+    let (f, t) = (PtxtType::Bool(false), PtxtType::Bool(true));
+    let gate = |gate_type: GateType, inputs: usize| {
+        Gate::new(
+            String::from(""),
+            gate_type,
+            (0..inputs).map(|i| format!("in{}", i)).collect(),
+            None,
+            "out".to_string(),
+            0,
+        )
+    };
+
+    // A controlling value masks the unknown input, anything else passes it on.
+    assert_eq!(gate(GateType::And, 2).evaluate(&[f, x]), f);
+    assert_eq!(gate(GateType::And, 2).evaluate(&[t, x]), x);
+    assert_eq!(gate(GateType::Or, 2).evaluate(&[x, t]), t);
+    assert_eq!(gate(GateType::Or, 2).evaluate(&[x, f]), x);
+    assert_eq!(gate(GateType::Nand, 2).evaluate(&[x, f]), t);
+    assert_eq!(gate(GateType::Nor, 2).evaluate(&[t, x]), f);
+    assert_eq!(gate(GateType::Xor, 2).evaluate(&[t, x]), x);
+    assert_eq!(gate(GateType::Not, 1).evaluate(&[x]), x);
+
+    // A mux with an unknown select is known only if both inputs agree.
+    assert_eq!(gate(GateType::Mux, 3).evaluate(&[t, t, x]), t);
+    assert_eq!(gate(GateType::Mux, 3).evaluate(&[t, f, x]), x);
+    assert_eq!(gate(GateType::Mux, 3).evaluate(&[x, f, f]), f);
+    assert_eq!(gate(GateType::ConstUnknown, 0).evaluate(&[]), x);
+}
+
+#[test]
+fn muxn_selects_by_integer_index() {
+    use tfhe::prelude::*;
+    use tfhe::set_server_key;
+    use tfhe::FheUint8;
+    use tfhe::{generate_keys, ConfigBuilder};
+
+    let config = ConfigBuilder::all_disabled()
+        .enable_custom_integers(
+            tfhe::shortint::parameters::PARAM_MULTI_BIT_MESSAGE_2_CARRY_2_GROUP_3_KS_PBS,
+            None,
+        )
+        .build();
+    let (client_key, server_key) = generate_keys(config); // integer ctxt
+    set_server_key(server_key);
+
+    let mut input_wires = vec!["s".to_string()];
+    input_wires.extend((0..4).map(|i| format!("d[{}]", i)));
+    let mut gate = Gate::new(
+        String::from("muxn"),
+        GateType::MuxN,
+        input_wires,
+        None,
+        "y".to_string(),
+        0,
+    );
+    assert_eq!(gate.bootstrap_count(), 6);
+
+    let data = [11u8, 22, 33, 44];
+    let data_ctxts = data
+        .iter()
+        .map(|&value| FheType::U8(FheUint8::try_encrypt(value, &client_key).unwrap()))
+        .collect::<Vec<_>>();
+    // The index is taken modulo the number of data inputs.
+    for (cycle, index) in [0u8, 1, 2, 3, 6].into_iter().enumerate() {
+        let expected = data[index as usize % 4];
+        let mut ptxts = vec![PtxtType::U8(index)];
+        ptxts.extend(data.iter().map(|&value| PtxtType::U8(value)));
+        assert_eq!(gate.evaluate(&ptxts), PtxtType::U8(expected));
+
+        let index_ctxt = FheType::U8(FheUint8::try_encrypt(index, &client_key).unwrap());
+        let output = gate.evaluate_encrypted_muxn_block(&index_ctxt, &data_ctxts, cycle + 1);
+        assert_eq!(output.decrypt(&client_key), PtxtType::U8(expected));
+    }
+}
+
+#[test]
+fn priority_encoder_indexes_highest_request() {
+    use tfhe::prelude::*;
+    use tfhe::set_server_key;
+    use tfhe::FheUint8;
+    use tfhe::{generate_keys, ConfigBuilder};
+
+    let config = ConfigBuilder::all_disabled()
+        .enable_custom_integers(
+            tfhe::shortint::parameters::PARAM_MULTI_BIT_MESSAGE_2_CARRY_2_GROUP_3_KS_PBS,
+            None,
+        )
+        .build();
+    let (client_key, server_key) = generate_keys(config); // integer ctxt
+    set_server_key(server_key);
+
+    let mut gate = Gate::new(
+        String::from("penc"),
+        GateType::PriorityEncoder,
+        (0..4).map(|i| format!("r[{}]", i)).collect(),
+        None,
+        "y".to_string(),
+        0,
+    );
+    // Only the low bit of a request asserts it.
+    let reference = |requests: &[u8]| {
+        requests
+            .iter()
+            .rposition(|request| request & 1 == 1)
+            .unwrap_or(0) as u8
+    };
+    let patterns = [
+        [0u8, 0, 0, 0],
+        [1, 0, 0, 0],
+        [0, 1, 1, 0],
+        [1, 0, 0, 1],
+        [3, 2, 5, 4],
+    ];
+    for (cycle, requests) in patterns.iter().enumerate() {
+        let ptxts = requests
+            .iter()
+            .map(|&request| PtxtType::U8(request))
+            .collect::<Vec<_>>();
+        assert_eq!(gate.evaluate(&ptxts), PtxtType::U8(reference(requests)));
+
+        let ctxts = requests
+            .iter()
+            .map(|&request| FheType::U8(FheUint8::try_encrypt(request, &client_key).unwrap()))
+            .collect::<Vec<_>>();
+        let output = gate.evaluate_encrypted_priority_encoder_block(&ctxts, cycle + 1);
+        assert_eq!(
+            output.decrypt(&client_key),
+            PtxtType::U8(reference(requests)),
+            "{:?}",
+            requests
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use helm::{PtxtType, U256};
+use std::str::FromStr;
+
+#[test]
+fn ptxt_type_from_hex() {
+    assert_eq!(
+        PtxtType::from_hex("1", "bool").unwrap(),
+        PtxtType::Bool(true)
+    );
+    assert_eq!(
+        PtxtType::from_hex("0", "bool").unwrap(),
+        PtxtType::Bool(false)
+    );
+    assert_eq!(PtxtType::from_hex("a5", "u8").unwrap(), PtxtType::U8(0xa5));
+    assert_eq!(
+        PtxtType::from_hex("0xA5", "u8").unwrap(),
+        PtxtType::U8(0xa5)
+    );
+    assert_eq!(
+        PtxtType::from_hex("beef", "u16").unwrap(),
+        PtxtType::U16(0xbeef)
+    );
+    assert_eq!(
+        PtxtType::from_hex("deadbeef", "u32").unwrap(),
+        PtxtType::U32(0xdeadbeef)
+    );
+    assert_eq!(
+        PtxtType::from_hex("0123456789abcdef", "u64").unwrap(),
+        PtxtType::U64(0x0123456789abcdef)
+    );
+    assert_eq!(
+        PtxtType::from_hex("ffffffffffffffffffffffffffffffff", "u128").unwrap(),
+        PtxtType::U128(u128::MAX)
+    );
+
+    // Out of range for the width, not hex, or an unknown width.
+    assert!(PtxtType::from_hex("2", "bool").is_err());
+    assert!(PtxtType::from_hex("100", "u8").is_err());
+    assert!(PtxtType::from_hex("xyz", "u32").is_err());
+    assert!(PtxtType::from_hex("1", "u7").is_err());
+}
+
+#[test]
+fn ptxt_type_to_hex() {
+    assert_eq!(PtxtType::Bool(true).to_hex(), "1");
+    assert_eq!(PtxtType::Bool(false).to_hex(), "0");
+    assert_eq!(PtxtType::U8(0xa5).to_hex(), "a5");
+    assert_eq!(PtxtType::U8(0x5).to_hex(), "05");
+    assert_eq!(PtxtType::U16(0xa5).to_hex(), "00a5");
+    assert_eq!(PtxtType::U32(0xdeadbeef).to_hex(), "deadbeef");
+    assert_eq!(PtxtType::U64(1).to_hex(), "0000000000000001");
+    assert_eq!(
+        PtxtType::U128(u128::MAX).to_hex(),
+        "ffffffffffffffffffffffffffffffff"
+    );
+    assert_eq!(PtxtType::Unknown.to_hex(), "x");
+    assert_eq!(PtxtType::None.to_hex(), "x");
+}
+
+#[test]
+fn ptxt_type_hex_round_trip() {
+    for (value, width) in [
+        (PtxtType::U8(0x7f), "u8"),
+        (PtxtType::U16(0x1234), "u16"),
+        (PtxtType::U32(0xcafe), "u32"),
+        (PtxtType::U64(u64::MAX), "u64"),
+        (PtxtType::U128(1 << 100), "u128"),
+    ] {
+        assert_eq!(PtxtType::from_hex(&value.to_hex(), width).unwrap(), value);
+    }
+}
+
+#[test]
+fn u256_parse_and_format() {
+    // 2^128 + 5
+    let value = U256::new(1, 5);
+    assert_eq!(value.to_string(), "340282366920938463463374607431768211461");
+    assert_eq!(
+        "340282366920938463463374607431768211461"
+            .parse::<U256>()
+            .unwrap(),
+        value
+    );
+    assert_eq!(
+        U256::MAX.to_string(),
+        "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+    );
+    assert_eq!(U256::MAX.to_string().parse::<U256>().unwrap(), U256::MAX);
+    // 2^256 doesn't fit.
+    assert!(
+        "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+            .parse::<U256>()
+            .is_err()
+    );
+
+    // Values too wide for u128 parse as U256.
+    assert_eq!(
+        PtxtType::from_str("340282366920938463463374607431768211461").unwrap(),
+        PtxtType::U256(value)
+    );
+    assert_eq!(
+        PtxtType::U256(value).to_string(),
+        "U256(340282366920938463463374607431768211461)"
+    );
+    assert_eq!(
+        PtxtType::U256(value).to_hex(),
+        "0000000000000000000000000000000100000000000000000000000000000005"
+    );
+    assert_eq!(
+        PtxtType::from_hex("100000000000000000000000000000005", "u256").unwrap(),
+        PtxtType::U256(value)
+    );
+}
+
+#[test]
+fn value_eq_ignores_width() {
+    assert!(PtxtType::U8(5).value_eq(&PtxtType::U32(5)));
+    assert!(PtxtType::U256(U256::from(5u128)).value_eq(&PtxtType::U16(5)));
+    assert!(!PtxtType::U8(5).value_eq(&PtxtType::U8(6)));
+    assert!(!PtxtType::U64(1 << 40).value_eq(&PtxtType::U8(0)));
+
+    // Booleans are not numbers, and None equals nothing but None.
+    assert!(PtxtType::Bool(true).value_eq(&PtxtType::Bool(true)));
+    assert!(!PtxtType::Bool(true).value_eq(&PtxtType::U8(1)));
+    assert!(!PtxtType::None.value_eq(&PtxtType::U8(0)));
+    assert!(PtxtType::None.value_eq(&PtxtType::None));
+}
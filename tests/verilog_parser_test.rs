@@ -1,5 +1,18 @@
-use helm::verilog_parser::{read_input_wires, read_verilog_file};
-use helm::PtxtType;
+mod common;
+
+use common::write_temp_file;
+use helm::circuit::Circuit;
+use helm::gates::GateType;
+use helm::verilog_parser::{
+    check_verilog_file, pack_register_state, read_binary_wires, read_input_wires,
+    read_module_attributes, read_register_init_values, read_verilog_file, read_wire_aliases,
+    write_binary_wires, write_output_wires, write_state_trace, VerilogParseError,
+};
+use helm::{
+    fill_unspecified_inputs, get_arithmetic_type, get_cycle_count, rename_wires,
+    run_circuit_positional, PtxtType, RunError, U256,
+};
+use std::collections::HashMap;
 
 #[test]
 fn parse_two_bit_adder() {
@@ -139,3 +152,895 @@ fn bool_input_wires_array_as_int() {
     assert_eq!(wire_map["in3[4]"], PtxtType::Bool(false));
     assert_eq!(wire_map["in3[5]"], PtxtType::Bool(false));
 }
+
+#[test]
+fn arithmetic_type_from_attribute() {
+    let netlist = write_temp_file(
+        "helm_arith_attribute.v",
+        "(* helm_arith = \"u32\" *)\n\
+         module adder(a, b, c);\n\
+         input a, b;\n\
+         output c;\n\
+         add g0(a, b, c);\n\
+         endmodule\n",
+    );
+
+    assert_eq!(read_module_attributes(&netlist)["helm_arith"], "u32");
+    assert_eq!(get_arithmetic_type(None, &netlist), Some("u32".to_string()));
+    assert_eq!(
+        get_arithmetic_type(Some(&"u8".to_string()), &netlist),
+        Some("u8".to_string())
+    );
+
+    let (gates, _, inputs, outputs, _, _, has_arith) = read_verilog_file(&netlist, true);
+    assert_eq!(gates.len(), 1);
+    assert_eq!(inputs, vec!["a", "b"]);
+    assert_eq!(outputs, vec!["c"]);
+    assert!(has_arith);
+}
+
+#[test]
+fn cycle_count_from_attribute_or_companion_file() {
+    let netlist = write_temp_file(
+        "helm_cycles_attribute.v",
+        "(* helm_cycles = 4 *)\n\
+         module pipeline(d, q);\n\
+         input d;\n\
+         output q;\n\
+         dff r0(d, q);\n\
+         endmodule\n",
+    );
+    assert_eq!(get_cycle_count(None, &netlist), 4);
+    assert_eq!(get_cycle_count(Some(2), &netlist), 2);
+
+    let netlist = write_temp_file(
+        "helm_cycles_companion.v",
+        "module pipeline(d, q);\n\
+         input d;\n\
+         output q;\n\
+         dff r0(d, q);\n\
+         endmodule\n",
+    );
+    let companion = common::temp_path("helm_cycles_companion.cycles");
+    let _ = std::fs::remove_file(&companion);
+    assert_eq!(get_cycle_count(None, &netlist), 1);
+    std::fs::write(&companion, "3\n").unwrap();
+    assert_eq!(get_cycle_count(None, &netlist), 3);
+    assert_eq!(get_cycle_count(Some(5), &netlist), 5);
+}
+
+#[test]
+fn ternary_assign_to_mux() {
+    let netlist = write_temp_file(
+        "helm_ternary_assign.v",
+        "module select(s, a, b, y);\n\
+         input s, a, b;\n\
+         output y;\n\
+         assign y = s ? a : b;\n\
+         endmodule\n",
+    );
+
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    assert_eq!(gates.len(), 1);
+    let mux = gates.iter().next().unwrap();
+    assert_eq!(mux.get_gate_type(), GateType::Mux);
+    assert_eq!(mux.get_input_wires(), &vec!["a", "b", "s"]);
+    assert_eq!(mux.get_output_wire(), "y");
+
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    for select in [true, false] {
+        let user_inputs = HashMap::from([
+            ("s".to_string(), PtxtType::Bool(select)),
+            ("a".to_string(), PtxtType::Bool(true)),
+            ("b".to_string(), PtxtType::Bool(false)),
+        ]);
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(wire_map["y"], PtxtType::Bool(select));
+    }
+}
+
+#[test]
+fn chained_ternary_assign_on_bus() {
+    let netlist = write_temp_file(
+        "helm_chained_ternary_assign.v",
+        "module select(s, t, a, b, c, y);\n\
+         input s, t;\n\
+         input [1:0] a, b, c;\n\
+         output [1:0] y;\n\
+         assign y = s ? a : (t ? b : c);\n\
+         endmodule\n",
+    );
+
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    // Two muxes per bit.
+    assert_eq!(gates.len(), 4);
+    assert!(gates
+        .iter()
+        .all(|gate| gate.get_gate_type() == GateType::Mux));
+
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let (a, b, c) = (0b01, 0b10, 0b11);
+    for (s, t, expected) in [(true, false, a), (false, true, b), (false, false, c)] {
+        let mut user_inputs = HashMap::from([
+            ("s".to_string(), PtxtType::Bool(s)),
+            ("t".to_string(), PtxtType::Bool(t)),
+        ]);
+        for bit in 0..2 {
+            user_inputs.insert(format!("a[{}]", bit), PtxtType::Bool((a >> bit) & 1 == 1));
+            user_inputs.insert(format!("b[{}]", bit), PtxtType::Bool((b >> bit) & 1 == 1));
+            user_inputs.insert(format!("c[{}]", bit), PtxtType::Bool((c >> bit) & 1 == 1));
+        }
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        for bit in 0..2 {
+            assert_eq!(
+                wire_map[&format!("y[{}]", bit)],
+                PtxtType::Bool((expected >> bit) & 1 == 1)
+            );
+        }
+    }
+}
+
+#[test]
+fn aliased_input_seeds_netlist_wire() {
+    let netlist = write_temp_file(
+        "helm_wire_aliases.v",
+        "module renamed(n_a, n_b, y);\n\
+         input n_a;\n\
+         input [1:0] n_b;\n\
+         output y;\n\
+         xor g0(n_a, n_b[1], y);\n\
+         endmodule\n",
+    );
+    let aliases = write_temp_file(
+        "helm_wire_aliases.csv",
+        "file_name, netlist_name\na, n_a\nb, n_b\n",
+    );
+    let inputs_file = write_temp_file(
+        "helm_wire_aliases.inputs.csv",
+        "wire, value\na, 1\nb[0], 1\nb[1], 0\n",
+    );
+
+    let aliases = read_wire_aliases(&aliases);
+    let user_inputs = rename_wires(read_input_wires(&inputs_file, "bool"), &aliases);
+    assert_eq!(user_inputs["n_a"], PtxtType::Bool(true));
+    assert_eq!(user_inputs["n_b[0]"], PtxtType::Bool(true));
+    assert_eq!(user_inputs["n_b[1]"], PtxtType::Bool(false));
+
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+    let wire_map = circuit.evaluate(&wire_map);
+    assert_eq!(wire_map["y"], PtxtType::Bool(true));
+}
+
+#[test]
+fn unoptimized_circuit_keeps_parsed_gates() {
+    let netlist = write_temp_file(
+        "helm_no_optimize.v",
+        "module buffered(a, b, y, z);\n\
+         input a, b;\n\
+         output y, z;\n\
+         wire t1, t2;\n\
+         buf g0(a, t1);\n\
+         buf g1(t1, t2);\n\
+         and g2(t2, b, y);\n\
+         buf g3(y, z);\n\
+         endmodule\n",
+    );
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    let parsed_count = gates.len();
+    assert_eq!(parsed_count, 4);
+
+    let mut verbatim = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    verbatim.sort_circuit();
+    verbatim.compute_levels();
+    assert_eq!(verbatim.gate_count(), parsed_count);
+
+    let user_inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(true)),
+    ]);
+    let wire_map = verbatim.initialize_wire_map(&wire_set, &user_inputs, "bool");
+    let wire_map = verbatim.evaluate(&wire_map);
+    assert_eq!(wire_map["y"], PtxtType::Bool(true));
+    assert_eq!(wire_map["z"], PtxtType::Bool(true));
+}
+
+#[test]
+fn sized_literal_drives_constant_bits() {
+    let mut netlist = String::from(
+        "module literal(a, y, z);\n\
+         input [7:0] a;\n\
+         output [7:0] y;\n\
+         output z;\n\
+         wire [7:0] k;\n\
+         assign k = 8'hA5;\n",
+    );
+    for bit in 0..8 {
+        netlist.push_str(&format!(
+            "and g{}(a[{}], k[{}], y[{}]);\n",
+            bit, bit, bit, bit
+        ));
+    }
+    netlist.push_str("xor g8(k[0], k[1], z);\nendmodule\n");
+    let netlist = write_temp_file("helm_sized_literal.v", &netlist);
+
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    for bit in 0..8 {
+        let constant = gates
+            .iter()
+            .find(|gate| gate.get_output_wire() == format!("k[{}]", bit))
+            .unwrap();
+        let expected = if (0xA5 >> bit) & 1 == 1 {
+            GateType::ConstOne
+        } else {
+            GateType::ConstZero
+        };
+        assert_eq!(constant.get_gate_type(), expected);
+    }
+
+    let user_inputs = (0..8)
+        .map(|bit| (format!("a[{}]", bit), PtxtType::Bool(true)))
+        .collect::<HashMap<_, _>>();
+    let mut verbatim = Circuit::new(gates.clone(), &inputs, &outputs, &dff_outputs);
+    verbatim.sort_circuit();
+    verbatim.compute_levels();
+    assert_eq!(verbatim.gate_count(), 17);
+    assert_eq!(verbatim.count_bootstraps(), 9);
+
+    // The XOR of two constants folds into a constant that needs no bootstrap.
+    let mut optimized = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    optimized.optimize();
+    optimized.sort_circuit();
+    optimized.compute_levels();
+    assert_eq!(optimized.gate_count(), 17);
+    assert_eq!(optimized.count_bootstraps(), 8);
+
+    for circuit in [&mut verbatim, &mut optimized] {
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        for bit in 0..8 {
+            assert_eq!(
+                wire_map[&format!("y[{}]", bit)],
+                PtxtType::Bool((0xA5 >> bit) & 1 == 1)
+            );
+        }
+        assert_eq!(wire_map["z"], PtxtType::Bool(true));
+    }
+}
+
+#[test]
+fn parse_crlf_files_with_bom() {
+    let netlist = write_temp_file(
+        "helm_crlf_bom.v",
+        "\u{feff}/* Generated by a Windows tool\r\n\
+         * on a CRLF system */\r\n\
+         module windows(a, b, y);\r\n\
+         input a, b; // operands\r\n\
+         output y;\r\n\
+         and g0(a, b, y);\r\n\
+         endmodule\r\n",
+    );
+    let inputs_file = write_temp_file(
+        "helm_crlf_bom.inputs.csv",
+        "\u{feff}wire, value\r\na, 1\r\nb, 1\r\n",
+    );
+
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    assert_eq!(gates.len(), 1);
+    assert_eq!(inputs, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(outputs, vec!["y".to_string()]);
+
+    let user_inputs = read_input_wires(&inputs_file, "bool");
+    assert_eq!(user_inputs.len(), 2);
+    assert_eq!(user_inputs["a"], PtxtType::Bool(true));
+    assert_eq!(user_inputs["b"], PtxtType::Bool(true));
+
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+    let wire_map = circuit.evaluate(&wire_map);
+    assert_eq!(wire_map["y"], PtxtType::Bool(true));
+}
+
+#[test]
+fn parse_muxn_cell() {
+    let netlist = write_temp_file(
+        "helm_muxn.v",
+        "module select(s, d, y);\n\
+         input [1:0] s;\n\
+         input [3:0] d;\n\
+         output y;\n\
+         muxn g0(4, s[0], s[1], d[0], d[1], d[2], d[3], y);\n\
+         endmodule\n",
+    );
+
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    let gate = gates.iter().next().unwrap();
+    assert_eq!(gate.get_gate_type(), GateType::MuxN);
+    assert_eq!(gate.get_input_wires().len(), 6);
+
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let data = 0b0110;
+    for index in 0..4 {
+        let mut user_inputs = HashMap::new();
+        for bit in 0..2 {
+            user_inputs.insert(
+                format!("s[{}]", bit),
+                PtxtType::Bool((index >> bit) & 1 == 1),
+            );
+        }
+        for bit in 0..4 {
+            user_inputs.insert(
+                format!("d[{}]", bit),
+                PtxtType::Bool((data >> bit) & 1 == 1),
+            );
+        }
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(wire_map["y"], PtxtType::Bool((data >> index) & 1 == 1));
+    }
+}
+
+#[test]
+fn outputs_follow_port_declaration_order() {
+    let netlist = write_temp_file(
+        "helm_port_order.v",
+        "module ports(b, zeta, a, alpha);\n\
+         input a, b;\n\
+         output alpha;\n\
+         output [1:0] zeta;\n\
+         and g0(a, b, alpha);\n\
+         or g1(a, b, zeta[0]);\n\
+         xor g2(a, b, zeta[1]);\n\
+         endmodule\n",
+    );
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    assert_eq!(
+        outputs,
+        vec![
+            "zeta[0]".to_string(),
+            "zeta[1]".to_string(),
+            "alpha".to_string()
+        ]
+    );
+
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    let user_inputs = HashMap::from([
+        ("a".to_string(), PtxtType::Bool(true)),
+        ("b".to_string(), PtxtType::Bool(false)),
+    ]);
+    let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+    let wire_map = circuit.evaluate(&wire_map);
+    let output_map = outputs
+        .iter()
+        .map(|wire| (wire.to_string(), wire_map[wire]))
+        .collect::<HashMap<_, _>>();
+
+    let output_file = common::temp_path("helm_port_order.outputs.csv");
+    let output_file = output_file.to_str().unwrap().to_owned();
+    write_output_wires(Some(output_file.clone()), &output_map, &outputs, false);
+    assert_eq!(
+        std::fs::read_to_string(&output_file).unwrap(),
+        "zeta[0], true\nzeta[1], true\nalpha, false\n"
+    );
+
+    // Without an order, the wires are sorted by name.
+    write_output_wires(Some(output_file.clone()), &output_map, &[], false);
+    assert_eq!(
+        std::fs::read_to_string(&output_file).unwrap(),
+        "alpha, false\nzeta[0], true\nzeta[1], true\n"
+    );
+}
+
+#[test]
+fn multi_line_module_header_gives_port_order() {
+    let netlist = write_temp_file(
+        "helm_multi_line_ports.v",
+        "module ports(\n\
+           b,\n\
+           zeta,\n\
+           a, alpha\n\
+         );\n\
+         input a, b;\n\
+         output alpha, zeta;\n\
+         and g0(a, b, alpha);\n\
+         or g1(a, b, zeta);\n\
+         endmodule\n",
+    );
+    let (_, _, _, outputs, _, _, _) = read_verilog_file(&netlist, false);
+    assert_eq!(outputs, vec!["zeta".to_string(), "alpha".to_string()]);
+}
+
+#[test]
+fn merge_two_adders_into_pipeline() {
+    // s = x + y (mod 4)
+    let netlist = write_temp_file(
+        "helm_merge_adder.v",
+        "module add2(x, y, s);\n\
+         input [1:0] x, y;\n\
+         output [1:0] s;\n\
+         wire c0, t;\n\
+         xor g0(x[0], y[0], s[0]);\n\
+         and g1(x[0], y[0], c0);\n\
+         xor g2(x[1], y[1], t);\n\
+         xor g3(t, c0, s[1]);\n\
+         endmodule\n",
+    );
+    let (first_gates, _, first_inputs, first_outputs, first_dffs, _, _) =
+        read_verilog_file(&netlist, false);
+    let (second_gates, _, second_inputs, second_outputs, second_dffs, _, _) =
+        read_verilog_file(&netlist, false);
+
+    let mut circuit = Circuit::new(first_gates, &first_inputs, &first_outputs, &first_dffs);
+    let second = Circuit::new(second_gates, &second_inputs, &second_outputs, &second_dffs);
+    circuit.merge(
+        second,
+        &[
+            ("s[0]".to_string(), "x[0]".to_string()),
+            ("s[1]".to_string(), "x[1]".to_string()),
+        ],
+    );
+    assert_eq!(circuit.gate_count(), 8);
+
+    let wire_set = circuit.wire_set();
+    assert!(wire_set.contains("merge0.c0"));
+    assert!(wire_set.contains("merge0.s[1]"));
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    for (x, y, z) in (0..4).flat_map(|x| (0..4).flat_map(move |y| (0..4).map(move |z| (x, y, z)))) {
+        let mut user_inputs = HashMap::new();
+        for bit in 0..2 {
+            user_inputs.insert(format!("x[{}]", bit), PtxtType::Bool((x >> bit) & 1 == 1));
+            user_inputs.insert(format!("y[{}]", bit), PtxtType::Bool((y >> bit) & 1 == 1));
+            user_inputs.insert(
+                format!("merge0.y[{}]", bit),
+                PtxtType::Bool((z >> bit) & 1 == 1),
+            );
+        }
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        for bit in 0..2 {
+            assert_eq!(
+                wire_map[&format!("s[{}]", bit)],
+                PtxtType::Bool((((x + y) % 4) >> bit) & 1 == 1)
+            );
+            assert_eq!(
+                wire_map[&format!("merge0.s[{}]", bit)],
+                PtxtType::Bool((((x + y + z) % 4) >> bit) & 1 == 1)
+            );
+        }
+    }
+}
+
+#[test]
+fn input_wire_references_another_wire() {
+    let inputs_file = write_temp_file(
+        "helm_input_references.csv",
+        "wire, value\nb, =a\na, 1\nc, =b\nd, 0\nbus, =word, 2\nword, 2, 2\n",
+    );
+
+    let user_inputs = read_input_wires(&inputs_file, "bool");
+    assert_eq!(user_inputs["a"], PtxtType::Bool(true));
+    assert_eq!(user_inputs["b"], PtxtType::Bool(true));
+    assert_eq!(user_inputs["c"], PtxtType::Bool(true));
+    assert_eq!(user_inputs["d"], PtxtType::Bool(false));
+    assert_eq!(user_inputs["bus[0]"], PtxtType::Bool(false));
+    assert_eq!(user_inputs["bus[1]"], PtxtType::Bool(true));
+}
+
+#[test]
+#[should_panic(expected = "Input wire reference cycle")]
+fn input_wire_reference_cycle_is_rejected() {
+    let inputs_file = write_temp_file(
+        "helm_input_reference_cycle.csv",
+        "wire, value\na, =b\nb, =c\nc, =a\n",
+    );
+
+    read_input_wires(&inputs_file, "bool");
+}
+
+#[test]
+fn output_reg_counter_starts_from_initial_value() {
+    let netlist = write_temp_file(
+        "helm_output_reg.v",
+        "module counter(count);\n\
+         output reg [2:0] count = 3'd5;\n\
+         wire [2:0] next;\n\
+         wire carry;\n\
+         not g0(count[0], next[0]);\n\
+         xor g1(count[1], count[0], next[1]);\n\
+         and g2(count[1], count[0], carry);\n\
+         xor g3(count[2], carry, next[2]);\n\
+         dff r0(next[0], count[0]);\n\
+         dff r1(next[1], count[1]);\n\
+         dff r2(next[2], count[2]);\n\
+         endmodule\n",
+    );
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    assert_eq!(dff_outputs.len(), 3);
+    assert_eq!(outputs.len(), 3);
+
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.set_register_init_values(read_register_init_values(&netlist));
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let count = |wire_map: &HashMap<String, PtxtType>| {
+        (0..3).fold(0, |count, bit| {
+            let wire = format!("count[{}]", bit);
+            count | ((wire_map[&wire] == PtxtType::Bool(true)) as u8) << bit
+        })
+    };
+    let mut wire_map = circuit.initialize_wire_map(&wire_set, &HashMap::new(), "bool");
+    assert_eq!(count(&wire_map), 5);
+    for expected in [6, 7, 0, 1] {
+        wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(count(&wire_map), expected);
+    }
+}
+
+#[test]
+fn run_two_input_gate_from_positional_inputs() {
+    let netlist = write_temp_file(
+        "helm_positional.v",
+        "module nand2(a, b, y);\n\
+         input a, b;\n\
+         output y;\n\
+         nand g0(a, b, y);\n\
+         endmodule\n",
+    );
+
+    for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+        let outputs =
+            run_circuit_positional(&netlist, &[PtxtType::Bool(a), PtxtType::Bool(b)], 1).unwrap();
+        assert_eq!(
+            outputs,
+            HashMap::from([("y".to_string(), PtxtType::Bool(!(a && b)))])
+        );
+    }
+
+    assert!(matches!(
+        run_circuit_positional(&netlist, &[PtxtType::Bool(true)], 1),
+        Err(RunError::InputCountMismatch {
+            expected: 2,
+            actual: 1
+        })
+    ));
+}
+
+#[test]
+fn state_trace_of_counter_increases() {
+    let netlist = write_temp_file(
+        "helm_state_trace.v",
+        "module counter(count, done);\n\
+         output [1:0] count;\n\
+         output done;\n\
+         wire [1:0] next;\n\
+         not g0(count[0], next[0]);\n\
+         xor g1(count[1], count[0], next[1]);\n\
+         and g2(count[1], count[0], done);\n\
+         dff r0(next[0], count[0]);\n\
+         dff r1(next[1], count[1]);\n\
+         endmodule\n",
+    );
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+
+    let mut trace = vec![];
+    let mut wire_map = circuit.initialize_wire_map(&wire_set, &HashMap::new(), "bool");
+    for _ in 0..5 {
+        wire_map = circuit.evaluate(&wire_map);
+        let registers = dff_outputs
+            .iter()
+            .map(|wire| (wire.to_string(), wire_map[wire]))
+            .collect::<HashMap<_, _>>();
+        trace.push(pack_register_state(&registers));
+    }
+
+    let trace_file = common::temp_path("helm_state_trace.csv");
+    let trace_file = trace_file.to_str().unwrap().to_owned();
+    write_state_trace(&trace_file, &trace);
+    assert_eq!(
+        std::fs::read_to_string(&trace_file).unwrap(),
+        "cycle,count\n0,1\n1,2\n2,3\n3,0\n4,1\n"
+    );
+}
+
+#[test]
+fn unspecified_u8_inputs_use_default_value() {
+    let input_wires = vec!["N0".to_string(), "N1".to_string(), "N2".to_string()];
+    let given = HashMap::from([("N1".to_string(), PtxtType::U8(7))]);
+
+    let wire_map = fill_unspecified_inputs(given, &input_wires, &[], "255", "u8");
+    assert_eq!(wire_map.len(), 3);
+    assert_eq!(wire_map["N0"], PtxtType::U8(0xFF));
+    assert_eq!(wire_map["N1"], PtxtType::U8(7));
+    assert_eq!(wire_map["N2"], PtxtType::U8(0xFF));
+
+    // No inputs at all: the placeholder for "all zero" is replaced too.
+    let none_given = HashMap::from([("dummy".to_string(), PtxtType::U8(0))]);
+    let wire_map = fill_unspecified_inputs(none_given, &input_wires, &[], "255", "u8");
+    assert!(!wire_map.contains_key("dummy"));
+    assert!(wire_map.values().all(|value| *value == PtxtType::U8(0xFF)));
+
+    // Register outputs keep their initial values.
+    let dff_outputs = vec!["N2".to_string()];
+    let wire_map = fill_unspecified_inputs(HashMap::new(), &input_wires, &dff_outputs, "255", "u8");
+    assert_eq!(wire_map.len(), 2);
+    assert!(!wire_map.contains_key("N2"));
+}
+
+#[test]
+fn always_block_if_else_to_mux() {
+    let netlist = write_temp_file(
+        "helm_always_mux.v",
+        "module select(s, a, b, y);\n\
+         input s, a, b;\n\
+         output reg y;\n\
+         always @(*) if (s) y = a; else y = b;\n\
+         endmodule\n",
+    );
+    let (gates, _, _, outputs, _, _, _) = read_verilog_file(&netlist, false);
+    assert_eq!(outputs, vec!["y".to_string()]);
+    assert_eq!(gates.len(), 1);
+    let mux = gates.iter().next().unwrap();
+    assert_eq!(mux.get_gate_type(), GateType::Mux);
+    assert_eq!(mux.get_input_wires(), &vec!["a", "b", "s"]);
+    assert_eq!(mux.get_output_wire(), "y");
+
+    // A default value followed by nested ifs over several lines.
+    let netlist = write_temp_file(
+        "helm_always_priority.v",
+        "module priority(s, t, a, b, c, y);\n\
+         input s, t, a, b, c;\n\
+         output y;\n\
+         reg y;\n\
+         always @* begin\n\
+           y = c;\n\
+           if (s)\n\
+             y = a;\n\
+           else if (t) begin\n\
+             y = b;\n\
+           end\n\
+         end\n\
+         and g0(a, b, z);\n\
+         endmodule\n",
+    );
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    for vector in 0..32 {
+        let bit = |i: usize| vector >> i & 1 == 1;
+        let user_inputs = ["s", "t", "a", "b", "c"]
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| (wire.to_string(), PtxtType::Bool(bit(i))))
+            .collect::<HashMap<_, _>>();
+        let expected = if bit(0) {
+            bit(2)
+        } else if bit(1) {
+            bit(3)
+        } else {
+            bit(4)
+        };
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(wire_map["y"], PtxtType::Bool(expected));
+    }
+}
+
+#[test]
+fn unknown_literal_bits_evaluate_to_x() {
+    let netlist = write_temp_file(
+        "helm_unknown_literal.v",
+        "module unknown(a, y, z);\n\
+         input a;\n\
+         output [1:0] y;\n\
+         output z;\n\
+         wire [3:0] k;\n\
+         assign k = 4'b1x0z;\n\
+         and g0(a, k[2], y[0]);\n\
+         or g1(a, k[2], y[1]);\n\
+         xor g2(k[3], k[1], z);\n\
+         endmodule\n",
+    );
+
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, false);
+    let constant = |wire: &str| {
+        gates
+            .iter()
+            .find(|gate| gate.get_output_wire() == wire)
+            .unwrap()
+            .get_gate_type()
+    };
+    assert_eq!(constant("k[0]"), GateType::ConstUnknown);
+    assert_eq!(constant("k[1]"), GateType::ConstZero);
+    assert_eq!(constant("k[2]"), GateType::ConstUnknown);
+    assert_eq!(constant("k[3]"), GateType::ConstOne);
+
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    for (a, y0, y1) in [
+        (false, PtxtType::Bool(false), PtxtType::Unknown),
+        (true, PtxtType::Unknown, PtxtType::Bool(true)),
+    ] {
+        let user_inputs = HashMap::from([("a".to_string(), PtxtType::Bool(a))]);
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "bool");
+        let wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(wire_map["y[0]"], y0);
+        assert_eq!(wire_map["y[1]"], y1);
+        assert_eq!(wire_map["z"], PtxtType::Bool(true));
+    }
+}
+
+#[test]
+fn binary_inputs_round_trip_like_csv() {
+    let csv = write_temp_file(
+        "helm_binary_inputs.csv",
+        "wire, value\n\
+         pixel_0, 17\n\
+         pixel_1, 65535\n\
+         weight, 300\n",
+    );
+    let from_csv = read_input_wires(&csv, "u16");
+    let binary = common::temp_path("helm_binary_inputs.bin");
+    let binary = binary.to_str().unwrap();
+    write_binary_wires(binary, &from_csv);
+    assert_eq!(read_binary_wires(binary, "u16"), from_csv);
+
+    // Every width keeps its value, and booleans take one byte.
+    let wide = HashMap::from([("w".to_string(), PtxtType::U256(U256::new(3, u128::MAX)))]);
+    write_binary_wires(binary, &wide);
+    assert_eq!(read_binary_wires(binary, "u256"), wide);
+    let bits = HashMap::from([
+        ("a[0]".to_string(), PtxtType::Bool(true)),
+        ("a[1]".to_string(), PtxtType::Bool(false)),
+    ]);
+    write_binary_wires(binary, &bits);
+    assert_eq!(
+        std::fs::metadata(binary).unwrap().len(),
+        5 + 2 * (4 + 2 + 4 + 1 + 1)
+    );
+    assert_eq!(read_binary_wires(binary, "bool"), bits);
+}
+
+#[test]
+fn parse_arithmetic_muxn_cell() {
+    let netlist = write_temp_file(
+        "helm_muxn_arith.v",
+        "module select(s, d0, d1, d2, d3, y);\n\
+         input s, d0, d1, d2, d3;\n\
+         output y;\n\
+         muxn g0(4, s, d0, d1, d2, d3, y);\n\
+         endmodule\n",
+    );
+
+    let (gates, wire_set, inputs, outputs, dff_outputs, _, _) = read_verilog_file(&netlist, true);
+    let mut circuit = Circuit::new(gates, &inputs, &outputs, &dff_outputs);
+    circuit.sort_circuit();
+    circuit.compute_levels();
+    for index in 0..4 {
+        let mut user_inputs = HashMap::from([("s".to_string(), PtxtType::U16(index))]);
+        for i in 0..4 {
+            user_inputs.insert(format!("d{}", i), PtxtType::U16(100 + i));
+        }
+        let wire_map = circuit.initialize_wire_map(&wire_set, &user_inputs, "u16");
+        let wire_map = circuit.evaluate(&wire_map);
+        assert_eq!(wire_map["y"], PtxtType::U16(100 + index));
+    }
+}
+
+#[test]
+fn check_reports_position_of_lint_errors() {
+    let netlist = write_temp_file(
+        "helm_lint_pins.v",
+        "module m(\n\
+           a, b, y\n\
+         );\n\
+         input a, b;\n\
+         output y;\n\
+         always @(*) begin\n\
+           t = a;\n\
+         end\n\
+         and g0(a, b, t, y);\n\
+         endmodule\n",
+    );
+    match check_verilog_file(&netlist, false) {
+        Err(VerilogParseError::Syntax { line, column, .. }) => assert_eq!((line, column), (9, 5)),
+        result => panic!("Expected a syntax error, got {:?}", result),
+    }
+
+    let netlist = write_temp_file(
+        "helm_lint_range.v",
+        "module m(a, y);\n\
+         input [x:0] a;\n\
+         output y;\n\
+         buf g0(a[0], y);\n\
+         endmodule\n",
+    );
+    match check_verilog_file(&netlist, false) {
+        Err(VerilogParseError::Syntax { line, column, .. }) => assert_eq!((line, column), (2, 7)),
+        result => panic!("Expected a syntax error, got {:?}", result),
+    }
+
+    let netlist = write_temp_file(
+        "helm_lint_empty.v",
+        "module m(a, y);\n\
+         input a;\n\
+         output y;\n\
+         endmodule\n",
+    );
+    assert!(matches!(
+        check_verilog_file(&netlist, false),
+        Err(VerilogParseError::Netlist { .. })
+    ));
+    assert!(matches!(
+        check_verilog_file("helm_missing_netlist.v", false),
+        Err(VerilogParseError::Io { .. })
+    ));
+}
+
+/// The parser rejects a cell with the message --check-only gives for it.
+#[test]
+#[should_panic(expected = "Cell \"arb\" should have the pins (N, r0..rN-1, g0..gN-1)")]
+fn parser_shares_cell_rules_with_check() {
+    let netlist = write_temp_file(
+        "helm_parse_penc_pins.v",
+        "module m(r0, r1, g0, g1, g2);\n\
+         input r0, r1;\n\
+         output g0, g1, g2;\n\
+         penc arb(2, r0, r1, g0, g1, g2);\n\
+         endmodule\n",
+    );
+    let message = match check_verilog_file(&netlist, false) {
+        Err(VerilogParseError::Syntax { message, .. }) => message,
+        result => panic!("Expected a syntax error, got {:?}", result),
+    };
+    assert!(message.starts_with("Cell \"arb\""), "{}", message);
+    read_verilog_file(&netlist, false);
+}
+
+#[test]
+fn check_reports_invalid_module_attributes() {
+    for (file_name, attribute) in [
+        ("helm_lint_arith_attribute.v", "helm_arith = \"u7\""),
+        ("helm_lint_cycles_attribute.v", "helm_cycles = \"many\""),
+    ] {
+        let netlist = write_temp_file(
+            file_name,
+            &format!(
+                "(* {} *)\n\
+                 module m(a, y);\n\
+                 input a;\n\
+                 output y;\n\
+                 buf g0(a, y);\n\
+                 endmodule\n",
+                attribute
+            ),
+        );
+        match check_verilog_file(&netlist, false) {
+            Err(VerilogParseError::Netlist { message, .. }) => {
+                assert!(message.starts_with("Invalid"), "{}", message)
+            }
+            result => panic!("Expected a netlist error, got {:?}", result),
+        }
+    }
+}